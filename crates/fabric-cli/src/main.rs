@@ -1,10 +1,32 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use chrono::{DateTime, Utc};
 use colored::Colorize;
-use fabric_core::crypto::{CryptoManager, FabricIdentity};
-use fabric_core::fabric::{FabricNetworkConfig, KaleidoFabricClient, FabricNetworkClient};
+use dialoguer::Password;
+use fabric_core::backup::BackupShare;
+use fabric_core::ca::{
+    EnrollmentRequest, FabricCaClient, RegistrationAttribute, RegistrationRequest,
+    RevocationRequest,
+};
+use fabric_core::crypto::{CryptoManager, EcCurve, FabricIdentity};
+use fabric_core::fabric::{
+    ChaincodeArgs, FabricNetworkConfig, KaleidoFabricClient, FabricNetworkClient,
+};
+use fabric_core::history::HistoryQuery;
+use fabric_core::keystore::{migrate_identity_file, write_atomic, IdentityFileFormat};
+use fabric_core::tls::{TlsClientAuth, TlsConfig, TlsVersion};
 use fabric_core::torrent::{TorrentHash, HashType, WebTorrentClient};
-use std::path::PathBuf;
-use tracing::{info, error, debug};
+use fabric_core::wallet::{FileSystemWallet, Wallet};
+use prettytable::{row, Table};
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the identity passphrase for scripted use
+const PASSPHRASE_ENV: &str = "FABRIC_IDENTITY_PASSPHRASE";
+
+/// Environment variable holding the enrollment secret for scripted use
+const SECRET_ENV: &str = "FABRIC_ENROLLMENT_SECRET";
+
+/// Arguments that each provide a complete identity to `key import`
+const IMPORT_SOURCES: [&str; 4] = ["msp_dir", "p12", "jwk", "pem_bundle"];
 
 #[derive(Parser)]
 #[command(name = "fabric")]
@@ -14,6 +36,15 @@ struct Cli {
     #[arg(short, long, global = true)]
     verbose: bool,
 
+    /// Wallet directory (defaults to $FABRIC_WALLET or ~/.fabric/wallet)
+    #[arg(long, global = true)]
+    wallet: Option<PathBuf>,
+
+    /// Connect with an MSP ID that doesn't follow the certificate's
+    /// organization (e.g. fabric-ca's O=Hyperledger)
+    #[arg(long, global = true)]
+    allow_mspid_mismatch: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -52,34 +83,224 @@ enum KeyCommands {
         /// Output path for identity file
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// Elliptic curve (p256 or p384)
+        #[arg(long, default_value = "p256")]
+        curve: EcCurve,
+        /// Include the private key when printing the identity
+        #[arg(long, conflicts_with = "output")]
+        show_private_key: bool,
     },
 
-    /// Import an existing certificate
+    /// Import a certificate, Fabric MSP directory, PKCS#12 archive, JWK or PEM bundle
     Import {
         /// Path to certificate file
-        #[arg(short, long)]
-        cert: PathBuf,
+        #[arg(short, long, required_unless_present_any = IMPORT_SOURCES)]
+        cert: Option<PathBuf>,
+        /// Path to the matching PKCS#8 private key
+        #[arg(short, long, required_unless_present_any = IMPORT_SOURCES)]
+        key: Option<PathBuf>,
+        /// Path to the issuing CA certificate chain
+        #[arg(long)]
+        ca_cert: Option<PathBuf>,
+        /// Fabric MSP directory (signcerts/, keystore/, cacerts/, config.yaml)
+        #[arg(short, long, conflicts_with_all = ["cert", "key", "ca_cert"])]
+        msp_dir: Option<PathBuf>,
+        /// Password-protected PKCS#12 archive with key, certificate and CA chain
+        #[arg(long, conflicts_with_all = ["cert", "key", "ca_cert", "msp_dir"])]
+        p12: Option<PathBuf>,
+        /// Private EC JWK with the certificate chain in x5c
+        #[arg(long, conflicts_with_all = ["cert", "key", "ca_cert", "msp_dir", "p12"])]
+        jwk: Option<PathBuf>,
+        /// PEM file with the private key, certificate and CA chain
+        #[arg(long, conflicts_with_all = ["cert", "key", "ca_cert", "msp_dir", "p12", "jwk"])]
+        pem_bundle: Option<PathBuf>,
+        /// MSP ID (defaults to config.yaml for MSP directories, else <org>MSP)
+        #[arg(long)]
+        mspid: Option<String>,
         /// User ID/enrollment ID
-        #[arg(short, long)]
-        user_id: String,
+        #[arg(short, long, required_unless_present_any = IMPORT_SOURCES)]
+        user_id: Option<String>,
         /// Organization name
+        #[arg(short, long, required_unless_present_any = IMPORT_SOURCES)]
+        org: Option<String>,
+        /// Store in the wallet under this label
         #[arg(short, long)]
-        org: String,
+        label: Option<String>,
+        /// Store as an unencrypted Fabric SDK wallet entry
+        #[arg(long)]
+        plaintext: bool,
+        /// Include the private key when printing the identity
+        #[arg(long, conflicts_with = "label")]
+        show_private_key: bool,
     },
 
-    /// List available identities
-    List {
-        /// Directory containing identity files
+    /// Export an identity for other Fabric tooling
+    Export {
+        /// Wallet label of the identity
+        #[arg(short, long)]
+        identity: String,
+        /// Export format
+        #[arg(short, long, value_enum)]
+        format: ExportFormat,
+        /// Output path
         #[arg(short, long)]
-        dir: Option<PathBuf>,
+        output: PathBuf,
+        /// Leave the private key of a PEM bundle unencrypted
+        #[arg(long)]
+        plaintext: bool,
     },
 
+    /// List identities in the wallet
+    List,
+
     /// Show identity details
     Show {
+        /// Wallet label of the identity
+        #[arg(short, long)]
+        identity: String,
+        /// Print the parsed certificate as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Split an identity's private key into Shamir shares
+    Backup {
+        /// Wallet label of the identity
+        #[arg(short, long)]
+        identity: String,
+        /// Number of shares to create
+        #[arg(short, long, default_value_t = 5)]
+        shares: u8,
+        /// Number of shares needed to restore the key
+        #[arg(short, long, default_value_t = 3)]
+        threshold: u8,
+    },
+
+    /// Restore an identity from Shamir shares and its certificate
+    Restore {
+        /// A backup share; repeat for each share, or pass none to read them from stdin
+        #[arg(short, long = "share")]
+        shares: Vec<String>,
+        /// Path to the identity's certificate
+        #[arg(short, long)]
+        cert: PathBuf,
+        /// Path to the issuing CA certificate chain
+        #[arg(long)]
+        ca_cert: Option<PathBuf>,
+        /// MSP ID (defaults to <org>MSP)
+        #[arg(long)]
+        mspid: Option<String>,
+        /// Store in the wallet under this label
+        #[arg(short, long)]
+        label: String,
+        /// Store as an unencrypted Fabric SDK wallet entry
+        #[arg(long)]
+        plaintext: bool,
+    },
+
+    /// Encrypt a plaintext identity file in place
+    Migrate {
         /// Path to identity file
         #[arg(short, long)]
         identity: PathBuf,
     },
+
+    /// Enroll with a Fabric CA using an enrollment secret
+    ///
+    /// The secret is read from $FABRIC_ENROLLMENT_SECRET or a prompt.
+    Enroll {
+        /// Fabric CA URL
+        #[arg(long)]
+        ca: String,
+        /// CA name on a multi-CA server
+        #[arg(long)]
+        ca_name: Option<String>,
+        /// Enrollment ID
+        #[arg(short, long)]
+        user_id: String,
+        /// Organization name
+        #[arg(long)]
+        org: String,
+        /// MSP ID (defaults to <org>MSP)
+        #[arg(long)]
+        mspid: Option<String>,
+        /// Wallet label (defaults to the enrollment ID)
+        #[arg(short, long)]
+        label: Option<String>,
+        /// Store as an unencrypted Fabric SDK wallet entry
+        #[arg(long)]
+        plaintext: bool,
+    },
+
+    /// Register a new identity with a Fabric CA
+    Register {
+        /// Fabric CA URL
+        #[arg(long)]
+        ca: String,
+        /// CA name on a multi-CA server
+        #[arg(long)]
+        ca_name: Option<String>,
+        /// Wallet label of the registrar identity
+        #[arg(short, long)]
+        registrar: String,
+        /// Enrollment ID to register
+        #[arg(short, long)]
+        user_id: String,
+        /// Affiliation (e.g. org1.department1)
+        #[arg(short, long)]
+        affiliation: String,
+        /// Identity type (client, peer, orderer, admin)
+        #[arg(short = 't', long, default_value = "client")]
+        identity_type: String,
+        /// Set the enrollment secret from $FABRIC_ENROLLMENT_SECRET or a
+        /// prompt instead of having the CA generate one
+        #[arg(short, long)]
+        secret: bool,
+        /// Attributes as name=value, may be repeated
+        #[arg(long = "attr")]
+        attrs: Vec<String>,
+    },
+
+    /// Revoke an identity or certificate with a Fabric CA
+    Revoke {
+        /// Fabric CA URL
+        #[arg(long)]
+        ca: String,
+        /// CA name on a multi-CA server
+        #[arg(long)]
+        ca_name: Option<String>,
+        /// Wallet label of the registrar identity
+        #[arg(short, long)]
+        registrar: String,
+        /// Enrollment ID whose certificates are revoked
+        #[arg(short, long)]
+        user_id: Option<String>,
+        /// Hex serial of a single certificate to revoke
+        #[arg(long)]
+        serial: Option<String>,
+        /// Hex authority key identifier of that certificate
+        #[arg(long)]
+        aki: Option<String>,
+        /// Revocation reason
+        #[arg(long)]
+        reason: Option<String>,
+        /// Request an updated CRL and write it to this path
+        #[arg(long)]
+        crl_output: Option<PathBuf>,
+    },
+}
+
+/// Identity export formats
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// Fabric MSP directory
+    Msp,
+    /// Password-protected PKCS#12 archive
+    P12,
+    /// Private EC JWK with the certificate chain in x5c
+    Jwk,
+    /// PEM private key, certificate and CA chain
+    PemBundle,
 }
 
 #[derive(Subcommand)]
@@ -92,9 +313,11 @@ enum NetworkCommands {
         /// CA URL
         #[arg(short, long)]
         ca: String,
-        /// Identity file path
+        /// Wallet label of the identity
         #[arg(short, long)]
-        identity: PathBuf,
+        identity: String,
+        #[command(flatten)]
+        tls: TlsArgs,
     },
 
     /// List all channels in the network
@@ -102,9 +325,14 @@ enum NetworkCommands {
         /// Kaleido gateway URL
         #[arg(short, long)]
         gateway: String,
-        /// Identity file path
+        /// Wallet label of the identity
         #[arg(short, long)]
-        identity: PathBuf,
+        identity: String,
+        /// List the built-in channels if discovery fails
+        #[arg(long)]
+        offline_fallback: bool,
+        #[command(flatten)]
+        tls: TlsArgs,
     },
 
     /// Get network information
@@ -115,7 +343,55 @@ enum NetworkCommands {
     },
 }
 
+/// TLS options for gateway connections
+#[derive(Args)]
+struct TlsArgs {
+    /// PEM bundle of CAs trusted for the gateway instead of the system roots
+    #[arg(long)]
+    tls_ca: Option<PathBuf>,
+    /// Present the identity's certificate for mutual TLS
+    #[arg(long, conflicts_with = "tls_client_cert")]
+    mtls: bool,
+    /// Client certificate for mutual TLS
+    #[arg(long, requires = "tls_client_key")]
+    tls_client_cert: Option<PathBuf>,
+    /// Private key of the client certificate
+    #[arg(long, requires = "tls_client_cert")]
+    tls_client_key: Option<PathBuf>,
+    /// Accepted gateway public key pin (sha256/<base64>), repeatable
+    #[arg(long = "pin")]
+    pins: Vec<String>,
+    /// Require TLS 1.3
+    #[arg(long)]
+    tls13: bool,
+}
+
+impl TlsArgs {
+    /// CA bundle path and TLS settings for a `FabricNetworkConfig`
+    fn config(&self) -> (Option<String>, TlsConfig) {
+        let client_auth = match (&self.tls_client_cert, &self.tls_client_key) {
+            (Some(cert), Some(key)) => TlsClientAuth::Files {
+                cert_path: cert.display().to_string(),
+                key_path: key.display().to_string(),
+            },
+            _ if self.mtls => TlsClientAuth::Identity,
+            _ => TlsClientAuth::None,
+        };
+        let tls = TlsConfig {
+            client_auth,
+            spki_pins: self.pins.clone(),
+            min_version: if self.tls13 {
+                TlsVersion::Tls13
+            } else {
+                TlsVersion::Tls12
+            },
+        };
+        (self.tls_ca.as_ref().map(|p| p.display().to_string()), tls)
+    }
+}
+
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum ChaincodeCommands {
     /// Query chaincode
     Query {
@@ -123,14 +399,26 @@ enum ChaincodeCommands {
         #[arg(short, long)]
         channel: String,
         /// Chaincode ID
-        #[arg(short, long)]
+        #[arg(short = 'n', long)]
         chaincode: String,
         /// Function name
         #[arg(short, long)]
         function: String,
-        /// Arguments (JSON format)
+        /// Arguments as JSON: an array holds one value per argument;
+        /// strings are passed as is, other values as JSON
         #[arg(short, long)]
         args: Option<String>,
+        /// Kaleido gateway URL
+        #[arg(short, long)]
+        gateway: String,
+        /// Wallet label of the identity
+        #[arg(short, long)]
+        identity: String,
+        /// Print the result payload as JSON
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        tls: TlsArgs,
     },
 
     /// Invoke chaincode (submit transaction)
@@ -139,14 +427,68 @@ enum ChaincodeCommands {
         #[arg(short, long)]
         channel: String,
         /// Chaincode ID
-        #[arg(short, long)]
+        #[arg(short = 'n', long)]
         chaincode: String,
         /// Function name
         #[arg(short, long)]
         function: String,
-        /// Arguments (JSON format)
+        /// Arguments as JSON: an array holds one value per argument;
+        /// strings are passed as is, other values as JSON
         #[arg(short, long)]
         args: Option<String>,
+        /// Kaleido gateway URL
+        #[arg(short, long)]
+        gateway: String,
+        /// Wallet label of the identity
+        #[arg(short, long)]
+        identity: String,
+        /// Print the transaction result as JSON
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        tls: TlsArgs,
+    },
+
+    /// Show transaction history, newest first
+    History {
+        /// Channel ID
+        #[arg(short, long)]
+        channel: String,
+        /// Chaincode ID
+        #[arg(short = 'n', long)]
+        chaincode: String,
+        /// Kaleido gateway URL
+        #[arg(short, long)]
+        gateway: String,
+        /// Wallet label of the identity
+        #[arg(short, long)]
+        identity: String,
+        /// Only transactions calling this function
+        #[arg(short, long)]
+        function: Option<String>,
+        /// Only transactions submitted by this MSP
+        #[arg(long)]
+        mspid: Option<String>,
+        /// Earliest transaction time (RFC 3339)
+        #[arg(long)]
+        from: Option<DateTime<Utc>>,
+        /// Latest transaction time (RFC 3339)
+        #[arg(long)]
+        to: Option<DateTime<Utc>>,
+        /// Transactions per page
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+        /// Blocks read per page
+        #[arg(long)]
+        max_blocks: Option<u64>,
+        /// Cursor printed with the previous page
+        #[arg(long)]
+        cursor: Option<String>,
+        /// Print the page as JSON
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        tls: TlsArgs,
     },
 }
 
@@ -198,22 +540,30 @@ enum TorrentCommands {
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
-    // Setup logging
+    // Setup logging; stdout is left to command output such as --json
     if cli.verbose {
         tracing_subscriber::fmt()
             .with_max_level(tracing::Level::DEBUG)
+            .with_writer(std::io::stderr)
             .init();
     } else {
         tracing_subscriber::fmt()
             .with_max_level(tracing::Level::INFO)
+            .with_writer(std::io::stderr)
             .init();
     }
 
+    let wallet_dir = cli.wallet.unwrap_or_else(FileSystemWallet::default_dir);
+
     match cli.command {
-        Commands::Key { command } => handle_key_commands(command).await?,
-        Commands::Network { command } => handle_network_commands(command).await?,
+        Commands::Key { command } => {
+            handle_key_commands(command, &wallet_dir).await?
+        }
+        Commands::Network { command } => {
+            handle_network_commands(command, &wallet_dir, cli.allow_mspid_mismatch).await?
+        }
         Commands::Chaincode { command } => {
-            handle_chaincode_commands(command).await?
+            handle_chaincode_commands(command, &wallet_dir, cli.allow_mspid_mismatch).await?
         }
         Commands::Torrent { command } => {
             handle_torrent_commands(command).await?
@@ -223,12 +573,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn handle_key_commands(cmd: KeyCommands) -> Result<(), Box<dyn std::error::Error>> {
+async fn handle_key_commands(
+    cmd: KeyCommands,
+    wallet_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
     match cmd {
-        KeyCommands::Generate { output } => {
-            println!("{}", "Generating new keypair...".bold().green());
+        KeyCommands::Generate {
+            output,
+            curve,
+            show_private_key,
+        } => {
+            println!(
+                "{}",
+                format!("Generating new {} keypair...", curve).bold().green()
+            );
             let (private, public) =
-                CryptoManager::generate_keypair()?;
+                CryptoManager::generate_keypair_with_curve(curve)?;
 
             let identity = FabricIdentity::new(
                 private,
@@ -241,35 +601,81 @@ async fn handle_key_commands(cmd: KeyCommands) -> Result<(), Box<dyn std::error:
             );
 
             if let Some(path) = output {
-                identity.save_to_file(&path)?;
+                save_identity(&identity, &path)?;
                 println!(
                     "{}",
                     format!("Identity saved to: {:?}", path)
                         .green()
                 );
             } else {
-                println!("{}", identity.to_json()?);
+                print_identity(&identity, show_private_key)?;
             }
         }
 
         KeyCommands::Import {
             cert,
+            key,
+            ca_cert,
+            msp_dir,
+            p12,
+            jwk,
+            pem_bundle,
+            mspid,
             user_id,
             org,
+            label,
+            plaintext,
+            show_private_key,
         } => {
-            println!("{}", "Importing certificate...".bold().green());
-            let cert_content =
-                CryptoManager::import_certificate_from_pem(&cert)?;
+            let identity = if let Some(msp_dir) = msp_dir {
+                println!("{}", "Importing MSP directory...".bold().green());
+                FabricIdentity::from_msp_dir(&msp_dir, mspid.as_deref())?
+            } else if let Some(path) = p12 {
+                println!("{}", "Importing PKCS#12 archive...".bold().green());
+                let der = std::fs::read(&path)?;
+                let password = passphrase_for(&path.display().to_string(), false)?;
+                FabricIdentity::from_pkcs12(&der, &password, mspid.as_deref())?
+            } else if let Some(path) = jwk {
+                println!("{}", "Importing JWK...".bold().green());
+                let json = std::fs::read_to_string(path)?;
+                FabricIdentity::from_jwk(&json, mspid.as_deref())?
+            } else if let Some(path) = pem_bundle {
+                println!("{}", "Importing PEM bundle...".bold().green());
+                let pem = std::fs::read_to_string(&path)?;
+                let passphrase = if pem.contains("ENCRYPTED PRIVATE KEY") {
+                    Some(passphrase_for(&path.display().to_string(), false)?)
+                } else {
+                    None
+                };
+                FabricIdentity::from_pem_bundle(&pem, passphrase.as_deref(), mspid.as_deref())?
+            } else {
+                println!("{}", "Importing certificate...".bold().green());
+                let (Some(cert), Some(key), Some(user_id), Some(org)) =
+                    (cert, key, user_id, org)
+                else {
+                    return Err("--cert, --key, --user-id and --org are required".into());
+                };
+                let cert_content =
+                    CryptoManager::import_certificate_from_pem(&cert)?;
+                let private_key = std::fs::read_to_string(key)?;
+                let public_key = CryptoManager::public_key_from_private(&private_key)?;
+                let ca_certificate = match ca_cert {
+                    Some(path) => CryptoManager::import_certificate_from_pem(&path)?,
+                    None => String::new(),
+                };
 
-            let identity = FabricIdentity::new(
-                "PRIVATE_KEY_PLACEHOLDER".to_string(),
-                "PUBLIC_KEY_PLACEHOLDER".to_string(),
-                cert_content,
-                "CA_CERT_PLACEHOLDER".to_string(),
-                user_id,
-                org.clone(),
-                format!("{}MSP", org),
-            );
+                FabricIdentity::new(
+                    private_key,
+                    public_key,
+                    cert_content,
+                    ca_certificate,
+                    user_id,
+                    org.clone(),
+                    mspid.unwrap_or_else(|| format!("{}MSP", org)),
+                )
+            };
+            // Checked when connecting, see --allow-mspid-mismatch
+            identity.validate_allowing_mspid_mismatch()?;
 
             println!(
                 "{}",
@@ -279,52 +685,450 @@ async fn handle_key_commands(cmd: KeyCommands) -> Result<(), Box<dyn std::error:
                 )
                 .green()
             );
-            println!("{}", serde_json::to_string_pretty(&identity)?);
+            if let Some(label) = label {
+                store_identity(wallet_dir, &label, &identity, plaintext)?;
+                println!(
+                    "{}",
+                    format!("Identity stored in wallet as: {}", label).green()
+                );
+            } else {
+                print_identity(&identity, show_private_key)?;
+            }
         }
 
-        KeyCommands::List { dir } => {
-            println!("{}", "Available identities:".bold().cyan());
-            if let Some(dir) = dir {
-                if dir.exists() {
-                    for entry in std::fs::read_dir(dir)? {
-                        let entry = entry?;
-                        let path = entry.path();
-                        if path.extension().map_or(false, |ext| {
-                            ext == "json"
-                        }) {
-                            println!(
-                                "  {}",
-                                path.file_name()
-                                    .unwrap_or_default()
-                                    .to_string_lossy()
-                            );
-                        }
-                    }
+        KeyCommands::Export {
+            identity,
+            format,
+            output,
+            plaintext,
+        } => {
+            if plaintext && !matches!(format, ExportFormat::PemBundle) {
+                return Err("--plaintext only applies to pem-bundle exports".into());
+            }
+            let identity = load_identity(wallet_dir, &identity)?;
+            let target = output.display().to_string();
+            match format {
+                ExportFormat::Msp => identity.export_msp_dir(&output)?,
+                ExportFormat::P12 => {
+                    let password = passphrase_for(&target, true)?;
+                    write_atomic(&output, &identity.to_pkcs12(&password)?)?;
+                }
+                ExportFormat::Jwk => {
+                    println!(
+                        "{}",
+                        "Warning: JWK files hold the private key unencrypted".yellow()
+                    );
+                    write_atomic(&output, identity.to_jwk()?.as_bytes())?;
+                }
+                ExportFormat::PemBundle => {
+                    let passphrase = if plaintext {
+                        None
+                    } else {
+                        Some(passphrase_for(&target, true)?)
+                    };
+                    let bundle = identity.to_pem_bundle(passphrase.as_deref())?;
+                    write_atomic(&output, bundle.as_bytes())?;
                 }
             }
+            println!(
+                "{}",
+                format!("Identity exported to: {:?}", output).green()
+            );
         }
 
-        KeyCommands::Show { identity } => {
+        KeyCommands::List => {
             println!(
                 "{}",
-                format!("Identity: {:?}", identity).bold().cyan()
+                format!("Identities in {:?}:", wallet_dir).bold().cyan()
             );
-            if let Ok(content) = std::fs::read_to_string(&identity) {
-                println!("{}", content);
+            let wallet = FileSystemWallet::new(wallet_dir)?;
+            for label in wallet.list()? {
+                if wallet.is_encrypted(&label)? {
+                    println!("  {} {}", label, "(encrypted)".yellow());
+                } else {
+                    let identity = wallet.require(&label)?;
+                    println!(
+                        "  {} - {}@{}",
+                        label, identity.user_id, identity.mspid
+                    );
+                }
+            }
+        }
+
+        KeyCommands::Show { identity: label, json } => {
+            let identity = load_identity(wallet_dir, &label)?;
+            let info = identity.certificate_info()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&info)?);
+                return Ok(());
+            }
+
+            println!("{}", format!("Identity: {}", label).bold().cyan());
+            let mut table = Table::new();
+            table.add_row(row!["MSP ID", identity.mspid]);
+            table.add_row(row!["Enrollment ID", info.enrollment_id().unwrap_or("-")]);
+            table.add_row(row!["Subject", info.subject]);
+            table.add_row(row!["Issuer", info.issuer]);
+            table.add_row(row!["Serial", info.serial]);
+            table.add_row(row!["Not Before", info.not_before.to_rfc3339()]);
+            table.add_row(row!["Not After", info.not_after.to_rfc3339()]);
+            table.add_row(row!["Key Algorithm", info.key_algorithm]);
+            table.add_row(row![
+                "NodeOU Role",
+                info.node_ou_role.as_deref().unwrap_or("-")
+            ]);
+            if !info.subject_alt_names.is_empty() {
+                table.add_row(row!["SANs", info.subject_alt_names.join("\n")]);
+            }
+            for (name, value) in &info.attributes {
+                table.add_row(row![format!("Attr {}", name), value]);
+            }
+            table.printstd();
+        }
+
+        KeyCommands::Backup {
+            identity,
+            shares,
+            threshold,
+        } => {
+            let label = identity;
+            let identity = load_identity(wallet_dir, &label)?;
+            let backup = identity.split_private_key(threshold, shares)?;
+            println!(
+                "{}",
+                format!(
+                    "Private key of {} split into {} shares; any {} restore it:",
+                    label, shares, threshold
+                )
+                .bold()
+                .green()
+            );
+            for share in &backup {
+                println!("\n  Share {}/{}:\n  {}", share.index, shares, share);
+            }
+            println!(
+                "\n{}",
+                "Give each share to a different custodian. The certificate is not part of \
+                 the shares; keep it with `fabric key export` to restore."
+                    .yellow()
+            );
+        }
+
+        KeyCommands::Restore {
+            shares,
+            cert,
+            ca_cert,
+            mspid,
+            label,
+            plaintext,
+        } => {
+            let shares = if shares.is_empty() {
+                println!("Enter one share per line, then an empty line:");
+                std::io::stdin()
+                    .lines()
+                    .map_while(|line| line.ok())
+                    .take_while(|line| !line.trim().is_empty())
+                    .collect()
+            } else {
+                shares
+            };
+            let shares = shares
+                .iter()
+                .map(|share| share.parse::<BackupShare>())
+                .collect::<Result<Vec<_>, _>>()?;
+            let certificate = CryptoManager::import_certificate_from_pem(&cert)?;
+            let ca_certificate = match ca_cert {
+                Some(path) => CryptoManager::import_certificate_from_pem(&path)?,
+                None => String::new(),
+            };
+
+            let identity = FabricIdentity::restore_from_shares(
+                &shares,
+                &certificate,
+                &ca_certificate,
+                mspid.as_deref(),
+            )?;
+            store_identity(wallet_dir, &label, &identity, plaintext)?;
+            println!(
+                "{}",
+                format!(
+                    "Restored {} from {} shares into wallet as: {}",
+                    identity.user_id,
+                    shares.len(),
+                    label
+                )
+                .green()
+            );
+        }
+
+        KeyCommands::Migrate { identity } => {
+            if IdentityFileFormat::detect_file(&identity)?
+                == IdentityFileFormat::Encrypted
+            {
+                println!(
+                    "{}",
+                    format!("Identity already encrypted: {:?}", identity).yellow()
+                );
+            } else {
+                let passphrase =
+                    passphrase_for(&identity.display().to_string(), true)?;
+                migrate_identity_file(&identity, &passphrase)?;
+                println!(
+                    "{}",
+                    format!("Identity encrypted: {:?}", identity).green()
+                );
+            }
+        }
+
+        KeyCommands::Enroll {
+            ca,
+            ca_name,
+            user_id,
+            org,
+            mspid,
+            label,
+            plaintext,
+        } => {
+            let secret = secret_for(&user_id, false)?;
+            println!(
+                "{}",
+                format!("Enrolling {} with {}...", user_id, ca).bold().green()
+            );
+            let client = ca_client(&ca, ca_name);
+            let mspid = mspid.unwrap_or_else(|| format!("{}MSP", org));
+            let identity = client
+                .enroll(&EnrollmentRequest::new(&user_id, &secret, &mspid, &org))
+                .await?;
+
+            let label = label.unwrap_or(user_id);
+            store_identity(wallet_dir, &label, &identity, plaintext)?;
+            println!(
+                "{}",
+                format!("Identity stored in wallet as: {}", label).green()
+            );
+        }
+
+        KeyCommands::Register {
+            ca,
+            ca_name,
+            registrar,
+            user_id,
+            affiliation,
+            identity_type,
+            secret,
+            attrs,
+        } => {
+            println!(
+                "{}",
+                format!("Registering {} with {}...", user_id, ca).bold().green()
+            );
+            let registrar = load_identity(wallet_dir, &registrar)?;
+            let mut request = RegistrationRequest::new(&user_id, &affiliation);
+            request.identity_type = identity_type;
+            if secret {
+                request.secret = Some(secret_for(&user_id, true)?);
+            }
+            for attr in attrs {
+                let (name, value) = attr
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid attribute: {}", attr))?;
+                request.attrs.push(RegistrationAttribute {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    ecert: true,
+                });
+            }
+
+            let secret = ca_client(&ca, ca_name)
+                .register(&registrar, &request)
+                .await?;
+            println!("{}", format!("Registered: {}", user_id).green());
+            println!("Enrollment secret: {}", secret.yellow());
+        }
+
+        KeyCommands::Revoke {
+            ca,
+            ca_name,
+            registrar,
+            user_id,
+            serial,
+            aki,
+            reason,
+            crl_output,
+        } => {
+            println!("{}", format!("Revoking with {}...", ca).bold().green());
+            let registrar = load_identity(wallet_dir, &registrar)?;
+            let request = RevocationRequest {
+                enrollment_id: user_id,
+                serial,
+                aki,
+                reason,
+                gen_crl: crl_output.is_some(),
+            };
+
+            let result = ca_client(&ca, ca_name)
+                .revoke(&registrar, &request)
+                .await?;
+            for revoked in &result.revoked {
+                println!(
+                    "  Revoked serial={} aki={}",
+                    revoked.serial, revoked.aki
+                );
+            }
+            if let (Some(path), Some(crl)) = (crl_output, result.crl) {
+                std::fs::write(&path, crl)?;
+                println!("{}", format!("CRL saved to: {:?}", path).green());
             }
         }
     }
     Ok(())
 }
 
+/// Read the identity passphrase from the environment or prompt for it
+fn passphrase_for(
+    target: &str,
+    confirm: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+
+    let prompt = format!("Passphrase for {}", target);
+    let mut input = Password::new().with_prompt(prompt);
+    if confirm {
+        input = input.with_confirmation("Confirm passphrase", "Passphrases do not match");
+    }
+    Ok(input.interact()?)
+}
+
+/// Read an enrollment secret from the environment or prompt for it
+fn secret_for(
+    user_id: &str,
+    confirm: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if let Ok(secret) = std::env::var(SECRET_ENV) {
+        return Ok(secret);
+    }
+
+    let prompt = format!("Enrollment secret for {}", user_id);
+    let mut input = Password::new().with_prompt(prompt);
+    if confirm {
+        input = input.with_confirmation("Confirm secret", "Secrets do not match");
+    }
+    Ok(input.interact()?)
+}
+
+/// Load an identity from the wallet, prompting for a passphrase if encrypted
+fn load_identity(
+    wallet_dir: &Path,
+    label: &str,
+) -> Result<FabricIdentity, Box<dyn std::error::Error>> {
+    let wallet = FileSystemWallet::new(wallet_dir)?;
+    if wallet.is_encrypted(label).unwrap_or(false) {
+        let passphrase = passphrase_for(label, false)?;
+        return Ok(wallet.with_passphrase(&passphrase).require(label)?);
+    }
+    Ok(wallet.require(label)?)
+}
+
+/// Print an identity as JSON, with the private key only if asked to
+fn print_identity(
+    identity: &FabricIdentity,
+    show_private_key: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if show_private_key {
+        println!("{}", identity.to_json()?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(identity)?);
+        eprintln!(
+            "{}",
+            "Private key not shown; pass --show-private-key to print it".yellow()
+        );
+    }
+    Ok(())
+}
+
+/// Store an identity in the wallet, encrypted unless `plaintext` is set
+fn store_identity(
+    wallet_dir: &Path,
+    label: &str,
+    identity: &FabricIdentity,
+    plaintext: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let wallet = FileSystemWallet::new(wallet_dir)?;
+    if plaintext {
+        wallet.put(label, identity)?;
+    } else {
+        let passphrase = passphrase_for(label, true)?;
+        wallet.with_passphrase(&passphrase).put(label, identity)?;
+    }
+    Ok(())
+}
+
+/// Save an identity file with its private key encrypted
+fn save_identity(
+    identity: &FabricIdentity,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let passphrase = passphrase_for(&path.display().to_string(), true)?;
+    identity.save_encrypted(path, &passphrase)?;
+    Ok(())
+}
+
+fn ca_client(url: &str, ca_name: Option<String>) -> FabricCaClient {
+    let client = FabricCaClient::new(url);
+    match ca_name {
+        Some(name) => client.with_ca_name(&name),
+        None => client,
+    }
+}
+
+/// Network configuration for a Kaleido gateway
+fn network_config(
+    gateway: &str,
+    ca_url: &str,
+    tls: &TlsArgs,
+    allow_mspid_mismatch: bool,
+) -> FabricNetworkConfig {
+    let (tls_cert_path, tls) = tls.config();
+    FabricNetworkConfig {
+        name: "Kaleido".to_string(),
+        orderers: vec![format!("{}/orderer", gateway)],
+        peers: vec![format!("{}/peer", gateway)],
+        ca_url: ca_url.to_string(),
+        gateway_url: gateway.to_string(),
+        tls_cert_path,
+        tls,
+        offline_fallback: false,
+        allow_mspid_mismatch,
+    }
+}
+
+/// Connect to a Kaleido gateway with a wallet identity
+async fn connect_client(
+    wallet_dir: &Path,
+    gateway: &str,
+    identity: &str,
+    tls: &TlsArgs,
+    allow_mspid_mismatch: bool,
+) -> Result<KaleidoFabricClient, Box<dyn std::error::Error>> {
+    let identity = load_identity(wallet_dir, identity)?;
+    let mut client = KaleidoFabricClient::new(network_config(gateway, "", tls, allow_mspid_mismatch));
+    client.connect(&identity).await?;
+    Ok(client)
+}
+
 async fn handle_network_commands(
     cmd: NetworkCommands,
+    wallet_dir: &Path,
+    allow_mspid_mismatch: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     match cmd {
         NetworkCommands::Connect {
             gateway,
             ca,
             identity,
+            tls,
         } => {
             println!(
                 "{}",
@@ -334,15 +1138,8 @@ async fn handle_network_commands(
             );
 
             let identity_data =
-                FabricIdentity::load_from_file(&identity)?;
-            let config = FabricNetworkConfig {
-                name: "Kaleido".to_string(),
-                orderers: vec![format!("{}/orderer", gateway)],
-                peers: vec![format!("{}/peer", gateway)],
-                ca_url: ca.clone(),
-                gateway_url: gateway.clone(),
-                tls_cert_path: None,
-            };
+                load_identity(wallet_dir, &identity)?;
+            let config = network_config(&gateway, &ca, &tls, allow_mspid_mismatch);
             let mut client = KaleidoFabricClient::new(config);
 
             match client.connect(&identity_data).await {
@@ -357,22 +1154,21 @@ async fn handle_network_commands(
             }
         }
 
-        NetworkCommands::Channels { gateway, identity } => {
+        NetworkCommands::Channels {
+            gateway,
+            identity,
+            offline_fallback,
+            tls,
+        } => {
             println!(
                 "{}",
                 "Retrieving channels...".bold().cyan()
             );
 
             let identity_data =
-                FabricIdentity::load_from_file(&identity)?;
-            let config = FabricNetworkConfig {
-                name: "Kaleido".to_string(),
-                orderers: vec![format!("{}/orderer", gateway)],
-                peers: vec![format!("{}/peer", gateway)],
-                ca_url: String::new(),
-                gateway_url: gateway.clone(),
-                tls_cert_path: None,
-            };
+                load_identity(wallet_dir, &identity)?;
+            let mut config = network_config(&gateway, "", &tls, allow_mspid_mismatch);
+            config.offline_fallback = offline_fallback;
             let mut client = KaleidoFabricClient::new(config);
 
             match client.connect(&identity_data).await {
@@ -381,9 +1177,15 @@ async fn handle_network_commands(
                         println!("\n{}", "Channels:".bold().cyan());
                         for channel in channels {
                             println!("  {} - {}", channel.id, channel.name);
+                            if !channel.description.is_empty() {
+                                println!(
+                                    "    Description: {}",
+                                    channel.description
+                                );
+                            }
                             println!(
-                                "    Description: {}",
-                                channel.description
+                                "    Chaincodes: {}",
+                                channel.chaincodes.join(", ")
                             );
                         }
                     }
@@ -413,47 +1215,143 @@ async fn handle_network_commands(
     Ok(())
 }
 
+/// Parse the `--args` JSON of a chaincode command
+fn chaincode_args(args: Option<&str>) -> Result<ChaincodeArgs, Box<dyn std::error::Error>> {
+    match args {
+        Some(json) => {
+            let json = serde_json::from_str(json)
+                .map_err(|e| format!("--args must be JSON, e.g. '[\"tt0111161\", 5]': {}", e))?;
+            Ok(ChaincodeArgs::from_json(json))
+        }
+        None => Ok(ChaincodeArgs::new()),
+    }
+}
+
 async fn handle_chaincode_commands(
     cmd: ChaincodeCommands,
+    wallet_dir: &Path,
+    allow_mspid_mismatch: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     match cmd {
         ChaincodeCommands::Query {
             channel,
             chaincode,
             function,
-            args: _,
+            args,
+            gateway,
+            identity,
+            json,
+            tls,
         } => {
-            println!(
-                "{}",
-                format!(
-                    "Querying: channel={}, chaincode={}, function={}",
-                    channel, chaincode, function
-                )
-                .bold()
-                .cyan()
-            );
-            println!("Query result: {}", "SUCCESS".green());
+            let args = chaincode_args(args.as_deref())?;
+            let client = connect_client(wallet_dir, &gateway, &identity, &tls, allow_mspid_mismatch).await?;
+            if !json {
+                println!(
+                    "{}",
+                    format!(
+                        "Querying: channel={}, chaincode={}, function={}",
+                        channel, chaincode, function
+                    )
+                    .bold()
+                    .cyan()
+                );
+            }
+            let result = client
+                .query_chaincode(&channel, &chaincode, &function, args.to_fabric_args())
+                .await?;
+            println!("{}", serde_json::to_string_pretty(&result)?);
         }
 
         ChaincodeCommands::Invoke {
             channel,
             chaincode,
             function,
-            args: _,
+            args,
+            gateway,
+            identity,
+            json,
+            tls,
         } => {
-            println!(
-                "{}",
-                format!(
-                    "Invoking: channel={}, chaincode={}, function={}",
-                    channel, chaincode, function
-                )
-                .bold()
-                .cyan()
-            );
-            println!(
-                "Transaction ID: {}",
-                uuid::Uuid::new_v4().to_string().yellow()
-            );
+            let args = chaincode_args(args.as_deref())?;
+            let client = connect_client(wallet_dir, &gateway, &identity, &tls, allow_mspid_mismatch).await?;
+            if !json {
+                println!(
+                    "{}",
+                    format!(
+                        "Invoking: channel={}, chaincode={}, function={}",
+                        channel, chaincode, function
+                    )
+                    .bold()
+                    .cyan()
+                );
+            }
+            let result = client
+                .invoke_chaincode(&channel, &chaincode, &function, args.to_fabric_args())
+                .await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&result)?);
+                return Ok(());
+            }
+            println!("Transaction ID: {}", result.transaction_id.yellow());
+            println!("Status: {}", format!("{:?}", result.status).green());
+            println!("{}", serde_json::to_string_pretty(&result.payload)?);
+        }
+
+        ChaincodeCommands::History {
+            channel,
+            chaincode,
+            gateway,
+            identity,
+            function,
+            mspid,
+            from,
+            to,
+            limit,
+            max_blocks,
+            cursor,
+            json,
+            tls,
+        } => {
+            let client = connect_client(wallet_dir, &gateway, &identity, &tls, allow_mspid_mismatch).await?;
+            let query = HistoryQuery {
+                function,
+                mspid,
+                from,
+                to,
+                limit: Some(limit),
+                max_blocks,
+                cursor,
+            };
+            let page = client
+                .get_transaction_history(&channel, &chaincode, &query)
+                .await?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&page)?);
+                return Ok(());
+            }
+
+            if page.transactions.is_empty() {
+                println!("{}", "No transactions found".yellow());
+            } else {
+                let mut table = Table::new();
+                table.add_row(row!["Block", "Time", "Function", "Arguments", "MSP ID", "Status", "Transaction ID"]);
+                for tx in &page.transactions {
+                    table.add_row(row![
+                        tx.block_number,
+                        tx.timestamp.to_rfc3339(),
+                        tx.function,
+                        tx.args.join(" "),
+                        tx.mspid,
+                        tx.validation_code,
+                        tx.transaction_id
+                    ]);
+                }
+                table.printstd();
+            }
+            if let Some(cursor) = page.next_cursor {
+                println!("\nMore transactions: --cursor {}", cursor.yellow());
+            }
         }
     }
     Ok(())
@@ -0,0 +1,447 @@
+/// Shamir secret sharing backups of identity private keys
+use crate::crypto::{EcCurve, FabricIdentity};
+use crate::error::{FabricCoreError, Result};
+use crate::formats::identity_from_parts;
+use crate::secret::SecretBytes;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey, EcPoint};
+use openssl::pkey::PKey;
+use openssl::rand::rand_bytes;
+use openssl::sha::sha256;
+use std::fmt;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// Prefix of every encoded share
+const SHARE_PREFIX: &str = "FABRIC-SHARE-";
+const SHARE_VERSION: u8 = 1;
+/// version, curve, threshold, index and backup ID
+const HEADER_LEN: usize = 8;
+const CHECKSUM_LEN: usize = 4;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// One share of a split private key
+///
+/// Encodes as `FABRIC-SHARE-` followed by upper-case base32, which fits the
+/// QR alphanumeric mode and survives being read aloud or retyped. The last
+/// four bytes are a SHA-256 checksum, so typos are caught on parse.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BackupShare {
+    /// Random tag shared by every share of one backup
+    pub backup_id: [u8; 4],
+    /// Share number, from 1 to the number of shares
+    pub index: u8,
+    /// Number of shares needed to restore the key
+    pub threshold: u8,
+    pub curve: EcCurve,
+    value: SecretBytes,
+}
+
+impl BackupShare {
+    fn encode(&self) -> Zeroizing<Vec<u8>> {
+        let mut bytes = Zeroizing::new(Vec::with_capacity(
+            HEADER_LEN + self.value.expose_secret().len() + CHECKSUM_LEN,
+        ));
+        bytes.extend_from_slice(&[
+            SHARE_VERSION,
+            curve_code(self.curve),
+            self.threshold,
+            self.index,
+        ]);
+        bytes.extend_from_slice(&self.backup_id);
+        bytes.extend_from_slice(self.value.expose_secret());
+        let checksum = sha256(&bytes);
+        bytes.extend_from_slice(&checksum[..CHECKSUM_LEN]);
+        bytes
+    }
+}
+
+impl fmt::Display for BackupShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", SHARE_PREFIX, base32_encode(&self.encode()))
+    }
+}
+
+impl fmt::Debug for BackupShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackupShare")
+            .field("backup_id", &self.backup_id)
+            .field("index", &self.index)
+            .field("threshold", &self.threshold)
+            .field("curve", &self.curve)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FromStr for BackupShare {
+    type Err = FabricCoreError;
+
+    /// Parse a share, ignoring case, whitespace and dashes
+    fn from_str(s: &str) -> Result<Self> {
+        let normalized: String = s
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .collect::<String>()
+            .to_ascii_uppercase();
+        let encoded = normalized
+            .strip_prefix(&SHARE_PREFIX.replace('-', ""))
+            .ok_or_else(|| share_err("missing FABRIC-SHARE prefix"))?;
+        let bytes = Zeroizing::new(base32_decode(encoded)?);
+        if bytes.len() <= HEADER_LEN + CHECKSUM_LEN {
+            return Err(share_err("share is truncated"));
+        }
+
+        let (payload, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        if sha256(payload)[..CHECKSUM_LEN] != *checksum {
+            return Err(share_err("checksum mismatch; check the share for typos"));
+        }
+        if payload[0] != SHARE_VERSION {
+            return Err(share_err(format!(
+                "unsupported share version {}",
+                payload[0]
+            )));
+        }
+        // The checksum is not keyed, so a forged header still parses this far
+        if payload[2] < 2 {
+            return Err(share_err(format!("invalid threshold {}", payload[2])));
+        }
+        let curve = curve_from_code(payload[1])?;
+        let value = &payload[HEADER_LEN..];
+        if value.len() != curve.field_size() {
+            return Err(share_err("share length does not match its curve"));
+        }
+
+        Ok(Self {
+            backup_id: payload[4..HEADER_LEN]
+                .try_into()
+                .expect("slice has 4 bytes"),
+            index: payload[3],
+            threshold: payload[2],
+            curve,
+            value: value.to_vec().into(),
+        })
+    }
+}
+
+impl FabricIdentity {
+    /// Split the private key into `count` shares, any `threshold` of which
+    /// restore it
+    pub fn split_private_key(&self, threshold: u8, count: u8) -> Result<Vec<BackupShare>> {
+        if threshold < 2 || threshold > count {
+            return Err(FabricCoreError::KeyManagementError(format!(
+                "Threshold must be between 2 and the number of shares ({})",
+                count
+            )));
+        }
+        if self.private_key.is_empty() {
+            return Err(FabricCoreError::KeyManagementError(
+                "Identity has no exportable private key".to_string(),
+            ));
+        }
+        let key = PKey::private_key_from_pem(self.private_key.expose_secret().as_bytes())
+            .and_then(|key| key.ec_key())
+            .map_err(|e| FabricCoreError::InvalidPrivateKey(e.to_string()))?;
+        let curve = key
+            .group()
+            .curve_name()
+            .and_then(EcCurve::from_nid)
+            .ok_or_else(|| {
+                FabricCoreError::InvalidPrivateKey("Unsupported elliptic curve".to_string())
+            })?;
+        let secret = Zeroizing::new(
+            key.private_key()
+                .to_vec_padded(curve.field_size() as i32)
+                .map_err(backup_err)?,
+        );
+
+        let mut backup_id = [0u8; 4];
+        rand_bytes(&mut backup_id).map_err(backup_err)?;
+        let values = shamir_split(&secret, threshold, count)?;
+        Ok(values
+            .into_iter()
+            .zip(1..=count)
+            .map(|(value, index)| BackupShare {
+                backup_id,
+                index,
+                threshold,
+                curve,
+                value,
+            })
+            .collect())
+    }
+
+    /// Rebuild an identity from key shares and its certificate
+    ///
+    /// The certificate is public and is not part of the shares. User ID and
+    /// organization come from its subject, the MSP ID defaults to
+    /// `<organization>MSP`, and the result is validated.
+    pub fn restore_from_shares(
+        shares: &[BackupShare],
+        certificate: &str,
+        ca_certificate: &str,
+        mspid: Option<&str>,
+    ) -> Result<Self> {
+        let first = shares.first().ok_or_else(|| share_err("no shares given"))?;
+        if first.threshold < 2 {
+            return Err(share_err(format!("invalid threshold {}", first.threshold)));
+        }
+        if shares.iter().any(|share| {
+            share.backup_id != first.backup_id
+                || share.threshold != first.threshold
+                || share.curve != first.curve
+        }) {
+            return Err(share_err("shares come from different backups"));
+        }
+        let mut indexes: Vec<u8> = shares.iter().map(|share| share.index).collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        if indexes.len() != shares.len() || indexes.contains(&0) {
+            return Err(share_err("share numbers must be distinct and non-zero"));
+        }
+        if shares.len() < usize::from(first.threshold) {
+            return Err(share_err(format!(
+                "{} of {} required shares given",
+                shares.len(),
+                first.threshold
+            )));
+        }
+
+        let secret = shamir_combine(&shares[..usize::from(first.threshold)]);
+        let group = EcGroup::from_curve_name(first.curve.nid()).map_err(backup_err)?;
+        let scalar = BigNum::from_slice(&secret).map_err(backup_err)?;
+        let mut context = BigNumContext::new().map_err(backup_err)?;
+        let mut public = EcPoint::new(&group).map_err(backup_err)?;
+        public
+            .mul_generator2(&group, &scalar, &mut context)
+            .map_err(backup_err)?;
+        let key = EcKey::from_private_components(&group, &scalar, &public)
+            .and_then(PKey::from_ec_key)
+            .map_err(|e| FabricCoreError::InvalidPrivateKey(e.to_string()))?;
+
+        identity_from_parts(
+            &key,
+            certificate.to_string(),
+            ca_certificate.to_string(),
+            mspid,
+        )
+    }
+}
+
+/// Split each byte of `secret` with a random polynomial over GF(256)
+fn shamir_split(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<SecretBytes>> {
+    let mut shares = vec![Vec::with_capacity(secret.len()); usize::from(count)];
+    let mut coefficients = Zeroizing::new(vec![0u8; usize::from(threshold)]);
+    for byte in secret {
+        coefficients[0] = *byte;
+        rand_bytes(&mut coefficients[1..]).map_err(backup_err)?;
+        for (share, x) in shares.iter_mut().zip(1..=count) {
+            // Horner's rule from the highest coefficient down
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0, |acc, coefficient| gf_mul(acc, x) ^ coefficient);
+            share.push(y);
+        }
+    }
+    Ok(shares.into_iter().map(SecretBytes::from).collect())
+}
+
+/// Lagrange interpolation at zero
+fn shamir_combine(shares: &[BackupShare]) -> Zeroizing<Vec<u8>> {
+    let len = shares[0].value.expose_secret().len();
+    let mut secret = Zeroizing::new(vec![0u8; len]);
+    for (j, share) in shares.iter().enumerate() {
+        let mut basis = 1u8;
+        for (m, other) in shares.iter().enumerate() {
+            if m != j {
+                basis = gf_mul(basis, gf_div(other.index, other.index ^ share.index));
+            }
+        }
+        for (out, y) in secret.iter_mut().zip(share.value.expose_secret()) {
+            *out ^= gf_mul(basis, *y);
+        }
+    }
+    secret
+}
+
+/// Multiplication in GF(2^8) with the AES polynomial
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+fn gf_div(a: u8, b: u8) -> u8 {
+    // b^254 is the inverse of b in GF(2^8)
+    let mut inverse = 1;
+    for _ in 0..254 {
+        inverse = gf_mul(inverse, b);
+    }
+    gf_mul(a, inverse)
+}
+
+fn curve_code(curve: EcCurve) -> u8 {
+    match curve {
+        EcCurve::P256 => 0,
+        EcCurve::P384 => 1,
+    }
+}
+
+fn curve_from_code(code: u8) -> Result<EcCurve> {
+    match code {
+        0 => Ok(EcCurve::P256),
+        1 => Ok(EcCurve::P384),
+        other => Err(share_err(format!("unknown curve {}", other))),
+    }
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u16::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[usize::from((buffer >> bits) & 0x1f)] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)] as char);
+    }
+    out
+}
+
+fn base32_decode(encoded: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for c in encoded.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or_else(|| share_err(format!("invalid character '{}'", c as char)))?;
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn share_err(message: impl fmt::Display) -> FabricCoreError {
+    FabricCoreError::InvalidBackupShare(message.to_string())
+}
+
+fn backup_err(e: impl fmt::Display) -> FabricCoreError {
+    FabricCoreError::CryptoError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{CertSpec, TestCert};
+
+    #[test]
+    fn test_any_threshold_subset_restores_identity() {
+        let identity = TestCert::identity();
+        let shares = identity.split_private_key(3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let encoded: Vec<String> = subset.iter().map(|i| shares[*i].to_string()).collect();
+            let parsed: Vec<BackupShare> = encoded.iter().map(|s| s.parse().unwrap()).collect();
+            let restored = FabricIdentity::restore_from_shares(
+                &parsed,
+                &identity.certificate,
+                &identity.ca_certificate,
+                None,
+            )
+            .unwrap();
+            assert_eq!(restored.private_key, identity.private_key);
+            assert_eq!(restored.user_id, "user1");
+            assert_eq!(restored.mspid, "Org1MSP");
+        }
+
+        assert!(matches!(
+            FabricIdentity::restore_from_shares(&shares[..2], &identity.certificate, "", None),
+            Err(FabricCoreError::InvalidBackupShare(_))
+        ));
+        assert!(identity.split_private_key(1, 5).is_err());
+        assert!(identity.split_private_key(6, 5).is_err());
+    }
+
+    #[test]
+    fn test_share_encoding_detects_typos() {
+        let share = TestCert::identity()
+            .split_private_key(2, 3)
+            .unwrap()
+            .remove(0);
+        let encoded = share.to_string();
+        assert!(encoded.starts_with("FABRIC-SHARE-"));
+        assert!(encoded
+            .bytes()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == b'-'));
+        assert!(!format!("{:?}", share).contains(&encoded[13..]));
+
+        // Case, spacing and grouping are ignored
+        let spaced = encoded.to_lowercase().replace("share-", "share- ");
+        assert_eq!(spaced.parse::<BackupShare>().unwrap(), share);
+
+        let mut typo = encoded.into_bytes();
+        let last = typo.len() - 5;
+        typo[last] = if typo[last] == b'A' { b'B' } else { b'A' };
+        assert!(matches!(
+            String::from_utf8(typo).unwrap().parse::<BackupShare>(),
+            Err(FabricCoreError::InvalidBackupShare(_))
+        ));
+    }
+
+    #[test]
+    fn test_shares_from_different_backups_are_rejected() {
+        let identity = TestCert::identity();
+        let first = identity.split_private_key(2, 3).unwrap();
+        let second = identity.split_private_key(2, 3).unwrap();
+
+        let mixed = [first[0].clone(), second[1].clone()];
+        assert!(matches!(
+            FabricIdentity::restore_from_shares(&mixed, &identity.certificate, "", None),
+            Err(FabricCoreError::InvalidBackupShare(_))
+        ));
+
+        let other = TestCert::root("ca.org1.example.com").issue(CertSpec::default());
+        assert!(matches!(
+            FabricIdentity::restore_from_shares(&first[..2], &other.cert_pem(), "", None),
+            Err(FabricCoreError::KeyCertificateMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_share_with_threshold_below_two_is_rejected() {
+        let identity = TestCert::identity();
+        let mut forged = identity.split_private_key(2, 3).unwrap().remove(0);
+        forged.threshold = 0;
+        // Encoding recomputes the checksum, as a forger would
+        assert!(matches!(
+            forged.to_string().parse::<BackupShare>(),
+            Err(FabricCoreError::InvalidBackupShare(_))
+        ));
+        assert!(matches!(
+            FabricIdentity::restore_from_shares(&[forged], &identity.certificate, "", None),
+            Err(FabricCoreError::InvalidBackupShare(_))
+        ));
+    }
+}
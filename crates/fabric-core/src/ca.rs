@@ -0,0 +1,862 @@
+/// Fabric CA client for enrollment and identity lifecycle management
+use crate::crypto::{EcCurve, FabricIdentity};
+use crate::error::{FabricCoreError, Result};
+use crate::fabric::FabricNetworkConfig;
+use crate::signer::{Signer, SoftwareSigner};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use openssl::pkey::PKey;
+use openssl::x509::X509Req;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const API_PREFIX: &str = "/api/v1";
+
+/// OID 2.5.4.3 (commonName)
+const COMMON_NAME_OID: &[u8] = &[0x55, 0x04, 0x03];
+/// OID 1.2.840.10045.4.3.2 (ecdsa-with-SHA256)
+const ECDSA_WITH_SHA256_OID: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+
+/// Attribute requested for inclusion in an enrollment certificate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributeRequest {
+    pub name: String,
+    #[serde(default)]
+    pub optional: bool,
+}
+
+/// Parameters for enrolling an identity with its enrollment secret
+#[derive(Debug, Clone)]
+pub struct EnrollmentRequest {
+    pub enrollment_id: String,
+    pub secret: String,
+    /// MSP ID recorded on the resulting identity
+    pub mspid: String,
+    /// Organization name recorded on the resulting identity
+    pub org_name: String,
+    /// Signing profile (e.g. "tls"), or the CA default
+    pub profile: Option<String>,
+    pub attr_reqs: Vec<AttributeRequest>,
+    pub curve: EcCurve,
+}
+
+impl EnrollmentRequest {
+    pub fn new(enrollment_id: &str, secret: &str, mspid: &str, org_name: &str) -> Self {
+        Self {
+            enrollment_id: enrollment_id.to_string(),
+            secret: secret.to_string(),
+            mspid: mspid.to_string(),
+            org_name: org_name.to_string(),
+            profile: None,
+            attr_reqs: Vec::new(),
+            curve: EcCurve::P256,
+        }
+    }
+}
+
+/// Attribute assigned to a registered identity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrationAttribute {
+    pub name: String,
+    pub value: String,
+    /// Include the attribute in enrollment certificates by default
+    #[serde(default)]
+    pub ecert: bool,
+}
+
+/// Parameters for registering a new identity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrationRequest {
+    #[serde(rename = "id")]
+    pub enrollment_id: String,
+    /// Identity type: client, peer, orderer or admin
+    #[serde(rename = "type")]
+    pub identity_type: String,
+    /// Enrollment secret; the CA generates one when omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub affiliation: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_enrollments: Option<i32>,
+    #[serde(default)]
+    pub attrs: Vec<RegistrationAttribute>,
+}
+
+impl RegistrationRequest {
+    pub fn new(enrollment_id: &str, affiliation: &str) -> Self {
+        Self {
+            enrollment_id: enrollment_id.to_string(),
+            identity_type: "client".to_string(),
+            secret: None,
+            affiliation: affiliation.to_string(),
+            max_enrollments: None,
+            attrs: Vec::new(),
+        }
+    }
+}
+
+/// Parameters for revoking an identity or a single certificate
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevocationRequest {
+    /// Revoke every certificate of this enrollment ID
+    #[serde(rename = "id", skip_serializing_if = "Option::is_none")]
+    pub enrollment_id: Option<String>,
+    /// Hex-encoded serial of a single certificate to revoke
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// Hex-encoded authority key identifier of that certificate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aki: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Ask the CA to return an updated CRL
+    #[serde(rename = "gencrl")]
+    pub gen_crl: bool,
+}
+
+/// A certificate reported as revoked by the CA
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevokedCertificate {
+    #[serde(rename = "Serial")]
+    pub serial: String,
+    #[serde(rename = "AKI")]
+    pub aki: String,
+}
+
+/// Outcome of a revocation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationResult {
+    pub revoked: Vec<RevokedCertificate>,
+    /// PEM-encoded CRL when `gen_crl` was requested
+    pub crl: Option<String>,
+}
+
+/// Filters for a generated CRL; empty includes every revoked certificate
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CrlRequest {
+    #[serde(rename = "revokedafter", skip_serializing_if = "Option::is_none")]
+    pub revoked_after: Option<DateTime<Utc>>,
+    #[serde(rename = "revokedbefore", skip_serializing_if = "Option::is_none")]
+    pub revoked_before: Option<DateTime<Utc>>,
+    #[serde(rename = "expireafter", skip_serializing_if = "Option::is_none")]
+    pub expire_after: Option<DateTime<Utc>>,
+    #[serde(rename = "expirebefore", skip_serializing_if = "Option::is_none")]
+    pub expire_before: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CaResponse {
+    #[serde(default)]
+    success: bool,
+    #[serde(default)]
+    result: serde_json::Value,
+    #[serde(default)]
+    errors: Vec<CaMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CaMessage {
+    #[serde(default)]
+    code: i64,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnrollmentResult {
+    #[serde(rename = "Cert")]
+    cert: String,
+    #[serde(rename = "ServerInfo", default)]
+    server_info: Option<ServerInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerInfo {
+    #[serde(rename = "CAChain", default)]
+    ca_chain: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevokeResult {
+    #[serde(rename = "RevokedCerts", default)]
+    revoked_certs: Vec<RevokedCertificate>,
+    #[serde(rename = "CRL", default)]
+    crl: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GenCrlResult {
+    #[serde(rename = "CRL")]
+    crl: String,
+}
+
+/// Client for the Fabric CA REST API
+pub struct FabricCaClient {
+    url: String,
+    ca_name: Option<String>,
+    http_client: reqwest::Client,
+}
+
+impl FabricCaClient {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            ca_name: None,
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a client for the CA configured on a network
+    pub fn from_config(config: &FabricNetworkConfig) -> Result<Self> {
+        if config.ca_url.is_empty() {
+            return Err(FabricCoreError::ConfigError(
+                "CA URL is not configured".to_string(),
+            ));
+        }
+        Ok(Self::new(&config.ca_url))
+    }
+
+    /// Target a named CA on a multi-CA server
+    pub fn with_ca_name(mut self, ca_name: &str) -> Self {
+        self.ca_name = Some(ca_name.to_string());
+        self
+    }
+
+    /// Get the CA URL
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Build a PEM certificate signing request for a local private key
+    pub fn create_csr(private_key_pem: &str, common_name: &str) -> Result<String> {
+        Self::create_csr_with_signer(&SoftwareSigner::from_pem(private_key_pem)?, common_name)
+    }
+
+    /// Build a PEM certificate signing request signed by any `Signer`
+    ///
+    /// The request is encoded by hand so that keys held outside the process,
+    /// such as in an HSM, never need to be loaded into OpenSSL.
+    pub fn create_csr_with_signer(signer: &dyn Signer, common_name: &str) -> Result<String> {
+        let public_key = PKey::public_key_from_pem(signer.public_key_pem()?.as_bytes())
+            .map_err(ca_err)?;
+        let spki = public_key.public_key_to_der().map_err(ca_err)?;
+
+        let rdn = der(0x30, &[der(0x06, COMMON_NAME_OID), der(0x0c, common_name.as_bytes())].concat());
+        let subject = der(0x30, &der(0x31, &rdn));
+        // CertificationRequestInfo: version 0, subject, SPKI, empty attributes
+        let info = der(0x30, &[der(0x02, &[0]), subject, spki, der(0xa0, &[])].concat());
+
+        let mut signature = vec![0];
+        signature.extend(signer.sign(&info)?);
+        let algorithm = der(0x30, &der(0x06, ECDSA_WITH_SHA256_OID));
+        let request = der(0x30, &[info, algorithm, der(0x03, &signature)].concat());
+
+        let pem = X509Req::from_der(&request)
+            .and_then(|req| req.to_pem())
+            .map_err(ca_err)?;
+        String::from_utf8(pem).map_err(ca_err)
+    }
+
+    /// Enroll with an enrollment secret, generating a fresh key pair
+    pub async fn enroll(&self, request: &EnrollmentRequest) -> Result<FabricIdentity> {
+        let signer = SoftwareSigner::generate(request.curve)?;
+        let (certificate, ca_certificate) = self.enroll_csr(request, &signer).await?;
+
+        Ok(FabricIdentity::new(
+            signer.private_key_pem()?,
+            signer.public_key_pem()?,
+            certificate,
+            ca_certificate,
+            request.enrollment_id.clone(),
+            request.org_name.clone(),
+            request.mspid.clone(),
+        ))
+    }
+
+    /// Enroll a key held by an external signer, such as an HSM
+    ///
+    /// The returned identity has no `private_key` and signs through
+    /// `signer`; `request.curve` is ignored in favour of the signer's key.
+    pub async fn enroll_with_signer(
+        &self,
+        request: &EnrollmentRequest,
+        signer: Arc<dyn Signer>,
+    ) -> Result<FabricIdentity> {
+        let (certificate, ca_certificate) = self.enroll_csr(request, signer.as_ref()).await?;
+
+        Ok(FabricIdentity::new(
+            String::new(),
+            signer.public_key_pem()?,
+            certificate,
+            ca_certificate,
+            request.enrollment_id.clone(),
+            request.org_name.clone(),
+            request.mspid.clone(),
+        )
+        .with_signer(signer))
+    }
+
+    /// Send an enrollment CSR, returning the certificate and CA chain PEMs
+    async fn enroll_csr(
+        &self,
+        request: &EnrollmentRequest,
+        signer: &dyn Signer,
+    ) -> Result<(String, String)> {
+        let csr = Self::create_csr_with_signer(signer, &request.enrollment_id)?;
+
+        let mut body = serde_json::json!({
+            "certificate_request": csr,
+            "attr_reqs": request.attr_reqs,
+        });
+        if let Some(profile) = &request.profile {
+            body["profile"] = serde_json::json!(profile);
+        }
+        self.add_ca_name(&mut body);
+
+        tracing::info!("Enrolling {} with {}", request.enrollment_id, self.url);
+        let http_request = self
+            .http_client
+            .post(self.endpoint("enroll"))
+            .basic_auth(&request.enrollment_id, Some(&request.secret))
+            .json(&body);
+        let result: EnrollmentResult = self.send(http_request).await?;
+
+        Ok((decode_pem(&result.cert)?, decode_ca_chain(result.server_info)?))
+    }
+
+    /// Register a new identity, returning its enrollment secret
+    pub async fn register(
+        &self,
+        registrar: &FabricIdentity,
+        request: &RegistrationRequest,
+    ) -> Result<String> {
+        let mut body = serde_json::to_value(request)?;
+        self.add_ca_name(&mut body);
+
+        tracing::info!("Registering {} with {}", request.enrollment_id, self.url);
+        let result: serde_json::Value =
+            self.send_authenticated(registrar, "register", &body).await?;
+
+        result
+            .get("secret")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| {
+                FabricCoreError::CaError("Registration response has no secret".to_string())
+            })
+    }
+
+    /// Reenroll an identity before its certificate expires
+    ///
+    /// Software identities get a new key pair on the same curve; identities
+    /// with an external signer keep their key and only renew the certificate.
+    pub async fn reenroll(&self, identity: &FabricIdentity) -> Result<FabricIdentity> {
+        let current = identity.signer()?;
+        let software = if identity.has_external_signer() {
+            None
+        } else {
+            Some(SoftwareSigner::generate(current.algorithm().curve())?)
+        };
+        let signer: &dyn Signer = match &software {
+            Some(signer) => signer,
+            None => current.as_ref(),
+        };
+        let csr = Self::create_csr_with_signer(signer, &identity.user_id)?;
+
+        let mut body = serde_json::json!({ "certificate_request": csr });
+        self.add_ca_name(&mut body);
+
+        tracing::info!("Reenrolling {} with {}", identity.user_id, self.url);
+        let result: EnrollmentResult =
+            self.send_authenticated(identity, "reenroll", &body).await?;
+
+        let ca_certificate = match decode_ca_chain(result.server_info)? {
+            chain if chain.is_empty() => identity.ca_certificate.clone(),
+            chain => chain,
+        };
+        let renewed = FabricIdentity::new(
+            match &software {
+                Some(signer) => signer.private_key_pem()?,
+                None => String::new(),
+            },
+            signer.public_key_pem()?,
+            decode_pem(&result.cert)?,
+            ca_certificate,
+            identity.user_id.clone(),
+            identity.org_name.clone(),
+            identity.mspid.clone(),
+        );
+        Ok(match software {
+            Some(_) => renewed,
+            None => renewed.with_signer(current),
+        })
+    }
+
+    /// Revoke an identity or certificate
+    pub async fn revoke(
+        &self,
+        registrar: &FabricIdentity,
+        request: &RevocationRequest,
+    ) -> Result<RevocationResult> {
+        if request.enrollment_id.is_none() && request.serial.is_none() {
+            return Err(FabricCoreError::CaError(
+                "Revocation requires an enrollment ID or a certificate serial".to_string(),
+            ));
+        }
+        let mut body = serde_json::to_value(request)?;
+        self.add_ca_name(&mut body);
+
+        tracing::info!("Revoking certificates with {}", self.url);
+        let result: RevokeResult = self.send_authenticated(registrar, "revoke", &body).await?;
+
+        let crl = if result.crl.is_empty() {
+            None
+        } else {
+            Some(decode_pem(&result.crl)?)
+        };
+        Ok(RevocationResult {
+            revoked: result.revoked_certs,
+            crl,
+        })
+    }
+
+    /// Generate a PEM CRL of the certificates this CA has revoked
+    pub async fn generate_crl(
+        &self,
+        registrar: &FabricIdentity,
+        request: &CrlRequest,
+    ) -> Result<String> {
+        let mut body = serde_json::to_value(request)?;
+        self.add_ca_name(&mut body);
+
+        tracing::info!("Generating CRL with {}", self.url);
+        let result: GenCrlResult = self.send_authenticated(registrar, "gencrl", &body).await?;
+        decode_pem(&result.crl)
+    }
+
+    fn endpoint(&self, name: &str) -> String {
+        format!("{}{}/{}", self.url, API_PREFIX, name)
+    }
+
+    fn add_ca_name(&self, body: &mut serde_json::Value) {
+        if let Some(ca_name) = &self.ca_name {
+            body["caname"] = serde_json::json!(ca_name);
+        }
+    }
+
+    async fn send_authenticated<T: serde::de::DeserializeOwned>(
+        &self,
+        identity: &FabricIdentity,
+        name: &str,
+        body: &serde_json::Value,
+    ) -> Result<T> {
+        let body = serde_json::to_vec(body)?;
+        let path = format!("{}/{}", API_PREFIX, name);
+        let token = Self::auth_token(identity, "POST", &path, &body)?;
+
+        let http_request = self
+            .http_client
+            .post(self.endpoint(name))
+            .header(reqwest::header::AUTHORIZATION, token)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+        self.send(http_request).await
+    }
+
+    /// Build the token Fabric CA expects on authenticated requests
+    ///
+    /// The token is `base64(cert).base64(signature)`, where the signature
+    /// covers `method.base64(path).base64(body).base64(cert)`.
+    pub fn auth_token(
+        identity: &FabricIdentity,
+        method: &str,
+        path: &str,
+        body: &[u8],
+    ) -> Result<String> {
+        let cert = BASE64.encode(identity.certificate.as_bytes());
+        let payload = format!(
+            "{}.{}.{}.{}",
+            method,
+            BASE64.encode(path.as_bytes()),
+            BASE64.encode(body),
+            cert
+        );
+        let signature = identity.sign(payload.as_bytes())?;
+        Ok(format!("{}.{}", cert, BASE64.encode(signature)))
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| FabricCoreError::NetworkError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| FabricCoreError::NetworkError(e.to_string()))?;
+
+        let parsed: CaResponse = serde_json::from_str(&text).map_err(|_| {
+            FabricCoreError::CaError(format!("Unexpected response ({}): {}", status, text))
+        })?;
+        if !status.is_success() || !parsed.success {
+            let errors = parsed
+                .errors
+                .iter()
+                .map(|e| format!("[{}] {}", e.code, e.message))
+                .collect::<Vec<_>>()
+                .join("; ");
+            return Err(FabricCoreError::CaError(format!(
+                "Request failed with status {}: {}",
+                status, errors
+            )));
+        }
+
+        Ok(serde_json::from_value(parsed.result)?)
+    }
+}
+
+fn ca_err(e: impl std::fmt::Display) -> FabricCoreError {
+    FabricCoreError::CaError(e.to_string())
+}
+
+fn decode_pem(encoded: &str) -> Result<String> {
+    let bytes = BASE64.decode(encoded.trim()).map_err(ca_err)?;
+    String::from_utf8(bytes).map_err(ca_err)
+}
+
+fn decode_ca_chain(server_info: Option<ServerInfo>) -> Result<String> {
+    match server_info {
+        Some(info) if !info.ca_chain.is_empty() => decode_pem(&info.ca_chain),
+        _ => Ok(String::new()),
+    }
+}
+
+/// DER-encode a single tag-length-value
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crl::{CertificateRevocationList, CrlStore};
+    use crate::crypto::CryptoManager;
+    use crate::test_util::{CertSpec, MockRequest, MockResponse, MockServer, TestCert};
+    use openssl::nid::Nid;
+
+    fn ok(result: serde_json::Value) -> MockResponse {
+        MockResponse::json(
+            200,
+            serde_json::json!({ "success": true, "result": result, "errors": [], "messages": [] }),
+        )
+    }
+
+    fn unauthorized(message: &str) -> MockResponse {
+        MockResponse::json(
+            401,
+            serde_json::json!({
+                "success": false,
+                "result": null,
+                "errors": [{ "code": 20, "message": message }],
+                "messages": []
+            }),
+        )
+    }
+
+    /// Check a Fabric CA token and return the caller's certificate
+    fn verify_token(request: &MockRequest) -> Option<String> {
+        let token = request.headers.get("authorization")?;
+        let (cert, signature) = token.split_once('.')?;
+        let cert_pem = String::from_utf8(BASE64.decode(cert).ok()?).ok()?;
+        let payload = format!(
+            "POST.{}.{}.{}",
+            BASE64.encode(request.path.as_bytes()),
+            BASE64.encode(&request.body),
+            cert
+        );
+        let public_key = openssl::x509::X509::from_pem(cert_pem.as_bytes())
+            .ok()?
+            .public_key()
+            .ok()?
+            .public_key_to_pem()
+            .ok()?;
+        let valid = CryptoManager::verify(
+            std::str::from_utf8(&public_key).ok()?,
+            payload.as_bytes(),
+            &BASE64.decode(signature).ok()?,
+        )
+        .ok()?;
+        valid.then_some(cert_pem)
+    }
+
+    async fn mock_ca() -> (MockServer, Arc<TestCert>) {
+        let root = Arc::new(TestCert::root("ca.org1.example.com"));
+        let ca = root.clone();
+
+        let server = MockServer::start(move |request| {
+            let body = request.json();
+            let issue = |cn: &str| {
+                let csr = X509Req::from_pem(
+                    body["certificate_request"].as_str().unwrap_or_default().as_bytes(),
+                )
+                .unwrap();
+                let key = csr.public_key().unwrap();
+                let cert = ca.issue_for_key(
+                    CertSpec {
+                        common_name: cn,
+                        ..Default::default()
+                    },
+                    &key,
+                );
+                ok(serde_json::json!({
+                    "Cert": BASE64.encode(cert.to_pem().unwrap()),
+                    "ServerInfo": { "CAName": "", "CAChain": BASE64.encode(ca.cert_pem()) }
+                }))
+            };
+
+            match request.path.as_str() {
+                "/api/v1/enroll" => {
+                    let expected = format!("Basic {}", BASE64.encode("user1:user1pw"));
+                    if request.headers.get("authorization") != Some(&expected) {
+                        return unauthorized("Authentication failure");
+                    }
+                    issue("user1")
+                }
+                "/api/v1/reenroll" => match verify_token(request) {
+                    Some(_) => issue("user1"),
+                    None => unauthorized("Invalid token"),
+                },
+                "/api/v1/register" => match verify_token(request) {
+                    Some(_) => ok(serde_json::json!({
+                        "secret": body["secret"].as_str().unwrap_or("generated-secret")
+                    })),
+                    None => unauthorized("Invalid token"),
+                },
+                "/api/v1/revoke" => match verify_token(request) {
+                    Some(_) => ok(serde_json::json!({
+                        "RevokedCerts": [{ "Serial": "1a2b", "AKI": "3c4d" }],
+                        "CRL": if body["gencrl"] == true {
+                            BASE64.encode("-----BEGIN X509 CRL-----\n-----END X509 CRL-----\n")
+                        } else {
+                            String::new()
+                        }
+                    })),
+                    None => unauthorized("Invalid token"),
+                },
+                "/api/v1/gencrl" => match verify_token(request) {
+                    Some(_) => ok(serde_json::json!({ "CRL": BASE64.encode(ca.crl(&[])) })),
+                    None => unauthorized("Invalid token"),
+                },
+                _ => MockResponse::json(404, serde_json::json!({})),
+            }
+        })
+        .await;
+
+        (server, root)
+    }
+
+    #[test]
+    fn test_create_csr() {
+        let (private_key, public_key) = CryptoManager::generate_keypair().unwrap();
+        let csr = FabricCaClient::create_csr(&private_key, "user1").unwrap();
+
+        let parsed = X509Req::from_pem(csr.as_bytes()).unwrap();
+        let key = parsed.public_key().unwrap();
+        assert!(parsed.verify(&key).unwrap());
+        assert_eq!(key.public_key_to_pem().unwrap(), public_key.as_bytes());
+        let cn = parsed
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .unwrap();
+        assert_eq!(cn.data().as_slice(), b"user1");
+    }
+
+    #[tokio::test]
+    async fn test_enroll_and_reenroll() {
+        let (server, root) = mock_ca().await;
+        let client = FabricCaClient::new(&server.url);
+
+        let identity = client
+            .enroll(&EnrollmentRequest::new("user1", "user1pw", "Org1MSP", "Org1"))
+            .await
+            .unwrap();
+        assert_eq!(identity.user_id, "user1");
+        assert_eq!(identity.mspid, "Org1MSP");
+        assert_eq!(identity.ca_certificate, root.cert_pem());
+        assert!(CryptoManager::verify_certificate(&identity.certificate, &identity.ca_certificate)
+            .unwrap()
+            .is_valid());
+        let cert_key = openssl::x509::X509::from_pem(identity.certificate.as_bytes())
+            .unwrap()
+            .public_key()
+            .unwrap();
+        assert_eq!(
+            cert_key.public_key_to_pem().unwrap(),
+            identity.public_key.as_bytes()
+        );
+
+        let renewed = client.reenroll(&identity).await.unwrap();
+        assert_ne!(renewed.private_key, identity.private_key);
+        assert_ne!(renewed.certificate, identity.certificate);
+        assert_eq!(renewed.user_id, "user1");
+    }
+
+    #[tokio::test]
+    async fn test_enroll_with_external_signer_keeps_key() {
+        let (server, _) = mock_ca().await;
+        let client = FabricCaClient::new(&server.url);
+        let signer: Arc<dyn Signer> = Arc::new(SoftwareSigner::generate(EcCurve::P256).unwrap());
+
+        let identity = client
+            .enroll_with_signer(
+                &EnrollmentRequest::new("user1", "user1pw", "Org1MSP", "Org1"),
+                signer.clone(),
+            )
+            .await
+            .unwrap();
+        assert!(identity.private_key.is_empty());
+        assert!(identity.has_external_signer());
+        assert_eq!(identity.public_key, signer.public_key_pem().unwrap());
+        identity.validate().unwrap();
+
+        // Reenrollment authenticates through the signer and keeps its key
+        let renewed = client.reenroll(&identity).await.unwrap();
+        assert!(renewed.has_external_signer());
+        assert_eq!(renewed.public_key, identity.public_key);
+        assert_ne!(renewed.certificate, identity.certificate);
+        renewed.validate().unwrap();
+    }
+
+    #[test]
+    fn test_create_csr_with_long_subject() {
+        let signer = SoftwareSigner::generate(EcCurve::P384).unwrap();
+        let common_name = "a".repeat(200);
+        let csr = FabricCaClient::create_csr_with_signer(&signer, &common_name).unwrap();
+
+        let parsed = X509Req::from_pem(csr.as_bytes()).unwrap();
+        assert!(parsed.verify(&parsed.public_key().unwrap()).unwrap());
+        let cn = parsed
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .unwrap();
+        assert_eq!(cn.data().as_slice(), common_name.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_enroll_with_wrong_secret() {
+        let (server, _) = mock_ca().await;
+        let client = FabricCaClient::new(&server.url);
+
+        let result = client
+            .enroll(&EnrollmentRequest::new("user1", "wrong", "Org1MSP", "Org1"))
+            .await;
+        match result {
+            Err(FabricCoreError::CaError(message)) => {
+                assert!(message.contains("Authentication failure"))
+            }
+            other => panic!("unexpected result: {:?}", other.map(|i| i.user_id)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_and_revoke_as_registrar() {
+        let (server, _) = mock_ca().await;
+        let client = FabricCaClient::new(&server.url).with_ca_name("ca-org1");
+        let registrar = client
+            .enroll(&EnrollmentRequest::new("user1", "user1pw", "Org1MSP", "Org1"))
+            .await
+            .unwrap();
+
+        let mut request = RegistrationRequest::new("moderator1", "org1.department1");
+        request.secret = Some("s3cret".to_string());
+        request.attrs.push(RegistrationAttribute {
+            name: "role".to_string(),
+            value: "moderator".to_string(),
+            ecert: true,
+        });
+        let secret = client.register(&registrar, &request).await.unwrap();
+        assert_eq!(secret, "s3cret");
+
+        let sent = server.requests();
+        let body = sent.last().unwrap().json();
+        assert_eq!(body["id"], "moderator1");
+        assert_eq!(body["type"], "client");
+        assert_eq!(body["caname"], "ca-org1");
+        assert_eq!(body["attrs"][0]["name"], "role");
+
+        let revocation = client
+            .revoke(
+                &registrar,
+                &RevocationRequest {
+                    enrollment_id: Some("moderator1".to_string()),
+                    reason: Some("keycompromise".to_string()),
+                    gen_crl: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(revocation.revoked.len(), 1);
+        assert_eq!(revocation.revoked[0].serial, "1a2b");
+        assert!(revocation.crl.unwrap().contains("BEGIN X509 CRL"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_crl() {
+        let (server, root) = mock_ca().await;
+        let client = FabricCaClient::new(&server.url);
+        let registrar = client
+            .enroll(&EnrollmentRequest::new("user1", "user1pw", "Org1MSP", "Org1"))
+            .await
+            .unwrap();
+
+        let request = CrlRequest {
+            revoked_after: Some(Utc::now() - chrono::Duration::days(1)),
+            ..Default::default()
+        };
+        let pem = client.generate_crl(&registrar, &request).await.unwrap();
+        let crl = CertificateRevocationList::from_pem(&pem).unwrap();
+        assert!(crl.verify_signature(&root.cert_pem()).unwrap());
+
+        let body = server.requests().last().unwrap().json();
+        assert!(body["revokedafter"].is_string());
+        assert!(body.get("expirebefore").is_none());
+
+        let mut crls = CrlStore::new();
+        crls.fetch_from_ca(&client, &registrar).await.unwrap();
+        registrar.check_revocation(&crls).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_request_rejects_foreign_key() {
+        let (server, _) = mock_ca().await;
+        let client = FabricCaClient::new(&server.url);
+        let mut registrar = client
+            .enroll(&EnrollmentRequest::new("user1", "user1pw", "Org1MSP", "Org1"))
+            .await
+            .unwrap();
+        registrar.private_key = CryptoManager::generate_keypair().unwrap().0.into();
+
+        let result = client
+            .register(&registrar, &RegistrationRequest::new("user2", "org1"))
+            .await;
+        assert!(matches!(result, Err(FabricCoreError::CaError(_))));
+    }
+}
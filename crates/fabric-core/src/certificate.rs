@@ -0,0 +1,258 @@
+/// Parsed view of Fabric X.509 certificates
+use crate::crypto::{name_to_string, FabricIdentity};
+use crate::error::{FabricCoreError, Result};
+use crate::msp::name_entries;
+use chrono::{DateTime, TimeZone, Utc};
+use openssl::asn1::{Asn1Time, Asn1TimeRef};
+use openssl::nid::Nid;
+use openssl::pkey::Id;
+use openssl::x509::{GeneralNameRef, X509};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+/// Extension OID under which Fabric CA embeds enrollment attributes
+pub const FABRIC_ATTRS_OID: &str = "1.2.3.4.5.6.7.8.1";
+
+/// DER encoding of `FABRIC_ATTRS_OID`
+const FABRIC_ATTRS_OID_DER: &[u8] = &[0x2a, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x01];
+
+/// OUs that Fabric treats as node roles when NodeOUs are enabled
+const NODE_OU_ROLES: [&str; 4] = ["client", "peer", "admin", "orderer"];
+
+/// Decoded contents of a certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    /// Subject DN as comma-separated `KEY=value` pairs
+    pub subject: String,
+    pub issuer: String,
+    pub common_name: Option<String>,
+    pub organization: Option<String>,
+    pub organizational_units: Vec<String>,
+    /// Subject alternative names, prefixed with `DNS:`, `IP:`, `email:` or `URI:`
+    pub subject_alt_names: Vec<String>,
+    /// Lowercase hex serial number, as Fabric CA reports it
+    pub serial: String,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    /// Public key algorithm, e.g. `ECDSA P-256`
+    pub key_algorithm: String,
+    /// First OU naming a Fabric node role (client, peer, admin or orderer)
+    pub node_ou_role: Option<String>,
+    /// Fabric CA attributes such as `hf.EnrollmentID`
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct FabricAttributes {
+    #[serde(default)]
+    attrs: BTreeMap<String, String>,
+}
+
+impl CertificateInfo {
+    /// Parse the first certificate of a PEM string
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let cert = X509::from_pem(pem.as_bytes()).map_err(cert_err)?;
+        let subject = cert.subject_name();
+
+        let subject_alt_names = cert
+            .subject_alt_names()
+            .map(|names| names.iter().filter_map(general_name).collect())
+            .unwrap_or_default();
+
+        let serial = cert
+            .serial_number()
+            .to_bn()
+            .and_then(|bn| bn.to_hex_str().map(|hex| hex.to_lowercase()))
+            .map_err(cert_err)?;
+
+        let organizational_units = name_entries(subject, Nid::ORGANIZATIONALUNITNAME);
+        let node_ou_role = organizational_units
+            .iter()
+            .find(|ou| NODE_OU_ROLES.contains(&ou.as_str()))
+            .cloned();
+
+        Ok(Self {
+            subject: name_to_string(subject),
+            issuer: name_to_string(cert.issuer_name()),
+            common_name: name_entries(subject, Nid::COMMONNAME).into_iter().next(),
+            organization: name_entries(subject, Nid::ORGANIZATIONNAME).into_iter().next(),
+            organizational_units,
+            subject_alt_names,
+            serial,
+            not_before: to_datetime(cert.not_before())?,
+            not_after: to_datetime(cert.not_after())?,
+            key_algorithm: key_algorithm(&cert)?,
+            node_ou_role,
+            attributes: fabric_attributes(&cert)?,
+        })
+    }
+
+    /// Look up a Fabric CA attribute
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    /// Enrollment ID from `hf.EnrollmentID`, falling back to the common name
+    pub fn enrollment_id(&self) -> Option<&str> {
+        self.attribute("hf.EnrollmentID")
+            .or(self.common_name.as_deref())
+    }
+
+    /// Whether `at` falls inside the validity window
+    pub fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.not_before <= at && at <= self.not_after
+    }
+}
+
+impl FabricIdentity {
+    /// Parsed view of the identity's certificate
+    pub fn certificate_info(&self) -> Result<CertificateInfo> {
+        CertificateInfo::from_pem(&self.certificate)
+    }
+}
+
+fn cert_err(e: impl std::fmt::Display) -> FabricCoreError {
+    FabricCoreError::InvalidCertificate(e.to_string())
+}
+
+fn general_name(name: &GeneralNameRef) -> Option<String> {
+    if let Some(dns) = name.dnsname() {
+        return Some(format!("DNS:{}", dns));
+    }
+    if let Some(ip) = name.ipaddress() {
+        let addr = match ip.len() {
+            4 => IpAddr::from(<[u8; 4]>::try_from(ip).ok()?),
+            16 => IpAddr::from(<[u8; 16]>::try_from(ip).ok()?),
+            _ => return None,
+        };
+        return Some(format!("IP:{}", addr));
+    }
+    if let Some(email) = name.email() {
+        return Some(format!("email:{}", email));
+    }
+    name.uri().map(|uri| format!("URI:{}", uri))
+}
+
+pub(crate) fn to_datetime(time: &Asn1TimeRef) -> Result<DateTime<Utc>> {
+    let epoch = Asn1Time::from_unix(0).map_err(cert_err)?;
+    let diff = epoch.diff(time).map_err(cert_err)?;
+    let seconds = i64::from(diff.days) * 86_400 + i64::from(diff.secs);
+    Utc.timestamp_opt(seconds, 0)
+        .single()
+        .ok_or_else(|| cert_err("timestamp out of range"))
+}
+
+fn key_algorithm(cert: &X509) -> Result<String> {
+    let key = cert.public_key().map_err(cert_err)?;
+    Ok(match key.id() {
+        Id::EC => {
+            let curve = key
+                .ec_key()
+                .ok()
+                .and_then(|ec| ec.group().curve_name())
+                .map(|nid| match nid {
+                    Nid::X9_62_PRIME256V1 => "P-256".to_string(),
+                    Nid::SECP384R1 => "P-384".to_string(),
+                    Nid::SECP521R1 => "P-521".to_string(),
+                    other => other.short_name().unwrap_or("unknown").to_string(),
+                })
+                .unwrap_or_else(|| "unknown curve".to_string());
+            format!("ECDSA {}", curve)
+        }
+        Id::RSA => format!("RSA {}", key.bits()),
+        Id::ED25519 => "Ed25519".to_string(),
+        other => format!("unknown ({})", other.as_raw()),
+    })
+}
+
+/// Decode the JSON attribute extension written by Fabric CA
+fn fabric_attributes(cert: &X509) -> Result<BTreeMap<String, String>> {
+    let der = cert.to_der().map_err(cert_err)?;
+    let parsed = x509_certificate::X509Certificate::from_der(der).map_err(cert_err)?;
+    let extension = parsed
+        .iter_extensions()
+        .find(|ext| ext.id.as_ref() == FABRIC_ATTRS_OID_DER);
+
+    match extension {
+        Some(ext) => {
+            let attrs: FabricAttributes = serde_json::from_slice(&ext.value.to_bytes())
+                .map_err(|e| cert_err(format!("malformed Fabric attributes: {}", e)))?;
+            Ok(attrs.attrs)
+        }
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{CertSpec, TestCert};
+
+    #[test]
+    fn test_certificate_info_fields() {
+        let root = TestCert::root("Org1 CA");
+        let leaf = root.issue(CertSpec {
+            common_name: "peer0",
+            organizational_units: vec!["org1", "peer"],
+            dns_names: vec!["peer0.org1.example.com", "10.0.0.5"],
+            ..Default::default()
+        });
+
+        let info = CertificateInfo::from_pem(&leaf.cert_pem()).unwrap();
+        assert_eq!(info.common_name.as_deref(), Some("peer0"));
+        assert!(info.subject.contains("CN=peer0"));
+        assert!(info.issuer.contains("CN=Org1 CA"));
+        assert_eq!(info.organizational_units, vec!["org1", "peer"]);
+        assert_eq!(info.node_ou_role.as_deref(), Some("peer"));
+        assert_eq!(
+            info.subject_alt_names,
+            vec!["DNS:peer0.org1.example.com", "IP:10.0.0.5"]
+        );
+        assert_eq!(info.key_algorithm, "ECDSA P-256");
+        assert!(info.is_valid_at(Utc::now()));
+        assert!(!info.is_valid_at(Utc::now() + chrono::Duration::days(400)));
+
+        let expected_serial = leaf
+            .cert
+            .serial_number()
+            .to_bn()
+            .unwrap()
+            .to_hex_str()
+            .unwrap()
+            .to_lowercase();
+        assert_eq!(info.serial, expected_serial);
+        assert!(info.attributes.is_empty());
+        assert_eq!(info.enrollment_id(), Some("peer0"));
+    }
+
+    #[test]
+    fn test_certificate_info_fabric_attributes() {
+        let attrs = br#"{"attrs":{"hf.Affiliation":"org1.department1","hf.EnrollmentID":"appUser","hf.Type":"client","movie.admin":"true"}}"#;
+        let root = TestCert::root("Org1 CA");
+        let leaf = root.issue(CertSpec {
+            common_name: "appUser",
+            extensions: vec![(FABRIC_ATTRS_OID, attrs.to_vec())],
+            ..Default::default()
+        });
+
+        let info = CertificateInfo::from_pem(&leaf.cert_pem()).unwrap();
+        assert_eq!(info.node_ou_role.as_deref(), Some("client"));
+        assert_eq!(info.enrollment_id(), Some("appUser"));
+        assert_eq!(info.attribute("hf.Type"), Some("client"));
+        assert_eq!(info.attribute("movie.admin"), Some("true"));
+        assert_eq!(info.attributes.len(), 4);
+    }
+
+    #[test]
+    fn test_certificate_info_rejects_garbage() {
+        assert!(CertificateInfo::from_pem("not a certificate").is_err());
+
+        let root = TestCert::root("Org1 CA");
+        let leaf = root.issue(CertSpec {
+            extensions: vec![(FABRIC_ATTRS_OID, b"{not json".to_vec())],
+            ..Default::default()
+        });
+        assert!(CertificateInfo::from_pem(&leaf.cert_pem()).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+/// Typed clients for the chaincode in `chaincode/`
+pub use crate::error::ValidationError;
+use crate::error::{FabricCoreError, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+
+pub mod movie;
+
+/// Envelope returned by the contracts' write operations
+///
+/// A failed operation still commits, with `success` false and no state
+/// changes, so the envelope has to be checked; `into_result` does that.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationResponse<T> {
+    pub success: bool,
+    pub message: String,
+    pub data: Option<T>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub errors: Vec<ValidationError>,
+    pub txn_id: String,
+    pub timestamp: String,
+}
+
+impl<T> OperationResponse<T> {
+    /// The operation's data, or `ContractError` if the contract refused it
+    pub fn into_result(self) -> Result<T> {
+        match self.data {
+            Some(data) if self.success => Ok(data),
+            _ => Err(FabricCoreError::ContractError {
+                message: self.message,
+                errors: self.errors,
+                transaction_id: self.txn_id,
+            }),
+        }
+    }
+}
+
+/// Go encodes nil slices and maps as `null`
+pub(crate) fn null_as_default<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::deserialize(deserializer)?.unwrap_or_default())
+}
@@ -0,0 +1,473 @@
+/// Typed client for the movie chaincode (`chaincode/movie`)
+use super::null_as_default;
+pub use super::{OperationResponse, ValidationError};
+use crate::error::{FabricCoreError, Result};
+use crate::fabric::{ChaincodeArgs, FabricNetworkClient, FabricNetworkClientExt};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Channel the movie chaincode is deployed on
+pub const CHANNEL_ID: &str = "movies";
+
+/// Chaincode ID of the movie contract
+pub const CHAINCODE_ID: &str = "movie-chaincode";
+
+/// Lifecycle status of a content request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestStatus {
+    #[default]
+    PendingReview,
+    Approved,
+    Rejected,
+    InProgress,
+    /// A status added to the contract after this client
+    #[serde(other)]
+    Unknown,
+}
+
+/// A user submission to add a movie, keyed by IMDb ID
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentRequest {
+    pub imdb_id: String,
+    pub request_id: String,
+    pub doc_type: String,
+    pub title: String,
+    /// Empty if not provided
+    pub director: String,
+    /// 0 if not provided
+    pub release_year: i32,
+    #[serde(deserialize_with = "null_as_default")]
+    pub genres: Vec<String>,
+    pub description: String,
+    pub submitter_id: String,
+    pub notes: String,
+    /// Torrent hashes by source or quality, e.g. `primary` or `720p`
+    #[serde(deserialize_with = "null_as_default")]
+    pub torrent_hashes: HashMap<String, String>,
+    pub status: RequestStatus,
+    /// RFC 3339
+    pub submitted_at: String,
+    /// Empty until reviewed
+    pub reviewed_by: String,
+    pub reviewed_at: String,
+    pub rejection_reason: String,
+    pub version: i64,
+}
+
+/// An approved movie in the catalog, keyed by IMDb ID
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Movie {
+    pub imdb_id: String,
+    pub movie_id: String,
+    pub doc_type: String,
+    pub title: String,
+    pub director: String,
+    pub release_year: i32,
+    #[serde(deserialize_with = "null_as_default")]
+    pub genres: Vec<String>,
+    pub description: String,
+    #[serde(deserialize_with = "null_as_default")]
+    pub torrent_hashes: HashMap<String, String>,
+    /// Bytes
+    pub file_size: i64,
+    /// Minutes, 0 for metadata-only entries
+    pub duration: i32,
+    pub approved_by: String,
+    pub approved_at: String,
+    /// Content request this movie was approved from
+    pub request_id: String,
+    pub submitter_id: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub version: i64,
+    /// Individual 1-5 star ratings
+    #[serde(deserialize_with = "null_as_default")]
+    pub ratings: Vec<i32>,
+    pub average_rating: f64,
+}
+
+/// Arguments of `SubmitContentRequest`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContentSubmission {
+    pub imdb_id: String,
+    pub title: String,
+    pub director: String,
+    /// 0 if unknown
+    pub release_year: i32,
+    pub genres: Vec<String>,
+    pub description: String,
+    pub submitter_id: String,
+    pub notes: String,
+    /// Stored as the `primary` torrent hash when not empty
+    pub torrent_hash: String,
+}
+
+/// Result of `ApproveContentRequest`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Approval {
+    pub content_request: ContentRequest,
+    pub movie: Movie,
+}
+
+/// One version of a content request from the key's ledger history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestVersion {
+    pub value: ContentRequest,
+    #[serde(default)]
+    pub timestamp: Option<LedgerTimestamp>,
+    #[serde(default)]
+    pub is_delete: bool,
+}
+
+/// Protobuf timestamp as Go's `encoding/json` writes it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LedgerTimestamp {
+    pub seconds: i64,
+    pub nanos: i32,
+}
+
+impl LedgerTimestamp {
+    pub fn to_datetime(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_opt(self.seconds, self.nanos.max(0) as u32)
+            .single()
+    }
+}
+
+/// Movie contract calls over any `FabricNetworkClient`
+///
+/// Write operations return `ContractError` when the contract rejects them,
+/// with the validation errors it reported.
+pub struct MovieContract<'a, C: FabricNetworkClient + ?Sized> {
+    client: &'a C,
+    channel_id: String,
+    chaincode_id: String,
+}
+
+impl<'a, C: FabricNetworkClient + ?Sized> MovieContract<'a, C> {
+    /// Contract at `movie-chaincode` on the `movies` channel
+    pub fn new(client: &'a C) -> Self {
+        Self {
+            client,
+            channel_id: CHANNEL_ID.to_string(),
+            chaincode_id: CHAINCODE_ID.to_string(),
+        }
+    }
+
+    /// Use a different channel or chaincode ID
+    pub fn with_chaincode(mut self, channel_id: &str, chaincode_id: &str) -> Self {
+        self.channel_id = channel_id.to_string();
+        self.chaincode_id = chaincode_id.to_string();
+        self
+    }
+
+    /// Submit a movie for review
+    pub async fn submit_content_request(
+        &self,
+        submission: &ContentSubmission,
+    ) -> Result<ContentRequest> {
+        let args = ChaincodeArgs::new()
+            .with_string("imdb_id", &submission.imdb_id)
+            .with_string("title", &submission.title)
+            .with_string("director", &submission.director)
+            .with_value("release_year", &submission.release_year)?
+            .with_value("genres", &submission.genres)?
+            .with_string("description", &submission.description)
+            .with_string("submitter_id", &submission.submitter_id)
+            .with_string("notes", &submission.notes)
+            .with_string("torrent_hash", &submission.torrent_hash);
+        self.invoke("SubmitContentRequest", args).await
+    }
+
+    /// Approve a pending request, creating its movie entry
+    pub async fn approve_content_request(
+        &self,
+        imdb_id: &str,
+        moderator_id: &str,
+    ) -> Result<Approval> {
+        let args = ChaincodeArgs::new()
+            .with_string("imdb_id", imdb_id)
+            .with_string("moderator_id", moderator_id);
+        self.invoke("ApproveContentRequest", args).await
+    }
+
+    /// Reject a request with a reason
+    pub async fn reject_content_request(
+        &self,
+        imdb_id: &str,
+        moderator_id: &str,
+        reason: &str,
+    ) -> Result<ContentRequest> {
+        let args = ChaincodeArgs::new()
+            .with_string("imdb_id", imdb_id)
+            .with_string("moderator_id", moderator_id)
+            .with_string("rejection_reason", reason);
+        self.invoke("RejectContentRequest", args).await
+    }
+
+    /// Approved movies, newest first
+    pub async fn query_all(&self) -> Result<Vec<Movie>> {
+        self.query("QueryAll", ChaincodeArgs::new()).await
+    }
+
+    /// Movies whose title contains `title`, ignoring case; the contract
+    /// clamps `limit` to 1..=100
+    pub async fn search_by_title(&self, title: &str, limit: u32) -> Result<Vec<Movie>> {
+        let args = ChaincodeArgs::new()
+            .with_string("title", title)
+            .with_value("limit", &limit)?;
+        self.query("SearchByTitle", args).await
+    }
+
+    /// All versions of a content request, oldest first
+    pub async fn get_request_history(&self, imdb_id: &str) -> Result<Vec<RequestVersion>> {
+        let args = ChaincodeArgs::new().with_string("imdb_id", imdb_id);
+        self.query("GetRequestHistory", args).await
+    }
+
+    pub async fn get_movie_by_imdb(&self, imdb_id: &str) -> Result<Movie> {
+        let args = ChaincodeArgs::new().with_string("imdb_id", imdb_id);
+        self.client
+            .query_typed(
+                &self.channel_id,
+                &self.chaincode_id,
+                "GetMovieByIMDB",
+                &args,
+            )
+            .await
+    }
+
+    /// Query returning a Go slice, which is `null` when empty
+    async fn query<T: DeserializeOwned>(
+        &self,
+        function: &str,
+        args: ChaincodeArgs,
+    ) -> Result<Vec<T>> {
+        let records: Option<Vec<T>> = self
+            .client
+            .query_typed(&self.channel_id, &self.chaincode_id, function, &args)
+            .await?;
+        Ok(records.unwrap_or_default())
+    }
+
+    async fn invoke<T: DeserializeOwned>(&self, function: &str, args: ChaincodeArgs) -> Result<T> {
+        let result = self
+            .client
+            .invoke_chaincode(
+                &self.channel_id,
+                &self.chaincode_id,
+                function,
+                args.to_fabric_args(),
+            )
+            .await?;
+        let response: OperationResponse<T> =
+            serde_json::from_value(result.payload).map_err(|e| {
+                FabricCoreError::InvocationError(format!(
+                    "Cannot decode {} response: {}",
+                    function, e
+                ))
+            })?;
+        response.into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockResponse, MockServer};
+
+    fn operation(success: bool, data: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "transactionId": "tx1",
+            "result": {
+                "success": success,
+                "message": if success { "Operation successful" } else { "IMDb ID already exists" },
+                "data": data,
+                "errors": if success { serde_json::json!([]) } else { serde_json::json!([
+                    {"field": "imdb_id", "message": "already submitted", "code": "duplicate"}
+                ]) },
+                "txn_id": "tx1",
+                "timestamp": "2025-11-01T12:00:00Z",
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_submit_sends_contract_args_and_reports_rejection() {
+        let server = MockServer::start(|request| {
+            let args = request.json()["args"].clone();
+            let duplicate = args[0] == "tt0113277";
+            let data = serde_json::json!({
+                "imdb_id": args[0],
+                "request_id": "r1",
+                "title": args[1],
+                "release_year": 1995,
+                "genres": null,
+                "torrent_hashes": {"primary": "abc"},
+                "status": "pending_review",
+                "version": 1,
+            });
+            MockResponse::json(200, operation(!duplicate, data))
+        })
+        .await;
+        let client = server.connected_client().await;
+        let contract = MovieContract::new(&client);
+        let mut submission = ContentSubmission {
+            imdb_id: "tt0111161".to_string(),
+            title: "The Shawshank Redemption".to_string(),
+            release_year: 1994,
+            genres: vec!["Drama".to_string()],
+            torrent_hash: "abc".to_string(),
+            ..Default::default()
+        };
+
+        let request = contract.submit_content_request(&submission).await.unwrap();
+        assert_eq!(request.status, RequestStatus::PendingReview);
+        assert_eq!(request.torrent_hashes["primary"], "abc");
+        assert!(request.genres.is_empty());
+        let sent = &server.requests()[0];
+        assert!(sent.path.ends_with("/movie-chaincode/SubmitContentRequest"));
+        assert_eq!(
+            sent.json()["args"],
+            serde_json::json!([
+                "tt0111161",
+                "The Shawshank Redemption",
+                "",
+                "1994",
+                r#"["Drama"]"#,
+                "",
+                "",
+                "",
+                "abc"
+            ])
+        );
+
+        submission.imdb_id = "tt0113277".to_string();
+        let err = contract
+            .submit_content_request(&submission)
+            .await
+            .unwrap_err();
+        match err {
+            FabricCoreError::ContractError {
+                message,
+                errors,
+                transaction_id,
+            } => {
+                assert_eq!(message, "IMDb ID already exists");
+                assert_eq!(errors[0].code, "duplicate");
+                assert_eq!(transaction_id, "tx1");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_moderation_sends_moderator_and_reason() {
+        let server = MockServer::start(|request| {
+            let args = request.json()["args"].clone();
+            let data = match request.path.rsplit('/').next().unwrap() {
+                "ApproveContentRequest" => serde_json::json!({
+                    "content_request": {"imdb_id": args[0], "status": "approved"},
+                    "movie": {"imdb_id": args[0], "title": "Heat"},
+                }),
+                _ => serde_json::json!({
+                    "imdb_id": args[0],
+                    "status": "rejected",
+                    "rejection_reason": args[2],
+                }),
+            };
+            MockResponse::json(200, operation(true, data))
+        })
+        .await;
+        let client = server.connected_client().await;
+        let contract = MovieContract::new(&client);
+
+        let approval = contract
+            .approve_content_request("tt0113277", "mod1")
+            .await
+            .unwrap();
+        assert_eq!(approval.content_request.status, RequestStatus::Approved);
+        assert_eq!(approval.movie.title, "Heat");
+        let rejected = contract
+            .reject_content_request("tt0111161", "mod1", "duplicate upload")
+            .await
+            .unwrap();
+        assert_eq!(rejected.status, RequestStatus::Rejected);
+
+        let requests = server.requests();
+        assert!(requests[0]
+            .path
+            .ends_with("/movie-chaincode/ApproveContentRequest"));
+        assert_eq!(
+            requests[0].json()["args"],
+            serde_json::json!(["tt0113277", "mod1"])
+        );
+        assert!(requests[1]
+            .path
+            .ends_with("/movie-chaincode/RejectContentRequest"));
+        assert_eq!(
+            requests[1].json()["args"],
+            serde_json::json!(["tt0111161", "mod1", "duplicate upload"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queries_decode_movies_and_history() {
+        let server = MockServer::start(|request| {
+            let movie = serde_json::json!({
+                "imdb_id": "tt0113277",
+                "title": "Heat",
+                "ratings": [5, 4],
+                "average_rating": 4.5,
+            });
+            let result = match request.path.rsplit('/').next().unwrap() {
+                "QueryAll" => serde_json::json!(null),
+                "SearchByTitle" => serde_json::json!([movie]),
+                "GetMovieByIMDB" => movie,
+                _ => serde_json::json!([
+                    {
+                        "value": {"imdb_id": "tt0113277", "status": "approved"},
+                        "timestamp": {"seconds": 1700000000, "nanos": 5},
+                        "is_delete": false,
+                    },
+                    {"value": {"imdb_id": "tt0113277", "status": "archived"}},
+                ]),
+            };
+            MockResponse::json(200, serde_json::json!({ "result": result }))
+        })
+        .await;
+        let client = server.connected_client().await;
+        let contract = MovieContract::new(&client).with_chaincode("films", "movie-cc");
+
+        assert!(contract.query_all().await.unwrap().is_empty());
+        let found = contract.search_by_title("heat", 5).await.unwrap();
+        assert_eq!(found[0].ratings, vec![5, 4]);
+        assert_eq!(
+            server.requests()[1].json()["args"],
+            serde_json::json!(["heat", "5"])
+        );
+        let movie = contract.get_movie_by_imdb("tt0113277").await.unwrap();
+        assert_eq!(movie.average_rating, 4.5);
+        let history = contract.get_request_history("tt0113277").await.unwrap();
+        assert_eq!(history[0].value.status, RequestStatus::Approved);
+        assert_eq!(history[1].value.status, RequestStatus::Unknown);
+        assert_eq!(
+            history[0]
+                .timestamp
+                .unwrap()
+                .to_datetime()
+                .unwrap()
+                .timestamp(),
+            1_700_000_000
+        );
+        assert!(server
+            .requests()
+            .iter()
+            .all(|r| r.path.starts_with("/channels/films/chaincodes/movie-cc/")));
+    }
+}
@@ -0,0 +1,454 @@
+/// Certificate revocation lists and revocation checking
+use crate::ca::FabricCaClient;
+use crate::certificate::to_datetime;
+use crate::crypto::{name_to_string, FabricIdentity};
+use crate::error::{FabricCoreError, Result};
+use crate::msp::MspDirectory;
+use chrono::{DateTime, Utc};
+use openssl::asn1::Asn1IntegerRef;
+use openssl::hash::MessageDigest;
+use openssl::x509::{CrlStatus, X509Crl, X509};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+const CRL_PEM_HEADER: &str = "-----BEGIN X509 CRL-----";
+
+/// A certificate listed in a CRL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevokedEntry {
+    /// Lowercase hex serial number, as Fabric CA reports it
+    pub serial: String,
+    pub revoked_at: DateTime<Utc>,
+}
+
+/// A parsed X.509 certificate revocation list
+pub struct CertificateRevocationList {
+    crl: X509Crl,
+    /// Distinguished name of the issuing CA
+    pub issuer: String,
+    pub last_update: DateTime<Utc>,
+    pub next_update: Option<DateTime<Utc>>,
+    stale_warned: AtomicBool,
+}
+
+impl CertificateRevocationList {
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        Self::from_crl(X509Crl::from_pem(pem.as_bytes()).map_err(crl_err)?)
+    }
+
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        Self::from_crl(X509Crl::from_der(der).map_err(crl_err)?)
+    }
+
+    fn from_crl(crl: X509Crl) -> Result<Self> {
+        Ok(Self {
+            issuer: name_to_string(crl.issuer_name()),
+            last_update: to_datetime(crl.last_update())?,
+            next_update: crl.next_update().map(to_datetime).transpose()?,
+            crl,
+            stale_warned: AtomicBool::new(false),
+        })
+    }
+
+    pub fn to_pem(&self) -> Result<String> {
+        String::from_utf8(self.crl.to_pem().map_err(crl_err)?).map_err(crl_err)
+    }
+
+    /// Every revoked certificate in the list
+    pub fn revoked(&self) -> Result<Vec<RevokedEntry>> {
+        self.crl
+            .get_revoked()
+            .map(|stack| {
+                stack
+                    .iter()
+                    .map(|revoked| {
+                        Ok(RevokedEntry {
+                            serial: serial_hex(revoked.serial_number())?,
+                            revoked_at: to_datetime(revoked.revocation_date())?,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_else(|| Ok(Vec::new()))
+    }
+
+    /// Whether the CA has published, or should have published, a newer list
+    pub fn is_stale_at(&self, at: DateTime<Utc>) -> bool {
+        self.next_update.is_some_and(|next| next < at)
+    }
+
+    /// Warn the first time the list is used past its next update
+    fn warn_if_stale(&self) {
+        if self.is_stale_at(Utc::now()) && !self.stale_warned.swap(true, Ordering::Relaxed) {
+            tracing::warn!("CRL from {} is past its next update", self.issuer);
+        }
+    }
+
+    /// Check the list's signature against a PEM CA bundle
+    ///
+    /// Only bundle certificates whose subject matches the CRL issuer are
+    /// tried. Returns `false` when none of them signed the list.
+    pub fn verify_signature(&self, ca_certificate_pem: &str) -> Result<bool> {
+        let ca_certs = X509::stack_from_pem(ca_certificate_pem.as_bytes())
+            .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))?;
+        for ca in ca_certs {
+            if name_to_string(ca.subject_name()) != self.issuer {
+                continue;
+            }
+            let key = ca
+                .public_key()
+                .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))?;
+            if self.crl.verify(&key).map_err(crl_err)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Revocation entry for a certificate issued by this list's CA
+    fn lookup(&self, cert: &X509) -> Result<Option<RevokedEntry>> {
+        if name_to_string(cert.issuer_name()) != self.issuer {
+            return Ok(None);
+        }
+        match self.crl.get_by_cert(cert) {
+            CrlStatus::Revoked(revoked) => Ok(Some(RevokedEntry {
+                serial: serial_hex(revoked.serial_number())?,
+                revoked_at: to_datetime(revoked.revocation_date())?,
+            })),
+            CrlStatus::NotRevoked | CrlStatus::RemoveFromCrl(_) => Ok(None),
+        }
+    }
+}
+
+impl fmt::Debug for CertificateRevocationList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertificateRevocationList")
+            .field("issuer", &self.issuer)
+            .field("last_update", &self.last_update)
+            .field("next_update", &self.next_update)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A set of CRLs with cached per-certificate lookups
+///
+/// Results are cached by certificate fingerprint until another CRL is
+/// added.
+#[derive(Debug, Default)]
+pub struct CrlStore {
+    crls: Vec<CertificateRevocationList>,
+    cache: Mutex<HashMap<Vec<u8>, Option<RevokedEntry>>>,
+}
+
+impl CrlStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the CRLs from an MSP directory's `crls/` folder
+    ///
+    /// Each list must be signed by one of the MSP's root or intermediate
+    /// CA certificates.
+    pub fn from_msp_dir(path: &Path) -> Result<Self> {
+        Self::from_msp(&MspDirectory::load(path)?)
+    }
+
+    pub(crate) fn from_msp(msp: &MspDirectory) -> Result<Self> {
+        let ca_bundle = msp
+            .cacerts
+            .iter()
+            .chain(msp.intermediatecerts.iter())
+            .map(|pem| pem.trim_end().to_string() + "\n")
+            .collect::<String>();
+
+        let mut store = Self::new();
+        for pem in &msp.crls {
+            for crl in parse_pem_bundle(pem)? {
+                if !crl.verify_signature(&ca_bundle)? {
+                    return Err(FabricCoreError::InvalidCrl(format!(
+                        "CRL from {} is not signed by an MSP CA certificate",
+                        crl.issuer
+                    )));
+                }
+                store.add(crl);
+            }
+        }
+        tracing::debug!("Loaded {} CRLs from {:?}", store.len(), msp.path);
+        Ok(store)
+    }
+
+    /// Load a PEM or DER CRL file
+    pub fn load_file(&mut self, path: &Path) -> Result<()> {
+        let content = std::fs::read(path)?;
+        match std::str::from_utf8(&content) {
+            Ok(pem) if pem.contains(CRL_PEM_HEADER) => self.add_pem(pem),
+            _ => {
+                self.add(CertificateRevocationList::from_der(&content)?);
+                Ok(())
+            }
+        }
+    }
+
+    /// Add every CRL in a PEM string
+    pub fn add_pem(&mut self, pem: &str) -> Result<()> {
+        for crl in parse_pem_bundle(pem)? {
+            self.add(crl);
+        }
+        Ok(())
+    }
+
+    /// Ask a Fabric CA to generate its current CRL and add it
+    pub async fn fetch_from_ca(
+        &mut self,
+        ca: &FabricCaClient,
+        registrar: &FabricIdentity,
+    ) -> Result<()> {
+        let pem = ca.generate_crl(registrar, &Default::default()).await?;
+        self.add_pem(&pem)
+    }
+
+    pub fn add(&mut self, crl: CertificateRevocationList) {
+        self.crls.push(crl);
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.crls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.crls.is_empty()
+    }
+
+    /// Revocation entry for the first certificate of a PEM string
+    pub fn is_revoked(&self, cert_pem: &str) -> Result<Option<RevokedEntry>> {
+        let cert = X509::from_pem(cert_pem.as_bytes())
+            .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))?;
+        self.revocation_of(&cert)
+    }
+
+    /// Fail with `CertificateRevoked` if any certificate in a PEM chain is
+    /// revoked
+    pub fn check(&self, cert_pem: &str) -> Result<()> {
+        let certs = X509::stack_from_pem(cert_pem.as_bytes())
+            .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))?;
+        for cert in &certs {
+            if let Some(entry) = self.revocation_of(cert)? {
+                return Err(FabricCoreError::CertificateRevoked {
+                    serial: entry.serial,
+                    revoked_at: entry.revoked_at.to_rfc3339(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn revocation_of(&self, cert: &X509) -> Result<Option<RevokedEntry>> {
+        let fingerprint = cert
+            .digest(MessageDigest::sha256())
+            .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))?
+            .to_vec();
+        if let Some(cached) = self
+            .cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&fingerprint)
+        {
+            return Ok(cached.clone());
+        }
+
+        let mut result = None;
+        for crl in &self.crls {
+            crl.warn_if_stale();
+            if let Some(entry) = crl.lookup(cert)? {
+                result = Some(entry);
+                break;
+            }
+        }
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(fingerprint, result.clone());
+        Ok(result)
+    }
+}
+
+impl FabricIdentity {
+    /// Fail with `CertificateRevoked` if the identity's certificate is listed
+    pub fn check_revocation(&self, crls: &CrlStore) -> Result<()> {
+        crls.check(&self.certificate)
+    }
+}
+
+fn parse_pem_bundle(pem: &str) -> Result<Vec<CertificateRevocationList>> {
+    let crls: Vec<_> = pem
+        .match_indices(CRL_PEM_HEADER)
+        .map(|(start, _)| CertificateRevocationList::from_pem(&pem[start..]))
+        .collect::<Result<_>>()?;
+    if crls.is_empty() {
+        return Err(crl_err("no CRL found in PEM data"));
+    }
+    Ok(crls)
+}
+
+fn serial_hex(serial: &Asn1IntegerRef) -> Result<String> {
+    serial
+        .to_bn()
+        .and_then(|bn| bn.to_hex_str().map(|hex| hex.to_lowercase()))
+        .map_err(crl_err)
+}
+
+fn crl_err(e: impl fmt::Display) -> FabricCoreError {
+    FabricCoreError::InvalidCrl(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{CertificateCheck, CryptoManager};
+    use crate::test_util::{CertSpec, TestCert};
+
+    #[test]
+    fn test_parse_crl() {
+        let root = TestCert::root("ca.org1.example.com");
+        let revoked = root.issue(CertSpec::default());
+        let crl = CertificateRevocationList::from_pem(&root.crl(&[&revoked])).unwrap();
+
+        assert_eq!(crl.issuer, name_to_string(root.cert.subject_name()));
+        assert!(!crl.is_stale_at(Utc::now()));
+        assert!(crl.is_stale_at(Utc::now() + chrono::Duration::days(60)));
+        assert!(crl.verify_signature(&root.cert_pem()).unwrap());
+        assert!(!crl
+            .verify_signature(&TestCert::root("ca.org1.example.com").cert_pem())
+            .unwrap());
+
+        let entries = crl.revoked().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].serial,
+            revoked
+                .cert
+                .serial_number()
+                .to_bn()
+                .unwrap()
+                .to_hex_str()
+                .unwrap()
+                .to_lowercase()
+        );
+
+        let reparsed = CertificateRevocationList::from_pem(&crl.to_pem().unwrap()).unwrap();
+        assert_eq!(reparsed.revoked().unwrap(), entries);
+    }
+
+    #[test]
+    fn test_revoked_certificate_is_rejected() {
+        let root = TestCert::root("ca.org1.example.com");
+        let moderator = root.issue(CertSpec {
+            common_name: "moderator",
+            ..Default::default()
+        });
+        let member = root.issue(CertSpec::default());
+        let mut crls = CrlStore::new();
+        crls.add_pem(&root.crl(&[&moderator])).unwrap();
+
+        // The chain itself is still valid; only the CRL catches it
+        let verification =
+            CryptoManager::verify_certificate(&moderator.cert_pem(), &root.cert_pem()).unwrap();
+        assert!(verification.is_valid());
+        assert!(matches!(
+            crls.check(&moderator.cert_pem()),
+            Err(FabricCoreError::CertificateRevoked { .. })
+        ));
+        crls.check(&member.cert_pem()).unwrap();
+        let verification = CryptoManager::verify_certificate_with_crls(
+            &moderator.cert_pem(),
+            &root.cert_pem(),
+            &crls,
+        )
+        .unwrap();
+        assert_eq!(
+            verification.failed_check,
+            Some(CertificateCheck::Revocation)
+        );
+        assert_eq!(verification.depth, Some(0));
+
+        // Repeated lookups are served from the cache
+        assert!(crls.is_revoked(&moderator.cert_pem()).unwrap().is_some());
+        assert_eq!(crls.cache.lock().unwrap().len(), 2);
+
+        // A CRL from another CA with the same serial does not apply
+        let other = TestCert::root("ca.org2.example.com");
+        let mut foreign = CrlStore::new();
+        foreign.add_pem(&other.crl(&[&moderator])).unwrap();
+        foreign.check(&moderator.cert_pem()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_rejects_identity_revoked_by_attached_crls() {
+        use crate::fabric::{FabricNetworkClient, KaleidoFabricClient};
+
+        let root = TestCert::root("ca.org1.example.com");
+        let revoked = root.issue(CertSpec::default());
+        let identity = FabricIdentity::new(
+            revoked.key_pem(),
+            revoked.public_key_pem(),
+            revoked.cert_pem(),
+            root.cert_pem(),
+            "user1".to_string(),
+            "Org1".to_string(),
+            "Org1MSP".to_string(),
+        );
+        let mut crls = CrlStore::new();
+        crls.add_pem(&root.crl(&[&revoked])).unwrap();
+
+        let mut client =
+            KaleidoFabricClient::from_kaleido_endpoint("https://gateway.example.com", "");
+        client.connect(&identity).await.unwrap();
+        client.disconnect().await.unwrap();
+        assert!(matches!(
+            client
+                .connect(&identity.with_crls(std::sync::Arc::new(crls)))
+                .await,
+            Err(FabricCoreError::CertificateRevoked { .. })
+        ));
+        assert!(!client.is_connected());
+    }
+
+    #[test]
+    fn test_load_crls_from_msp_dir_and_file() {
+        let dir = std::env::temp_dir().join(format!("fabric-crl-{}", uuid::Uuid::new_v4()));
+        let root = TestCert::root("ca.org1.example.com");
+        let revoked = root.issue(CertSpec::default());
+        std::fs::create_dir_all(dir.join("cacerts")).unwrap();
+        std::fs::create_dir_all(dir.join("crls")).unwrap();
+        std::fs::write(dir.join("cacerts/ca.pem"), root.cert_pem()).unwrap();
+        std::fs::write(dir.join("crls/crl.pem"), root.crl(&[&revoked])).unwrap();
+
+        let crls = CrlStore::from_msp_dir(&dir).unwrap();
+        assert_eq!(crls.len(), 1);
+        assert!(crls.is_revoked(&revoked.cert_pem()).unwrap().is_some());
+
+        let der = X509Crl::from_pem(root.crl(&[&revoked]).as_bytes())
+            .unwrap()
+            .to_der()
+            .unwrap();
+        std::fs::write(dir.join("crl.der"), der).unwrap();
+        let mut from_file = CrlStore::new();
+        from_file.load_file(&dir.join("crl.der")).unwrap();
+        assert!(from_file.is_revoked(&revoked.cert_pem()).unwrap().is_some());
+
+        // A CRL signed by a foreign CA is refused
+        let forged = TestCert::root("ca.org1.example.com");
+        std::fs::write(dir.join("crls/crl.pem"), forged.crl(&[&revoked])).unwrap();
+        assert!(matches!(
+            CrlStore::from_msp_dir(&dir),
+            Err(FabricCoreError::InvalidCrl(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
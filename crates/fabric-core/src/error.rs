@@ -1,7 +1,17 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, FabricCoreError>;
 
+/// A field that failed contract validation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationError {
+    pub field: String,
+    pub message: String,
+    /// e.g. `required`, `invalid_format`, `duplicate`
+    pub code: String,
+}
+
 #[derive(Debug, Error)]
 pub enum FabricCoreError {
     #[error("Cryptography error: {0}")]
@@ -28,12 +38,64 @@ pub enum FabricCoreError {
     #[error("Key management error: {0}")]
     KeyManagementError(String),
 
+    #[error("Identity file is encrypted; a passphrase is required")]
+    PassphraseRequired,
+
+    #[error("Invalid passphrase or corrupted identity file")]
+    InvalidPassphrase,
+
+    #[error("Invalid private key: {0}")]
+    InvalidPrivateKey(String),
+
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(String),
+
+    #[error("Private key does not match the certificate")]
+    KeyCertificateMismatch,
+
+    #[error("MSP ID {mspid} does not match certificate organization {organization}")]
+    MspIdMismatch { mspid: String, organization: String },
+
+    #[error("Certificate does not chain to the CA certificate: {0}")]
+    UntrustedCertificate(String),
+
+    #[error("Certificate expired at {0}")]
+    CertificateExpired(String),
+
+    #[error("Certificate is not valid until {0}")]
+    CertificateNotYetValid(String),
+
+    #[error("Invalid certificate revocation list: {0}")]
+    InvalidCrl(String),
+
+    #[error("Invalid backup share: {0}")]
+    InvalidBackupShare(String),
+
+    #[error("Certificate {serial} was revoked at {revoked_at}")]
+    CertificateRevoked { serial: String, revoked_at: String },
+
+    #[error("Certificate authority error: {0}")]
+    CaError(String),
+
     #[error("Query error: {0}")]
     QueryError(String),
 
     #[error("Invocation error: {0}")]
     InvocationError(String),
 
+    #[error("Contract error: {message}")]
+    ContractError {
+        message: String,
+        errors: Vec<ValidationError>,
+        transaction_id: String,
+    },
+
+    #[error("Transaction {transaction_id} was not committed in time")]
+    CommitTimeout { transaction_id: String },
+
+    #[error("Event stream error: {0}")]
+    EventError(String),
+
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
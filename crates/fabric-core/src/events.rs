@@ -0,0 +1,470 @@
+/// Chaincode and block event subscriptions with resumable checkpoints
+use crate::error::Result;
+use crate::history::{BlockSource, TransactionRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio_stream::Stream;
+
+/// Events delivered by `FabricNetworkClient::subscribe_events`
+pub type EventStream = Pin<Box<dyn Stream<Item = Result<FabricEvent>> + Send>>;
+
+/// Committed blocks of a channel, in order
+pub(crate) type BlockStream = Pin<Box<dyn Stream<Item = Result<BlockEvent>> + Send>>;
+
+/// Event set by a chaincode transaction, e.g. `ContentApproved`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChaincodeEvent {
+    pub channel_id: String,
+    pub chaincode_id: String,
+    pub event_name: String,
+    pub transaction_id: String,
+    pub block_number: u64,
+    pub payload: Vec<u8>,
+}
+
+impl ChaincodeEvent {
+    /// Payload parsed as JSON, the format the movie chaincode emits
+    pub fn payload_json(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::from_slice(&self.payload)?)
+    }
+}
+
+/// A committed block with its transactions and chaincode events
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockEvent {
+    pub channel_id: String,
+    pub block_number: u64,
+    /// Endorser transactions, in block order
+    pub transactions: Vec<TransactionRecord>,
+    /// Events of the block's valid transactions, in block order
+    pub chaincode_events: Vec<ChaincodeEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FabricEvent {
+    Chaincode(ChaincodeEvent),
+    Block(BlockEvent),
+}
+
+/// What a subscription delivers
+///
+/// A block's chaincode events come first, followed by the block itself
+/// when `blocks` is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventFilter {
+    /// Chaincode whose events are delivered, `None` for no chaincode events
+    pub chaincode_id: Option<String>,
+    /// Only chaincode events with this name
+    pub event_name: Option<String>,
+    /// Deliver a `FabricEvent::Block` for every block
+    #[serde(default)]
+    pub blocks: bool,
+    /// Block to start from when there is no checkpoint, defaulting to the
+    /// next block to be committed
+    pub start_block: Option<u64>,
+}
+
+impl EventFilter {
+    /// All events of a chaincode
+    pub fn chaincode(chaincode_id: &str) -> Self {
+        Self {
+            chaincode_id: Some(chaincode_id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Every block, without chaincode events
+    pub fn blocks() -> Self {
+        Self {
+            blocks: true,
+            ..Default::default()
+        }
+    }
+
+    /// Restrict chaincode events to one name
+    pub fn named(mut self, event_name: &str) -> Self {
+        self.event_name = Some(event_name.to_string());
+        self
+    }
+
+    fn matches(&self, event: &ChaincodeEvent) -> bool {
+        self.chaincode_id
+            .as_ref()
+            .is_some_and(|id| *id == event.chaincode_id)
+            && self
+                .event_name
+                .as_ref()
+                .is_none_or(|name| *name == event.event_name)
+    }
+}
+
+/// Position a subscription resumes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Next block to read
+    pub block_number: u64,
+    /// Last transaction of `block_number` whose events were delivered
+    pub transaction_id: Option<String>,
+}
+
+/// Storage for a subscription's checkpoint
+pub trait Checkpointer: Send + Sync {
+    fn load(&self) -> Result<Option<Checkpoint>>;
+
+    fn save(&self, checkpoint: &Checkpoint) -> Result<()>;
+}
+
+/// Checkpoint kept in memory, lost on restart
+#[derive(Debug, Default)]
+pub struct MemoryCheckpointer(Mutex<Option<Checkpoint>>);
+
+impl Checkpointer for MemoryCheckpointer {
+    fn load(&self) -> Result<Option<Checkpoint>> {
+        Ok(self.0.lock().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+
+    fn save(&self, checkpoint: &Checkpoint) -> Result<()> {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(checkpoint.clone());
+        Ok(())
+    }
+}
+
+/// Checkpoint stored as a JSON file
+///
+/// Writes go through a temporary file and a rename, so a crash never
+/// leaves a truncated checkpoint behind.
+#[derive(Debug, Clone)]
+pub struct FileCheckpointer {
+    path: PathBuf,
+}
+
+impl FileCheckpointer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl Checkpointer for FileCheckpointer {
+    fn load(&self) -> Result<Option<Checkpoint>> {
+        match std::fs::read(&self.path) {
+            Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, checkpoint: &Checkpoint) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, serde_json::to_vec(checkpoint)?)?;
+        std::fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// First block to read and the transaction to resume after, from the saved
+/// checkpoint, the filter's start block or the current ledger height
+pub(crate) async fn resume_position(
+    source: &dyn BlockSource,
+    channel_id: &str,
+    filter: &EventFilter,
+    checkpointer: &dyn Checkpointer,
+) -> Result<(u64, Option<String>)> {
+    if let Some(checkpoint) = checkpointer.load()? {
+        return Ok((checkpoint.block_number, checkpoint.transaction_id));
+    }
+    let start = match filter.start_block {
+        Some(block) => block,
+        None => source.height(channel_id).await?,
+    };
+    Ok((start, None))
+}
+
+/// Read blocks from `start` on, waiting `interval` whenever the reader
+/// has caught up with the ledger height
+///
+/// The stream ends after the first error.
+pub(crate) fn poll_blocks(
+    source: Arc<dyn BlockSource>,
+    channel_id: String,
+    start: u64,
+    interval: Duration,
+) -> BlockStream {
+    let (sender, receiver) = tokio::sync::mpsc::channel(16);
+    tokio::spawn(async move {
+        let mut next = start;
+        let mut height = 0;
+        loop {
+            if next >= height {
+                if sender.is_closed() {
+                    return;
+                }
+                match source.height(&channel_id).await {
+                    Ok(current) => height = current,
+                    Err(e) => {
+                        let _ = sender.send(Err(e)).await;
+                        return;
+                    }
+                }
+                if next >= height {
+                    tokio::time::sleep(interval).await;
+                    continue;
+                }
+            }
+            let block = source.block(&channel_id, next).await;
+            let failed = block.is_err();
+            if sender.send(block).await.is_err() || failed {
+                return;
+            }
+            next += 1;
+        }
+    });
+    Box::pin(tokio_stream::wrappers::ReceiverStream::new(receiver))
+}
+
+/// Filter blocks into events, checkpointing as they are consumed
+///
+/// An event's checkpoint is saved when the next event is requested, so an
+/// event whose handling was cut short by a crash is delivered again after
+/// a restart. `resume_after` skips the events of the first block up to and
+/// including that transaction.
+pub(crate) fn event_stream(
+    blocks: BlockStream,
+    filter: EventFilter,
+    resume_after: Option<String>,
+    checkpointer: Arc<dyn Checkpointer>,
+) -> EventStream {
+    Box::pin(Subscription {
+        blocks,
+        filter,
+        checkpointer,
+        resume_after,
+        queue: VecDeque::new(),
+        delivered: None,
+        done: false,
+    })
+}
+
+struct Subscription {
+    blocks: BlockStream,
+    filter: EventFilter,
+    checkpointer: Arc<dyn Checkpointer>,
+    resume_after: Option<String>,
+    /// Events of the current block, each with the checkpoint after it
+    queue: VecDeque<(FabricEvent, Checkpoint)>,
+    /// Checkpoint of the last event handed out, not yet saved
+    delivered: Option<Checkpoint>,
+    done: bool,
+}
+
+impl Subscription {
+    fn enqueue(&mut self, block: BlockEvent) -> Result<()> {
+        let skip = match self.resume_after.take() {
+            Some(tx) => block
+                .chaincode_events
+                .iter()
+                .position(|event| event.transaction_id == tx)
+                .map_or(0, |index| index + 1),
+            None => 0,
+        };
+        for event in block.chaincode_events.iter().skip(skip) {
+            if self.filter.matches(event) {
+                let checkpoint = Checkpoint {
+                    block_number: block.block_number,
+                    transaction_id: Some(event.transaction_id.clone()),
+                };
+                self.queue
+                    .push_back((FabricEvent::Chaincode(event.clone()), checkpoint));
+            }
+        }
+
+        let block_done = Checkpoint {
+            block_number: block.block_number + 1,
+            transaction_id: None,
+        };
+        if self.filter.blocks {
+            self.queue
+                .push_back((FabricEvent::Block(block), block_done));
+        } else if let Some((_, checkpoint)) = self.queue.back_mut() {
+            *checkpoint = block_done;
+        } else {
+            self.checkpointer.save(&block_done)?;
+        }
+        Ok(())
+    }
+
+    fn fail(&mut self, error: crate::FabricCoreError) -> Poll<Option<Result<FabricEvent>>> {
+        self.done = true;
+        Poll::Ready(Some(Err(error)))
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Result<FabricEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if let Some(checkpoint) = this.delivered.take() {
+            if let Err(e) = this.checkpointer.save(&checkpoint) {
+                return this.fail(e);
+            }
+        }
+        loop {
+            if let Some((event, checkpoint)) = this.queue.pop_front() {
+                this.delivered = Some(checkpoint);
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            match ready!(this.blocks.as_mut().poll_next(cx)) {
+                Some(Ok(block)) => {
+                    if let Err(e) = this.enqueue(block) {
+                        return this.fail(e);
+                    }
+                }
+                Some(Err(e)) => return this.fail(e),
+                None => this.done = true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    fn block(number: u64, events: &[(&str, &str)]) -> BlockEvent {
+        BlockEvent {
+            channel_id: "movies".to_string(),
+            block_number: number,
+            transactions: Vec::new(),
+            chaincode_events: events
+                .iter()
+                .map(|(tx, name)| ChaincodeEvent {
+                    channel_id: "movies".to_string(),
+                    chaincode_id: "movie-chaincode".to_string(),
+                    event_name: name.to_string(),
+                    transaction_id: tx.to_string(),
+                    block_number: number,
+                    payload: b"{}".to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    fn ledger() -> BlockStream {
+        Box::pin(tokio_stream::iter(vec![
+            Ok(block(
+                5,
+                &[("t1", "ContentRequested"), ("t2", "ContentApproved")],
+            )),
+            Ok(block(6, &[])),
+            Ok(block(7, &[("t3", "ContentApproved")])),
+        ]))
+    }
+
+    fn transaction(event: &FabricEvent) -> &str {
+        match event {
+            FabricEvent::Chaincode(event) => &event.transaction_id,
+            FabricEvent::Block(_) => "block",
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_follows_consumed_events() {
+        let checkpointer = Arc::new(MemoryCheckpointer::default());
+        let mut events = event_stream(
+            ledger(),
+            EventFilter::chaincode("movie-chaincode"),
+            None,
+            checkpointer.clone(),
+        );
+
+        let first = events.next().await.unwrap().unwrap();
+        assert_eq!(transaction(&first), "t1");
+        // Not saved until the consumer asks for the next event
+        assert_eq!(checkpointer.load().unwrap(), None);
+
+        let second = events.next().await.unwrap().unwrap();
+        assert_eq!(transaction(&second), "t2");
+        assert_eq!(
+            checkpointer.load().unwrap(),
+            Some(Checkpoint {
+                block_number: 5,
+                transaction_id: Some("t1".to_string()),
+            })
+        );
+
+        let third = events.next().await.unwrap().unwrap();
+        assert_eq!(transaction(&third), "t3");
+        // Block 6 had no events and was checkpointed while passing it
+        assert_eq!(checkpointer.load().unwrap().unwrap().block_number, 7);
+
+        assert!(events.next().await.is_none());
+        assert_eq!(
+            checkpointer.load().unwrap(),
+            Some(Checkpoint {
+                block_number: 8,
+                transaction_id: None,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_delivered_events_and_filters_names() {
+        let checkpointer = Arc::new(MemoryCheckpointer::default());
+        let filter = EventFilter {
+            blocks: true,
+            ..EventFilter::chaincode("movie-chaincode").named("ContentApproved")
+        };
+        let events: Vec<_> = event_stream(ledger(), filter, Some("t1".to_string()), checkpointer)
+            .map(|event| transaction(&event.unwrap()).to_string())
+            .collect()
+            .await;
+        assert_eq!(events, vec!["t2", "block", "block", "t3", "block"]);
+
+        let events: Vec<_> = event_stream(
+            ledger(),
+            EventFilter::chaincode("other-chaincode"),
+            None,
+            Arc::new(MemoryCheckpointer::default()),
+        )
+        .collect()
+        .await;
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_file_checkpointer_round_trip() {
+        let dir = std::env::temp_dir().join(format!("fabric-events-{}", uuid::Uuid::new_v4()));
+        let checkpointer = FileCheckpointer::new(dir.join("movies.json"));
+        assert_eq!(checkpointer.load().unwrap(), None);
+
+        let checkpoint = Checkpoint {
+            block_number: 12,
+            transaction_id: Some("t9".to_string()),
+        };
+        checkpointer.save(&checkpoint).unwrap();
+        assert_eq!(
+            FileCheckpointer::new(dir.join("movies.json"))
+                .load()
+                .unwrap(),
+            Some(checkpoint)
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
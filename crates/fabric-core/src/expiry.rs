@@ -0,0 +1,465 @@
+/// Certificate expiry monitoring and automatic re-enrollment
+use crate::ca::FabricCaClient;
+use crate::error::Result;
+use crate::fabric::SharedIdentity;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+
+/// Source of the current time, replaceable in tests
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Resolve once `duration` has passed on this clock
+    fn sleep(&self, duration: std::time::Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+/// Wall-clock time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: std::time::Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Clock that only moves when told to
+///
+/// Sleeps end when `set` or `advance` moves the clock past their deadline.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+    changed: Arc<Notify>,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+            changed: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+        self.changed.notify_waiters();
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+        self.changed.notify_waiters();
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn sleep(&self, duration: std::time::Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let clock = self.clone();
+        // An unrepresentable deadline never arrives
+        let deadline = Duration::from_std(duration)
+            .ok()
+            .and_then(|duration| self.now().checked_add_signed(duration));
+        Box::pin(async move {
+            loop {
+                // Registered before the check so a concurrent advance is not missed
+                let changed = clock.changed.notified();
+                if deadline.is_some_and(|deadline| clock.now() >= deadline) {
+                    return;
+                }
+                changed.await;
+            }
+        })
+    }
+}
+
+/// When to warn and when to reenroll
+#[derive(Debug, Clone)]
+pub struct ExpiryMonitorConfig {
+    /// Remaining lifetimes that trigger a warning, e.g. 30, 7 and 1 days
+    pub warning_thresholds: Vec<Duration>,
+    /// Remaining lifetime below which the identity is reenrolled
+    pub reenroll_before: Duration,
+    /// Time between checks when running in the background
+    pub check_interval: std::time::Duration,
+}
+
+impl Default for ExpiryMonitorConfig {
+    fn default() -> Self {
+        Self {
+            warning_thresholds: vec![Duration::days(30), Duration::days(7), Duration::days(1)],
+            reenroll_before: Duration::days(7),
+            check_interval: std::time::Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// Notable outcome of an expiry check
+#[derive(Debug, Clone)]
+pub enum ExpiryEvent {
+    /// The certificate crossed a warning threshold
+    Warning {
+        threshold: Duration,
+        remaining: Duration,
+        not_after: DateTime<Utc>,
+    },
+    /// The certificate has expired and can no longer be reenrolled
+    Expired { not_after: DateTime<Utc> },
+    /// The identity was reenrolled and swapped in place
+    Reenrolled {
+        identity: Box<crate::crypto::FabricIdentity>,
+        not_after: DateTime<Utc>,
+    },
+    /// Reenrollment was attempted and failed; it is retried on the next check
+    ReenrollFailed { error: String },
+}
+
+/// Watches the certificate of a shared identity
+///
+/// Each threshold warns once per certificate. With a CA client attached, the
+/// identity is reenrolled once `reenroll_before` is reached and swapped into
+/// the `SharedIdentity`, so a connected client picks it up, along with its
+/// mutual TLS certificate, on its next request.
+pub struct ExpiryMonitor {
+    identity: SharedIdentity,
+    config: ExpiryMonitorConfig,
+    clock: Arc<dyn Clock>,
+    ca: Option<FabricCaClient>,
+    serial: Option<String>,
+    warned: BTreeSet<i64>,
+    expired_reported: bool,
+}
+
+impl ExpiryMonitor {
+    pub fn new(identity: SharedIdentity, config: ExpiryMonitorConfig) -> Self {
+        Self {
+            identity,
+            config,
+            clock: Arc::new(SystemClock),
+            ca: None,
+            serial: None,
+            warned: BTreeSet::new(),
+            expired_reported: false,
+        }
+    }
+
+    /// Reenroll through this CA, authenticating with the identity itself
+    pub fn with_ca(mut self, ca: FabricCaClient) -> Self {
+        self.ca = Some(ca);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Run a single check and return what happened
+    pub async fn check(&mut self) -> Result<Vec<ExpiryEvent>> {
+        let Some(identity) = self.identity.get() else {
+            return Ok(Vec::new());
+        };
+        let mut info = identity.certificate_info()?;
+        self.track_certificate(&info.serial);
+
+        let mut events = Vec::new();
+        let now = self.clock.now();
+        if now >= info.not_after {
+            if !self.expired_reported {
+                tracing::error!(
+                    "Certificate of {} expired at {}",
+                    identity.user_id,
+                    info.not_after
+                );
+                self.expired_reported = true;
+                events.push(ExpiryEvent::Expired {
+                    not_after: info.not_after,
+                });
+            }
+            return Ok(events);
+        }
+
+        if let Some(ca) = &self.ca {
+            if info.not_after - now <= self.config.reenroll_before {
+                match reenroll(ca, &identity).await {
+                    Ok(renewed) => {
+                        info = renewed.certificate_info()?;
+                        tracing::info!(
+                            "Reenrolled {}; new certificate valid until {}",
+                            renewed.user_id,
+                            info.not_after
+                        );
+                        self.identity.replace(renewed.clone());
+                        self.track_certificate(&info.serial);
+                        events.push(ExpiryEvent::Reenrolled {
+                            identity: Box::new(renewed),
+                            not_after: info.not_after,
+                        });
+                    }
+                    Err(e) => {
+                        tracing::warn!("Reenrollment of {} failed: {}", identity.user_id, e);
+                        events.push(ExpiryEvent::ReenrollFailed {
+                            error: e.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let remaining = info.not_after - now;
+        let crossed: Vec<Duration> = self
+            .config
+            .warning_thresholds
+            .iter()
+            .copied()
+            .filter(|threshold| remaining <= *threshold)
+            .filter(|threshold| !self.warned.contains(&threshold.num_seconds()))
+            .collect();
+        // Report only the tightest new threshold when several are crossed at once
+        if let Some(threshold) = crossed.iter().min().copied() {
+            tracing::warn!(
+                "Certificate of {} expires in {} hours (at {})",
+                identity.user_id,
+                remaining.num_hours(),
+                info.not_after
+            );
+            events.push(ExpiryEvent::Warning {
+                threshold,
+                remaining,
+                not_after: info.not_after,
+            });
+        }
+        self.warned
+            .extend(crossed.iter().map(|threshold| threshold.num_seconds()));
+
+        Ok(events)
+    }
+
+    /// Check periodically in a background task, publishing events
+    ///
+    /// The first check runs immediately; later ones wait `check_interval`
+    /// on the monitor's clock.
+    pub fn spawn(mut self) -> (JoinHandle<()>, mpsc::UnboundedReceiver<ExpiryEvent>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(async move {
+            loop {
+                match self.check().await {
+                    Ok(events) => {
+                        for event in events {
+                            if sender.send(event).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => tracing::warn!("Certificate expiry check failed: {}", e),
+                }
+                self.clock.sleep(self.config.check_interval).await;
+            }
+        });
+        (handle, receiver)
+    }
+
+    /// Reset per-certificate state when a new certificate appears
+    fn track_certificate(&mut self, serial: &str) {
+        if self.serial.as_deref() != Some(serial) {
+            self.serial = Some(serial.to_string());
+            self.warned.clear();
+            self.expired_reported = false;
+        }
+    }
+}
+
+async fn reenroll(
+    ca: &FabricCaClient,
+    identity: &crate::crypto::FabricIdentity,
+) -> Result<crate::crypto::FabricIdentity> {
+    let renewed = ca.reenroll(identity).await?;
+    // Same MSP ID and issuer as the identity the client already accepted
+    renewed.validate_allowing_mspid_mismatch()?;
+    Ok(renewed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::FabricIdentity;
+    use crate::fabric::{FabricNetworkClient, KaleidoFabricClient};
+    use crate::test_util::{CertSpec, MockResponse, MockServer, TestCert};
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use openssl::x509::X509Req;
+
+    fn identity(ca: &TestCert, leaf: &TestCert) -> FabricIdentity {
+        FabricIdentity::new(
+            leaf.key_pem(),
+            leaf.public_key_pem(),
+            leaf.cert_pem(),
+            ca.cert_pem(),
+            "user1".to_string(),
+            "Org1".to_string(),
+            "Org1MSP".to_string(),
+        )
+    }
+
+    /// Fabric CA stand-in that renews certificates for a year
+    async fn mock_ca(root: Arc<TestCert>) -> MockServer {
+        MockServer::start(move |request| {
+            if request.path != "/api/v1/reenroll" {
+                return MockResponse::json(404, serde_json::json!({}));
+            }
+            let body = request.json();
+            let csr = X509Req::from_pem(
+                body["certificate_request"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .as_bytes(),
+            )
+            .unwrap();
+            let cert = root.issue_for_key(CertSpec::default(), &csr.public_key().unwrap());
+            MockResponse::json(
+                200,
+                serde_json::json!({
+                    "success": true,
+                    "result": { "Cert": BASE64.encode(cert.to_pem().unwrap()) },
+                    "errors": [],
+                    "messages": []
+                }),
+            )
+        })
+        .await
+    }
+
+    fn warnings(events: &[ExpiryEvent]) -> Vec<i64> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                ExpiryEvent::Warning { threshold, .. } => Some(threshold.num_days()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_warnings_fire_once_per_threshold() {
+        let root = TestCert::root("ca.org1.example.com");
+        let leaf = root.issue(CertSpec::default());
+        let shared = SharedIdentity::default();
+        shared.replace(identity(&root, &leaf));
+        let not_after = shared.get().unwrap().certificate_info().unwrap().not_after;
+
+        let clock = ManualClock::new(not_after - Duration::days(60));
+        let mut monitor = ExpiryMonitor::new(shared, ExpiryMonitorConfig::default())
+            .with_clock(Arc::new(clock.clone()));
+
+        assert!(monitor.check().await.unwrap().is_empty());
+
+        clock.set(not_after - Duration::days(20));
+        assert_eq!(warnings(&monitor.check().await.unwrap()), vec![30]);
+        assert!(monitor.check().await.unwrap().is_empty());
+
+        // Jumping past two thresholds reports only the tightest
+        clock.set(not_after - Duration::hours(12));
+        assert_eq!(warnings(&monitor.check().await.unwrap()), vec![1]);
+        assert!(monitor.check().await.unwrap().is_empty());
+
+        clock.advance(Duration::days(1));
+        let events = monitor.check().await.unwrap();
+        assert!(matches!(events.as_slice(), [ExpiryEvent::Expired { .. }]));
+        assert!(monitor.check().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spawned_monitor_follows_injected_clock() {
+        let root = TestCert::root("ca.org1.example.com");
+        let leaf = root.issue(CertSpec::default());
+        let shared = SharedIdentity::default();
+        shared.replace(identity(&root, &leaf));
+        let not_after = shared.get().unwrap().certificate_info().unwrap().not_after;
+
+        let clock = ManualClock::new(not_after - Duration::days(20));
+        let (handle, mut events) = ExpiryMonitor::new(shared, ExpiryMonitorConfig::default())
+            .with_clock(Arc::new(clock.clone()))
+            .spawn();
+        assert_eq!(warnings(&[events.recv().await.unwrap()]), vec![30]);
+
+        // Less than the hourly check interval: no new check
+        clock.advance(Duration::minutes(30));
+        let wait = std::time::Duration::from_millis(100);
+        assert!(tokio::time::timeout(wait, events.recv()).await.is_err());
+
+        clock.advance(Duration::days(14));
+        let wait = std::time::Duration::from_secs(5);
+        let event = tokio::time::timeout(wait, events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(warnings(&[event]), vec![7]);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_reenrolls_and_swaps_connected_client_identity() {
+        let root = Arc::new(TestCert::root("ca.org1.example.com"));
+        let leaf = root.issue(CertSpec {
+            valid_until_days: 5,
+            ..Default::default()
+        });
+        let server = mock_ca(root.clone()).await;
+
+        let mut client =
+            KaleidoFabricClient::from_kaleido_endpoint("https://gateway.example.com", &server.url);
+        let original = identity(&root, &leaf);
+        client.connect(&original).await.unwrap();
+
+        let mut monitor =
+            ExpiryMonitor::new(client.shared_identity(), ExpiryMonitorConfig::default())
+                .with_ca(FabricCaClient::new(&server.url))
+                .with_clock(Arc::new(ManualClock::new(Utc::now())));
+
+        let events = monitor.check().await.unwrap();
+        assert!(matches!(
+            events.as_slice(),
+            [ExpiryEvent::Reenrolled { .. }]
+        ));
+
+        let current = client.identity().unwrap();
+        assert!(client.is_connected());
+        assert_ne!(current.certificate, original.certificate);
+        assert_ne!(current.private_key, original.private_key);
+        current.validate().unwrap();
+
+        // The renewed certificate is far from expiry, so nothing else happens
+        assert!(monitor.check().await.unwrap().is_empty());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_reenrollment_still_warns() {
+        let root = TestCert::root("ca.org1.example.com");
+        let leaf = root.issue(CertSpec {
+            valid_until_days: 3,
+            ..Default::default()
+        });
+        let server = MockServer::start(|_| MockResponse::json(500, serde_json::json!({}))).await;
+        let shared = SharedIdentity::default();
+        shared.replace(identity(&root, &leaf));
+
+        let mut monitor = ExpiryMonitor::new(shared.clone(), ExpiryMonitorConfig::default())
+            .with_ca(FabricCaClient::new(&server.url));
+        let events = monitor.check().await.unwrap();
+
+        assert!(matches!(events[0], ExpiryEvent::ReenrollFailed { .. }));
+        assert_eq!(warnings(&events), vec![7]);
+        assert_eq!(shared.get().unwrap().certificate, leaf.cert_pem());
+    }
+}
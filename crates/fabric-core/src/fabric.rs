@@ -1,8 +1,29 @@
 /// Hyperledger Fabric network interaction module
 use crate::error::{Result, FabricCoreError};
 use crate::crypto::FabricIdentity;
+use crate::events::{
+    event_stream, poll_blocks, resume_position, BlockEvent, ChaincodeEvent, Checkpointer,
+    EventFilter, EventStream,
+};
+use crate::history::{
+    collect_history, BlockSource, HistoryQuery, TransactionPage, TransactionRecord,
+};
+use crate::private_data::PrivateData;
+use crate::protos::peer::TxValidationCode;
+use crate::tls::{self, TlsClientAuth, TlsConfig};
 use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// How often `KaleidoFabricClient` event subscriptions poll for new blocks
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often `KaleidoFabricClient::wait_for_commit` polls for a receipt
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 /// Configuration for connecting to a Hyperledger Fabric network
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +40,16 @@ pub struct FabricNetworkConfig {
     pub gateway_url: String,
     /// TLS certificate path for gateway
     pub tls_cert_path: Option<String>,
+    /// Mutual TLS, key pinning and protocol settings for the gateway
+    #[serde(default)]
+    pub tls: TlsConfig,
+    /// Return the built-in channel list when channel discovery fails
+    #[serde(default)]
+    pub offline_fallback: bool,
+    /// Accept identities whose MSP ID doesn't follow the certificate's
+    /// organization, e.g. fabric-ca's default `O=Hyperledger`
+    #[serde(default)]
+    pub allow_mspid_mismatch: bool,
 }
 
 /// Represents a channel in the Hyperledger Fabric network
@@ -27,7 +58,11 @@ pub struct FabricChannel {
     pub id: String,
     pub name: String,
     pub description: String,
+    /// First chaincode on the channel, empty if there is none
     pub chaincode_id: String,
+    /// Chaincodes defined on the channel
+    #[serde(default)]
+    pub chaincodes: Vec<String>,
 }
 
 impl FabricChannel {
@@ -37,31 +72,217 @@ impl FabricChannel {
         description: String,
         chaincode_id: String,
     ) -> Self {
+        let chaincodes = if chaincode_id.is_empty() {
+            Vec::new()
+        } else {
+            vec![chaincode_id.clone()]
+        };
         Self {
             id,
             name,
             description,
             chaincode_id,
+            chaincodes,
+        }
+    }
+
+    /// Channel discovered on the network, named after its ID
+    pub fn discovered(id: String, chaincodes: Vec<String>) -> Self {
+        Self {
+            name: id.clone(),
+            id,
+            description: String::new(),
+            chaincode_id: chaincodes.first().cloned().unwrap_or_default(),
+            chaincodes,
         }
     }
 }
 
+/// Built-in channels of the movie network, for use without a connection
+pub fn offline_channels() -> Vec<FabricChannel> {
+    vec![
+        FabricChannel::new(
+            "movies".to_string(),
+            "Movies".to_string(),
+            "Movie database and torrent hashes".to_string(),
+            "movie-chaincode".to_string(),
+        ),
+        FabricChannel::new(
+            "tv-shows".to_string(),
+            "TV Shows".to_string(),
+            "TV show database and torrent hashes".to_string(),
+            "tvshow-chaincode".to_string(),
+        ),
+        FabricChannel::new(
+            "games".to_string(),
+            "Games".to_string(),
+            "Game database and torrent hashes".to_string(),
+            "game-chaincode".to_string(),
+        ),
+        FabricChannel::new(
+            "voting".to_string(),
+            "Voting".to_string(),
+            "Voting and consensus mechanism".to_string(),
+            "voting-chaincode".to_string(),
+        ),
+    ]
+}
+
+/// Apply `offline_fallback` to the outcome of channel discovery
+pub(crate) fn discovered_or_offline(
+    config: &FabricNetworkConfig,
+    discovered: Result<Vec<FabricChannel>>,
+) -> Result<Vec<FabricChannel>> {
+    match discovered {
+        Err(e) if config.offline_fallback => {
+            tracing::warn!("Channel discovery failed, using offline channel list: {}", e);
+            Ok(offline_channels())
+        }
+        result => result,
+    }
+}
+
+/// Validate an identity, applying `allow_mspid_mismatch`
+pub(crate) fn validate_identity(
+    config: &FabricNetworkConfig,
+    identity: &FabricIdentity,
+) -> Result<()> {
+    if config.allow_mspid_mismatch {
+        identity.validate_allowing_mspid_mismatch()
+    } else {
+        identity.validate()
+    }
+}
+
 /// Chaincode query/invoke parameter
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ChaincodeArg {
+    /// Parameter name, used in logs and error messages only; Fabric
+    /// arguments are positional
     pub key: String,
     pub value: serde_json::Value,
 }
 
+impl ChaincodeArg {
+    pub fn new(key: &str, value: serde_json::Value) -> Self {
+        Self {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    /// Fabric argument string: strings are passed as they are, everything
+    /// else as compact JSON, which is what contractapi parameters expect
+    pub fn to_fabric_arg(&self) -> String {
+        match &self.value {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        }
+    }
+}
+
+/// Builder for the positional arguments of a chaincode call
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChaincodeArgs {
+    args: Vec<ChaincodeArg>,
+}
+
+impl ChaincodeArgs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Arguments from a JSON value: each element of an array is one
+    /// argument, any other value is a single argument
+    pub fn from_json(json: serde_json::Value) -> Self {
+        let values = match json {
+            serde_json::Value::Array(values) => values,
+            value => vec![value],
+        };
+        Self {
+            args: values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| ChaincodeArg::new(&i.to_string(), value))
+                .collect(),
+        }
+    }
+
+    /// Add a prepared argument
+    pub fn with_arg(mut self, arg: ChaincodeArg) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    /// Add a string argument
+    pub fn with_string(self, key: &str, value: &str) -> Self {
+        self.with_arg(ChaincodeArg::new(key, value.into()))
+    }
+
+    /// Add a number, struct or other serializable value
+    pub fn with_value<T: Serialize + ?Sized>(self, key: &str, value: &T) -> Result<Self> {
+        Ok(self.with_arg(ChaincodeArg::new(key, serde_json::to_value(value)?)))
+    }
+
+    /// Add a byte argument, base64 encoded as Go's `encoding/json` does for
+    /// `[]byte`
+    pub fn with_bytes(self, key: &str, value: impl AsRef<[u8]>) -> Self {
+        self.with_arg(ChaincodeArg::new(key, BASE64.encode(value).into()))
+    }
+
+    pub fn args(&self) -> &[ChaincodeArg] {
+        &self.args
+    }
+
+    /// Argument strings for `query_chaincode` and `invoke_chaincode`
+    pub fn to_fabric_args(&self) -> Vec<String> {
+        self.args.iter().map(ChaincodeArg::to_fabric_arg).collect()
+    }
+}
+
+/// Where a transaction is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionStatus {
+    /// Endorsed and sent for ordering, commit not yet confirmed
+    Submitted,
+    /// Committed to the ledger as valid
+    Committed,
+    /// Committed to the ledger but rejected by validation
+    Invalid,
+}
+
+impl TransactionStatus {
+    /// Status of a committed transaction from its validation code name
+    pub fn from_validation_code(code: &str) -> Self {
+        if code == "VALID" {
+            Self::Committed
+        } else {
+            Self::Invalid
+        }
+    }
+}
+
 /// Transaction result from chaincode invocation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionResult {
     pub transaction_id: String,
-    pub status: String,
+    pub status: TransactionStatus,
     pub payload: serde_json::Value,
     pub timestamp: String,
 }
 
+/// Final outcome of a submitted transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitReceipt {
+    pub transaction_id: String,
+    /// `Committed` or `Invalid`
+    pub status: TransactionStatus,
+    /// Fabric validation code name, e.g. `VALID` or `MVCC_READ_CONFLICT`
+    pub validation_code: String,
+    pub block_number: Option<u64>,
+}
+
 /// Trait for Fabric network operations
 #[async_trait]
 pub trait FabricNetworkClient: Send + Sync {
@@ -83,6 +304,24 @@ pub trait FabricNetworkClient: Send + Sync {
         _args: Vec<String>,
     ) -> Result<serde_json::Value>;
 
+    /// Chaincode payload of a `query_chaincode` response
+    ///
+    /// The response is the payload itself unless the client wraps it.
+    fn query_payload(&self, response: serde_json::Value) -> Result<serde_json::Value> {
+        Ok(response)
+    }
+
+    /// Query chaincode with transient data, e.g. to read a private data
+    /// collection
+    async fn query_chaincode_private(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+        private: &PrivateData,
+    ) -> Result<serde_json::Value>;
+
     /// Invoke chaincode (submit transaction)
     async fn invoke_chaincode(
         &self,
@@ -92,20 +331,116 @@ pub trait FabricNetworkClient: Send + Sync {
         _args: Vec<String>,
     ) -> Result<TransactionResult>;
 
-    /// Get transaction history
+    /// Invoke chaincode with transient data that is kept off the ledger
+    async fn invoke_chaincode_private(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+        private: &PrivateData,
+    ) -> Result<TransactionResult>;
+
+    /// Submit a transaction without waiting for it to commit
+    ///
+    /// The result has status `Submitted`; pass its ID to `wait_for_commit`.
+    async fn submit_chaincode(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+    ) -> Result<TransactionResult>;
+
+    /// Wait for a submitted transaction to commit, failing with
+    /// `CommitTimeout` once `timeout` has passed
+    async fn wait_for_commit(
+        &self,
+        channel_id: &str,
+        transaction_id: &str,
+        timeout: Duration,
+    ) -> Result<CommitReceipt>;
+
+    /// Get transaction history of a chaincode, newest first
     async fn get_transaction_history(
         &self,
         channel_id: &str,
         chaincode_id: &str,
-    ) -> Result<Vec<TransactionResult>>;
+        query: &HistoryQuery,
+    ) -> Result<TransactionPage>;
+
+    /// Subscribe to chaincode and block events of a channel
+    ///
+    /// The stream starts from the checkpointer's saved position when there
+    /// is one and keeps it up to date as events are consumed.
+    async fn subscribe_events(
+        &self,
+        channel_id: &str,
+        filter: EventFilter,
+        checkpointer: Arc<dyn Checkpointer>,
+    ) -> Result<EventStream>;
+}
+
+/// Typed calls on top of any `FabricNetworkClient`
+#[async_trait]
+pub trait FabricNetworkClientExt: FabricNetworkClient {
+    /// Query chaincode and decode its payload, see `query_payload`, into `T`
+    async fn query_typed<T: DeserializeOwned>(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: &ChaincodeArgs,
+    ) -> Result<T> {
+        let response = self
+            .query_chaincode(channel_id, chaincode_id, function, args.to_fabric_args())
+            .await?;
+        decode_result(function, self.query_payload(response)?)
+    }
+}
+
+impl<C: FabricNetworkClient + ?Sized> FabricNetworkClientExt for C {}
+
+fn decode_result<T: DeserializeOwned>(function: &str, payload: serde_json::Value) -> Result<T> {
+    T::deserialize(&payload).map_err(|e| {
+        FabricCoreError::QueryError(format!("Cannot decode {} result: {}", function, e))
+    })
+}
+
+/// Identity slot shared between a client and background tasks
+///
+/// Cloning yields another handle to the same slot, so a task such as the
+/// expiry monitor can replace the identity of a connected client.
+#[derive(Debug, Clone, Default)]
+pub struct SharedIdentity(Arc<RwLock<Option<FabricIdentity>>>);
+
+impl SharedIdentity {
+    /// Snapshot of the current identity
+    pub fn get(&self) -> Option<FabricIdentity> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replace the identity, returning the previous one
+    pub fn replace(&self, identity: FabricIdentity) -> Option<FabricIdentity> {
+        self.0
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .replace(identity)
+    }
+
+    pub(crate) fn clear(&self) {
+        self.0.write().unwrap_or_else(|e| e.into_inner()).take();
+    }
 }
 
 /// Default implementation for Kaleido-based Hyperledger Fabric network
+#[derive(Clone)]
 pub struct KaleidoFabricClient {
     config: FabricNetworkConfig,
     connected: bool,
-    identity: Option<FabricIdentity>,
-    http_client: Option<reqwest::Client>,
+    identity: SharedIdentity,
+    /// HTTP client and the certificate it was built for
+    http_client: Arc<Mutex<Option<(String, reqwest::Client)>>>,
 }
 
 impl KaleidoFabricClient {
@@ -113,8 +448,8 @@ impl KaleidoFabricClient {
         Self {
             config,
             connected: false,
-            identity: None,
-            http_client: None,
+            identity: SharedIdentity::default(),
+            http_client: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -130,6 +465,9 @@ impl KaleidoFabricClient {
             ca_url: ca_url.to_string(),
             gateway_url: gateway_url.to_string(),
             tls_cert_path: None,
+            tls: TlsConfig::default(),
+            offline_fallback: false,
+            allow_mspid_mismatch: false,
         };
 
         Self::new(config)
@@ -144,17 +482,300 @@ impl KaleidoFabricClient {
     pub fn is_connected(&self) -> bool {
         self.connected
     }
+
+    /// The identity currently used for requests
+    pub fn identity(&self) -> Option<FabricIdentity> {
+        self.identity.get()
+    }
+
+    /// Handle to the client's identity slot, for background tasks
+    pub fn shared_identity(&self) -> SharedIdentity {
+        self.identity.clone()
+    }
+
+    /// Replace the identity of a connected client without reconnecting
+    pub fn swap_identity(&self, identity: FabricIdentity) -> Result<()> {
+        if !self.connected {
+            return Err(FabricCoreError::ConnectionError(
+                "Not connected to network".to_string(),
+            ));
+        }
+        validate_identity(&self.config, &identity)?;
+        self.identity.replace(identity);
+        Ok(())
+    }
+}
+
+impl KaleidoFabricClient {
+    /// HTTP client for the current identity
+    ///
+    /// With identity mutual TLS the client is rebuilt once the identity has
+    /// been swapped, e.g. by reenrollment, so the new certificate is
+    /// presented.
+    fn http_client(&self) -> Result<reqwest::Client> {
+        let mut cached = self.http_client.lock().unwrap_or_else(|e| e.into_inner());
+        let (certificate, client) = cached.as_mut().ok_or(FabricCoreError::ConnectionError(
+            "HTTP client not initialized".to_string(),
+        ))?;
+        if self.config.tls.client_auth == TlsClientAuth::Identity {
+            if let Some(identity) = self.identity.get() {
+                if identity.certificate != *certificate {
+                    *client = tls::http_client(&self.config, &identity)?;
+                    *certificate = identity.certificate;
+                }
+            }
+        }
+        Ok(client.clone())
+    }
+
+    /// List channels through `GET {gateway}/channels`
+    ///
+    /// Channels listed without their chaincodes are completed with
+    /// `GET {gateway}/channels/{id}/chaincodes`.
+    async fn discover_channels(&self) -> Result<Vec<FabricChannel>> {
+        let base = self.config.gateway_url.trim_end_matches('/');
+        let listing = self
+            .get_json(&format!("{}/channels", base), FabricCoreError::ChannelError)
+            .await?;
+        let entries = listing
+            .get("channels")
+            .unwrap_or(&listing)
+            .as_array()
+            .cloned()
+            .ok_or_else(|| {
+                FabricCoreError::ChannelError(format!("Unexpected channel listing: {}", listing))
+            })?;
+
+        let mut channels = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let id = match &entry {
+                serde_json::Value::String(id) => id.clone(),
+                _ => ["id", "channel_id", "name"]
+                    .iter()
+                    .find_map(|key| entry.get(key).and_then(|v| v.as_str()))
+                    .ok_or_else(|| {
+                        FabricCoreError::ChannelError(format!("Channel without an ID: {}", entry))
+                    })?
+                    .to_string(),
+            };
+            let chaincodes = match entry.get("chaincodes") {
+                Some(chaincodes) => chaincode_names(chaincodes),
+                None => chaincode_names(
+                    &self
+                        .get_json(
+                            &format!("{}/channels/{}/chaincodes", base, id),
+                            FabricCoreError::ChannelError,
+                        )
+                        .await?,
+                ),
+            };
+            channels.push(FabricChannel::discovered(id, chaincodes));
+        }
+        Ok(channels)
+    }
+
+    /// POST a transaction to `{gateway}/channels/{ch}/chaincodes/{cc}/{fn}`
+    async fn post_transaction(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+        private: &PrivateData,
+        sync: bool,
+    ) -> Result<serde_json::Value> {
+        if !self.connected {
+            return Err(FabricCoreError::ConnectionError(
+                "Not connected to network".to_string(),
+            ));
+        }
+
+        let http_client = self.http_client()?;
+
+        // Build Kaleido REST API URL for invocation
+        let invoke_url = format!(
+            "{}/channels/{}/chaincodes/{}/{}",
+            self.config.gateway_url.trim_end_matches('/'),
+            channel_id,
+            chaincode_id,
+            function
+        );
+
+        // Build invoke request body
+        let request_body = serde_json::json!({
+            "args": args,
+            "sync": sync,
+            "transient_map": private.transient_base64(),
+            "private_data": private.targeting(),
+        });
+
+        tracing::info!(
+            "Invoking chaincode: channel={}, id={}, function={}, sync={}, url={}",
+            channel_id,
+            chaincode_id,
+            function,
+            sync,
+            invoke_url
+        );
+
+        // Make HTTP request to Kaleido
+        let response = http_client
+            .post(&invoke_url)
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|e| FabricCoreError::InvocationError(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(FabricCoreError::InvocationError(format!(
+                "Invocation failed with status {}: {}",
+                status, error_text
+            )));
+        }
+
+        response
+            .json::<serde_json::Value>()
+            .await
+            .map_err(|e| FabricCoreError::InvocationError(e.to_string()))
+    }
+
+    async fn get_json(
+        &self,
+        url: &str,
+        error: fn(String) -> FabricCoreError,
+    ) -> Result<serde_json::Value> {
+        let http_client = self.http_client()?;
+        let response = http_client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| error(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(error(format!(
+                "Request to {} failed with status {}: {}",
+                url, status, error_text
+            )));
+        }
+
+        response
+            .json::<serde_json::Value>()
+            .await
+            .map_err(|e| error(e.to_string()))
+    }
+}
+
+fn response_transaction_id(result: &serde_json::Value) -> Option<String> {
+    result
+        .get("transactionId")
+        .or_else(|| result.get("txn_id"))
+        .or_else(|| result.get("id"))
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+}
+
+fn required_transaction_id(result: &serde_json::Value) -> Result<String> {
+    response_transaction_id(result).ok_or_else(|| {
+        FabricCoreError::InvocationError(format!(
+            "Gateway did not return a transaction ID: {}",
+            result
+        ))
+    })
+}
+
+/// Receipt of a committed transaction, `None` while it is still pending
+///
+/// The outcome comes from the validation code (`validation_code`,
+/// `validationCode` or `status`, by name or number). Without one, an
+/// `Error` receipt (`"headers": {"type": "Error"}`) is reported as
+/// `INVALID_OTHER_REASON`, a receipt without `headers.type` is still
+/// pending, and anything else is an error rather than assumed valid.
+fn commit_receipt(transaction_id: &str, body: &serde_json::Value) -> Result<Option<CommitReceipt>> {
+    let receipt = body.get("result").unwrap_or(body);
+    let unrecognised = |what: &str, value: &serde_json::Value| {
+        FabricCoreError::InvocationError(format!(
+            "Unrecognised {} in receipt of {}: {}",
+            what, transaction_id, value
+        ))
+    };
+    let status = &receipt["status"];
+    if status.as_str().is_some_and(|s| s.eq_ignore_ascii_case("pending")) {
+        return Ok(None);
+    }
+    let code = [&receipt["validation_code"], &receipt["validationCode"], status]
+        .into_iter()
+        .find(|value| !value.is_null())
+        .map(|value| {
+            let code = match value {
+                serde_json::Value::String(name) => TxValidationCode::from_str_name(name),
+                other => other
+                    .as_i64()
+                    .and_then(|n| i32::try_from(n).ok())
+                    .and_then(|n| TxValidationCode::try_from(n).ok()),
+            };
+            code.ok_or_else(|| unrecognised("validation code", value))
+        })
+        .transpose()?;
+    let code = match (code, &receipt["headers"]["type"]) {
+        (Some(code), _) => code,
+        (None, serde_json::Value::Null) => return Ok(None),
+        (None, kind) if kind.as_str() == Some("Error") => TxValidationCode::InvalidOtherReason,
+        (None, kind) if kind.as_str() == Some("TransactionSuccess") => {
+            return Err(FabricCoreError::InvocationError(format!(
+                "Receipt of {} has no validation code",
+                transaction_id
+            )))
+        }
+        (None, kind) => return Err(unrecognised("receipt type", kind)),
+    };
+    let validation_code = code.as_str_name().to_string();
+
+    Ok(Some(CommitReceipt {
+        transaction_id: transaction_id.to_string(),
+        status: TransactionStatus::from_validation_code(&validation_code),
+        validation_code,
+        block_number: json_u64(&receipt["block_number"])
+            .or_else(|| json_u64(&receipt["blockNumber"])),
+    }))
+}
+
+/// Chaincode names from a list of names or `{"name": ...}` objects
+fn chaincode_names(value: &serde_json::Value) -> Vec<String> {
+    let list = value.get("chaincodes").unwrap_or(value);
+    list.as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    item.as_str()
+                        .or_else(|| item.get("name").and_then(|v| v.as_str()))
+                        .map(str::to_string)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 #[async_trait]
 impl FabricNetworkClient for KaleidoFabricClient {
     async fn connect(&mut self, identity: &FabricIdentity) -> Result<()> {
         // Validate identity
-        identity.validate()?;
+        validate_identity(&self.config, identity)?;
 
         // Initialize HTTP client
-        self.http_client = Some(reqwest::Client::new());
-        self.identity = Some(identity.clone());
+        let http_client = tls::http_client(&self.config, identity)?;
+        self.http_client = Arc::new(Mutex::new(Some((identity.certificate.clone(), http_client))));
+        self.identity.replace(identity.clone());
         self.connected = true;
 
         tracing::info!(
@@ -166,8 +787,8 @@ impl FabricNetworkClient for KaleidoFabricClient {
 
     async fn disconnect(&mut self) -> Result<()> {
         self.connected = false;
-        self.identity = None;
-        self.http_client = None;
+        self.identity.clear();
+        self.http_client = Arc::new(Mutex::new(None));
 
         tracing::info!("Disconnected from Hyperledger network");
         Ok(())
@@ -180,33 +801,7 @@ impl FabricNetworkClient for KaleidoFabricClient {
             ));
         }
 
-        // Return predefined channels for the use case
-        let channels = vec![
-            FabricChannel::new(
-                "movies".to_string(),
-                "Movies".to_string(),
-                "Movie database and torrent hashes".to_string(),
-                "movie-chaincode".to_string(),
-            ),
-            FabricChannel::new(
-                "tv-shows".to_string(),
-                "TV Shows".to_string(),
-                "TV show database and torrent hashes".to_string(),
-                "tvshow-chaincode".to_string(),
-            ),
-            FabricChannel::new(
-                "games".to_string(),
-                "Games".to_string(),
-                "Game database and torrent hashes".to_string(),
-                "game-chaincode".to_string(),
-            ),
-            FabricChannel::new(
-                "voting".to_string(),
-                "Voting".to_string(),
-                "Voting and consensus mechanism".to_string(),
-                "voting-chaincode".to_string(),
-            ),
-        ];
+        let channels = discovered_or_offline(&self.config, self.discover_channels().await)?;
 
         tracing::debug!("Retrieved {} channels", channels.len());
         Ok(channels)
@@ -218,6 +813,29 @@ impl FabricNetworkClient for KaleidoFabricClient {
         chaincode_id: &str,
         function: &str,
         args: Vec<String>,
+    ) -> Result<serde_json::Value> {
+        self.query_chaincode_private(channel_id, chaincode_id, function, args, &PrivateData::new())
+            .await
+    }
+
+    /// The REST gateway returns the payload in a `result` field
+    fn query_payload(&self, mut response: serde_json::Value) -> Result<serde_json::Value> {
+        match response.get_mut("result") {
+            Some(result) => Ok(result.take()),
+            None => Err(FabricCoreError::QueryError(format!(
+                "Gateway response has no result: {}",
+                response
+            ))),
+        }
+    }
+
+    async fn query_chaincode_private(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+        private: &PrivateData,
     ) -> Result<serde_json::Value> {
         if !self.connected {
             return Err(FabricCoreError::ConnectionError(
@@ -225,9 +843,7 @@ impl FabricNetworkClient for KaleidoFabricClient {
             ));
         }
 
-        let http_client = self.http_client.as_ref().ok_or(FabricCoreError::ConnectionError(
-            "HTTP client not initialized".to_string(),
-        ))?;
+        let http_client = self.http_client()?;
 
         // Build Kaleido REST API URL for query
         let query_url = format!(
@@ -242,6 +858,8 @@ impl FabricNetworkClient for KaleidoFabricClient {
         let request_body = serde_json::json!({
             "args": args,
             "sync": true,
+            "transient_map": private.transient_base64(),
+            "private_data": private.targeting(),
         });
 
         tracing::debug!(
@@ -288,99 +906,145 @@ impl FabricNetworkClient for KaleidoFabricClient {
         function: &str,
         args: Vec<String>,
     ) -> Result<TransactionResult> {
-        if !self.connected {
-            return Err(FabricCoreError::ConnectionError(
-                "Not connected to network".to_string(),
-            ));
-        }
-
-        let http_client = self.http_client.as_ref().ok_or(FabricCoreError::ConnectionError(
-            "HTTP client not initialized".to_string(),
-        ))?;
-
-        // Build Kaleido REST API URL for invocation
-        let invoke_url = format!(
-            "{}/channels/{}/chaincodes/{}/{}",
-            self.config.gateway_url.trim_end_matches('/'),
-            channel_id,
-            chaincode_id,
-            function
-        );
-
-        // Build invoke request body
-        let request_body = serde_json::json!({
-            "args": args,
-            "sync": true,
-            "private_data": {},
-        });
+        self.invoke_chaincode_private(channel_id, chaincode_id, function, args, &PrivateData::new())
+            .await
+    }
 
-        tracing::info!(
-            "Invoking chaincode: channel={}, id={}, function={}, url={}",
-            channel_id,
-            chaincode_id,
-            function,
-            invoke_url
-        );
+    /// Transient values are sent base64 encoded in `transient_map`, and the
+    /// collection targeting in `private_data`
+    async fn invoke_chaincode_private(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+        private: &PrivateData,
+    ) -> Result<TransactionResult> {
+        let result = self
+            .post_transaction(channel_id, chaincode_id, function, args, private, true)
+            .await?;
 
-        // Make HTTP request to Kaleido
-        let response = http_client
-            .post(&invoke_url)
-            .json(&request_body)
-            .send()
-            .await
-            .map_err(|e| FabricCoreError::InvocationError(e.to_string()))?;
+        let transaction_id = required_transaction_id(&result)?;
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
+        // A sync gateway answers with the receipt, which may be a failure
+        if result["headers"]["type"].as_str() == Some("Error") {
+            let reason = result["errorMessage"]
+                .as_str()
+                .or_else(|| result["validationCode"].as_str())
+                .unwrap_or("INVALID_OTHER_REASON");
             return Err(FabricCoreError::InvocationError(format!(
-                "Invocation failed with status {}: {}",
-                status, error_text
+                "Transaction {} failed: {}",
+                transaction_id, reason
             )));
         }
 
-        let result = response
-            .json::<serde_json::Value>()
-            .await
-            .map_err(|e| FabricCoreError::InvocationError(e.to_string()))?;
-
-        // Extract transaction ID from response
-        let transaction_id = result
-            .get("transactionId")
-            .or_else(|| result.get("txn_id"))
-            .or_else(|| result.get("id"))
-            .and_then(|v| v.as_str())
-            .unwrap_or(&uuid::Uuid::new_v4().to_string())
-            .to_string();
-
         let payload = result
             .get("result")
             .or_else(|| result.get("payload"))
             .cloned()
             .unwrap_or_else(|| result.clone());
 
-        tracing::info!(
-            "Invocation successful: txn_id={}, status={}",
-            transaction_id,
-            "SUCCESS"
-        );
+        tracing::info!("Invocation committed: txn_id={}", transaction_id);
 
         Ok(TransactionResult {
             transaction_id,
-            status: "SUCCESS".to_string(),
+            status: TransactionStatus::Committed,
             payload,
             timestamp: chrono::Utc::now().to_rfc3339(),
         })
     }
 
+    /// Sends `"sync": false`, so the gateway answers once the transaction
+    /// is endorsed and sent for ordering
+    async fn submit_chaincode(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+    ) -> Result<TransactionResult> {
+        let result = self
+            .post_transaction(channel_id, chaincode_id, function, args, &PrivateData::new(), false)
+            .await?;
+        let transaction_id = required_transaction_id(&result)?;
+
+        tracing::info!("Transaction submitted: txn_id={}", transaction_id);
+
+        Ok(TransactionResult {
+            transaction_id,
+            status: TransactionStatus::Submitted,
+            payload: result
+                .get("result")
+                .or_else(|| result.get("payload"))
+                .cloned()
+                .unwrap_or(serde_json::Value::Null),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Polls `GET {gateway}/receipts/{tx_id}` until the receipt is final
+    async fn wait_for_commit(
+        &self,
+        _channel_id: &str,
+        transaction_id: &str,
+        timeout: Duration,
+    ) -> Result<CommitReceipt> {
+        if !self.connected {
+            return Err(FabricCoreError::ConnectionError(
+                "Not connected to network".to_string(),
+            ));
+        }
+
+        let http_client = self.http_client()?;
+        let receipt_url = format!(
+            "{}/receipts/{}",
+            self.config.gateway_url.trim_end_matches('/'),
+            transaction_id
+        );
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            let response = http_client
+                .get(&receipt_url)
+                .send()
+                .await
+                .map_err(|e| FabricCoreError::InvocationError(e.to_string()))?;
+            let status = response.status();
+            if status.is_success() {
+                let body = response
+                    .json::<serde_json::Value>()
+                    .await
+                    .map_err(|e| FabricCoreError::InvocationError(e.to_string()))?;
+                if let Some(receipt) = commit_receipt(transaction_id, &body)? {
+                    return Ok(receipt);
+                }
+            } else if status != reqwest::StatusCode::NOT_FOUND {
+                let error_text = response
+                    .text()
+                    .await
+                    .unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(FabricCoreError::InvocationError(format!(
+                    "Receipt request failed with status {}: {}",
+                    status, error_text
+                )));
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(FabricCoreError::CommitTimeout {
+                    transaction_id: transaction_id.to_string(),
+                });
+            }
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
     async fn get_transaction_history(
         &self,
         channel_id: &str,
         chaincode_id: &str,
-    ) -> Result<Vec<TransactionResult>> {
+        query: &HistoryQuery,
+    ) -> Result<TransactionPage> {
         if !self.connected {
             return Err(FabricCoreError::ConnectionError(
                 "Not connected to network".to_string(),
@@ -393,13 +1057,199 @@ impl FabricNetworkClient for KaleidoFabricClient {
             chaincode_id
         );
 
-        Ok(Vec::new())
+        collect_history(self, channel_id, chaincode_id, query).await
+    }
+
+    /// Events are read by polling `GET {gateway}/blocks/{n}`
+    async fn subscribe_events(
+        &self,
+        channel_id: &str,
+        filter: EventFilter,
+        checkpointer: Arc<dyn Checkpointer>,
+    ) -> Result<EventStream> {
+        if !self.connected {
+            return Err(FabricCoreError::ConnectionError(
+                "Not connected to network".to_string(),
+            ));
+        }
+
+        let (start, resume_after) =
+            resume_position(self, channel_id, &filter, checkpointer.as_ref()).await?;
+        tracing::debug!(
+            "Subscribing to events: channel={}, start block={}",
+            channel_id,
+            start
+        );
+        let blocks = poll_blocks(
+            Arc::new(self.clone()),
+            channel_id.to_string(),
+            start,
+            BLOCK_POLL_INTERVAL,
+        );
+        Ok(event_stream(blocks, filter, resume_after, checkpointer))
+    }
+}
+
+/// Blocks through the gateway's `GET /chaininfo` and `GET /blocks/{n}`
+#[async_trait]
+impl BlockSource for KaleidoFabricClient {
+    async fn height(&self, channel_id: &str) -> Result<u64> {
+        let info = self
+            .get_json(
+                &format!(
+                    "{}/chaininfo?channel={}",
+                    self.config.gateway_url.trim_end_matches('/'),
+                    channel_id
+                ),
+                FabricCoreError::QueryError,
+            )
+            .await?;
+        let info = info.get("result").unwrap_or(&info);
+        json_u64(&info["height"]).ok_or_else(|| {
+            FabricCoreError::QueryError(format!("Unexpected chain info: {}", info))
+        })
+    }
+
+    async fn block(&self, channel_id: &str, block_number: u64) -> Result<BlockEvent> {
+        let body = self
+            .get_json(
+                &format!(
+                    "{}/blocks/{}?channel={}",
+                    self.config.gateway_url.trim_end_matches('/'),
+                    block_number,
+                    channel_id
+                ),
+                FabricCoreError::QueryError,
+            )
+            .await?;
+        let body = body.get("result").unwrap_or(&body);
+        let block = body.get("block").unwrap_or(body);
+
+        let mut event = BlockEvent {
+            channel_id: channel_id.to_string(),
+            block_number,
+            ..Default::default()
+        };
+        for tx in block["transactions"].as_array().into_iter().flatten() {
+            if let Some(record) = transaction_record(channel_id, block_number, tx) {
+                event.chaincode_events.extend(chaincode_event(&record, tx));
+                event.transactions.push(record);
+            }
+        }
+        Ok(event)
     }
 }
 
+/// History entry from a block transaction in the gateway's JSON format
+///
+/// Configuration transactions and entries without a chaincode action are
+/// skipped.
+fn transaction_record(
+    channel_id: &str,
+    block_number: u64,
+    tx: &serde_json::Value,
+) -> Option<TransactionRecord> {
+    if tx["type"]
+        .as_str()
+        .is_some_and(|t| t != "ENDORSER_TRANSACTION")
+    {
+        return None;
+    }
+    let action = tx["actions"].get(0)?;
+    let chaincode_id = action["chaincode_id"]["name"]
+        .as_str()
+        .or_else(|| action["chaincode_id"].as_str())?;
+    let mut args = action["input"]["args"]
+        .as_array()
+        .map(|args| {
+            args.iter()
+                .map(|arg| match arg {
+                    serde_json::Value::String(arg) => arg.clone(),
+                    other => other.to_string(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default()
+        .into_iter();
+    let text = |value: &serde_json::Value| value.as_str().unwrap_or_default().to_string();
+
+    Some(TransactionRecord {
+        transaction_id: tx["tx_id"].as_str().or_else(|| tx["id"].as_str())?.to_string(),
+        block_number,
+        channel_id: channel_id.to_string(),
+        chaincode_id: chaincode_id.to_string(),
+        function: args.next().unwrap_or_default(),
+        args: args.collect(),
+        mspid: text(&tx["creator"]["mspid"]),
+        timestamp: json_timestamp(&tx["timestamp"])?,
+        // Never assume VALID without the block's transaction filter
+        validation_code: tx["status"]
+            .as_str()
+            .or_else(|| tx["validation_code"].as_str())
+            .unwrap_or("UNKNOWN")
+            .to_string(),
+    })
+}
+
+/// Event of a valid transaction, from `actions[0].event`
+///
+/// JSON payloads are decoded by the gateway and re-encoded here; string
+/// payloads are taken as they are.
+fn chaincode_event(record: &TransactionRecord, tx: &serde_json::Value) -> Option<ChaincodeEvent> {
+    if record.validation_code != "VALID" {
+        return None;
+    }
+    let event = &tx["actions"][0]["event"];
+    let event_name = event["eventName"]
+        .as_str()
+        .or_else(|| event["event_name"].as_str())
+        .filter(|name| !name.is_empty())?;
+    let chaincode_id = event["chaincodeId"]
+        .as_str()
+        .or_else(|| event["chaincode_id"].as_str())
+        .unwrap_or(&record.chaincode_id);
+    let payload = match &event["payload"] {
+        serde_json::Value::Null => Vec::new(),
+        serde_json::Value::String(payload) => payload.clone().into_bytes(),
+        other => other.to_string().into_bytes(),
+    };
+
+    Some(ChaincodeEvent {
+        channel_id: record.channel_id.clone(),
+        chaincode_id: chaincode_id.to_string(),
+        event_name: event_name.to_string(),
+        transaction_id: record.transaction_id.clone(),
+        block_number: record.block_number,
+        payload,
+    })
+}
+
+fn json_u64(value: &serde_json::Value) -> Option<u64> {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+/// RFC 3339 strings, `{"seconds", "nanos"}` objects, or Unix time in
+/// nanoseconds as the gateway's block decoder writes it
+fn json_timestamp(value: &serde_json::Value) -> Option<DateTime<Utc>> {
+    if let Some(text) = value.as_str() {
+        if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+            return Some(time.with_timezone(&Utc));
+        }
+    }
+    if value.is_object() {
+        let seconds = json_u64(&value["seconds"])? as i64;
+        let nanos = json_u64(&value["nanos"]).unwrap_or(0) as u32;
+        return Utc.timestamp_opt(seconds, nanos).single();
+    }
+    Some(Utc.timestamp_nanos(i64::try_from(json_u64(value)?).ok()?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{MockResponse, MockServer, TestCert};
 
     #[test]
     fn test_channel_creation() {
@@ -424,4 +1274,467 @@ mod tests {
 
         assert!(!client.is_connected());
     }
+
+    #[tokio::test]
+    async fn test_discovers_channels_from_gateway() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/channels" => MockResponse::json(
+                200,
+                serde_json::json!({"channels": [
+                    {"id": "movies", "chaincodes": [{"name": "movie-chaincode"}, {"name": "ratings"}]},
+                    "audit",
+                ]}),
+            ),
+            "/channels/audit/chaincodes" => MockResponse::json(200, serde_json::json!([])),
+            _ => MockResponse::json(404, serde_json::json!({})),
+        })
+        .await;
+        let client = server.connected_client().await;
+
+        let channels = client.get_channels().await.unwrap();
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0].id, "movies");
+        assert_eq!(channels[0].chaincode_id, "movie-chaincode");
+        assert_eq!(channels[0].chaincodes, vec!["movie-chaincode", "ratings"]);
+        assert_eq!(channels[1].id, "audit");
+        assert_eq!(channels[1].chaincode_id, "");
+        assert!(channels[1].chaincodes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_history_from_blocks() {
+        let server = MockServer::start(|request| {
+            let tx = |id: &str, function: &str, timestamp: serde_json::Value| {
+                serde_json::json!({
+                    "type": "ENDORSER_TRANSACTION",
+                    "tx_id": id,
+                    "timestamp": timestamp,
+                    "creator": {"mspid": "Org1MSP"},
+                    "status": "VALID",
+                    "actions": [{
+                        "chaincode_id": {"name": "movie-chaincode"},
+                        "input": {"args": [function, "m1"]},
+                    }],
+                })
+            };
+            match request.path.as_str() {
+                "/chaininfo?channel=movies" => {
+                    MockResponse::json(200, serde_json::json!({"result": {"height": 3}}))
+                }
+                "/blocks/0?channel=movies" => MockResponse::json(
+                    200,
+                    serde_json::json!({"block": {"transactions": [{"type": "CONFIG"}]}}),
+                ),
+                "/blocks/1?channel=movies" => {
+                    let mut t1 = tx("t1", "CreateMovie", serde_json::json!("2024-05-01T10:00:00Z"));
+                    t1.as_object_mut().unwrap().remove("status");
+                    MockResponse::json(200, serde_json::json!({"block": {"transactions": [t1]}}))
+                }
+                "/blocks/2?channel=movies" => MockResponse::json(
+                    200,
+                    serde_json::json!({"block": {"transactions": [
+                        tx("t2", "CreateMovie", serde_json::json!({"seconds": 1714561200, "nanos": 0})),
+                        tx("t3", "CreateMovie", serde_json::json!(1714561260000000000u64)),
+                    ]}}),
+                ),
+                _ => MockResponse::json(404, serde_json::json!({})),
+            }
+        })
+        .await;
+        let client = server.connected_client().await;
+
+        let query = HistoryQuery {
+            function: Some("CreateMovie".to_string()),
+            ..Default::default()
+        };
+        let page = client
+            .get_transaction_history("movies", "movie-chaincode", &query)
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.transactions.iter().map(|t| t.transaction_id.as_str()).collect();
+        assert_eq!(ids, vec!["t3", "t2", "t1"]);
+        assert_eq!(page.transactions[0].block_number, 2);
+        assert_eq!(page.transactions[0].args, vec!["m1".to_string()]);
+        let times: Vec<_> = page.transactions.iter().map(|t| t.timestamp).collect();
+        assert_eq!(
+            times,
+            vec![
+                Utc.with_ymd_and_hms(2024, 5, 1, 11, 1, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 5, 1, 11, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap(),
+            ]
+        );
+        assert_eq!(page.transactions[1].validation_code, "VALID");
+        assert_eq!(page.transactions[2].validation_code, "UNKNOWN");
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_events_poll_blocks_and_resume_from_file_checkpoint() {
+        use crate::events::{FabricEvent, FileCheckpointer};
+        use tokio_stream::StreamExt;
+
+        let server = MockServer::start(|request| {
+            let tx = |id: &str, event: &str| {
+                serde_json::json!({
+                    "type": "ENDORSER_TRANSACTION",
+                    "tx_id": id,
+                    "timestamp": 1714561200000000000u64,
+                    "creator": {"mspid": "Org1MSP"},
+                    "status": "VALID",
+                    "actions": [{
+                        "chaincode_id": {"name": "movie-chaincode"},
+                        "input": {"args": ["ApproveContentRequest", "tt1"]},
+                        "event": {
+                            "chaincodeId": "movie-chaincode",
+                            "eventName": event,
+                            "payload": {"imdbId": id},
+                        },
+                    }],
+                })
+            };
+            match request.path.as_str() {
+                "/chaininfo?channel=movies" => {
+                    MockResponse::json(200, serde_json::json!({"height": 3}))
+                }
+                "/blocks/1?channel=movies" => MockResponse::json(
+                    200,
+                    serde_json::json!({"block": {"transactions": [tx("t1", "ContentApproved")]}}),
+                ),
+                "/blocks/2?channel=movies" => MockResponse::json(
+                    200,
+                    serde_json::json!({"block": {"transactions": [
+                        tx("t2", "ContentRequested"),
+                        tx("t3", "ContentApproved"),
+                    ]}}),
+                ),
+                _ => MockResponse::json(404, serde_json::json!({})),
+            }
+        })
+        .await;
+        let client = server.connected_client().await;
+
+        let path = std::env::temp_dir().join(format!("fabric-checkpoint-{}.json", uuid::Uuid::new_v4()));
+        let filter = EventFilter {
+            start_block: Some(1),
+            ..EventFilter::chaincode("movie-chaincode").named("ContentApproved")
+        };
+        let subscribe = || {
+            client.subscribe_events(
+                "movies",
+                filter.clone(),
+                Arc::new(FileCheckpointer::new(&path)),
+            )
+        };
+        let transaction = |event: Option<Result<FabricEvent>>| match event {
+            Some(Ok(FabricEvent::Chaincode(event))) => event.transaction_id,
+            other => panic!("unexpected event {:?}", other),
+        };
+
+        let mut events = subscribe().await.unwrap();
+        assert_eq!(transaction(events.next().await), "t1");
+        assert_eq!(transaction(events.next().await), "t3");
+        drop(events);
+
+        // Restarting resumes after t1, the last acknowledged event
+        let mut events = subscribe().await.unwrap();
+        assert_eq!(transaction(events.next().await), "t3");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_async_submit_and_receipt_polling() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let receipt_polls = AtomicUsize::new(0);
+        let server = MockServer::start(move |request| match request.path.as_str() {
+            "/channels/movies/chaincodes/movie-chaincode/CreateMovie" => {
+                assert_eq!(request.json()["sync"], false);
+                MockResponse::json(200, serde_json::json!({"transactionId": "tx1", "sent": true}))
+            }
+            // Not found on the first poll, committed on the second
+            "/receipts/tx1" if receipt_polls.fetch_add(1, Ordering::SeqCst) == 0 => {
+                MockResponse::json(404, serde_json::json!({}))
+            }
+            "/receipts/tx1" => MockResponse::json(
+                200,
+                serde_json::json!({"status": "VALID", "blockNumber": "7"}),
+            ),
+            "/receipts/tx2" => MockResponse::json(
+                200,
+                serde_json::json!({
+                    "headers": {"type": "Error"},
+                    "validationCode": "ENDORSEMENT_POLICY_FAILURE",
+                }),
+            ),
+            _ => MockResponse::json(404, serde_json::json!({})),
+        })
+        .await;
+        let client = server.connected_client().await;
+
+        let submitted = client
+            .submit_chaincode("movies", "movie-chaincode", "CreateMovie", vec!["m1".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(submitted.transaction_id, "tx1");
+        assert_eq!(submitted.status, TransactionStatus::Submitted);
+
+        let receipt = client
+            .wait_for_commit("movies", "tx1", Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(receipt.status, TransactionStatus::Committed);
+        assert_eq!(receipt.block_number, Some(7));
+
+        let receipt = client
+            .wait_for_commit("movies", "tx2", Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(receipt.status, TransactionStatus::Invalid);
+        assert_eq!(receipt.validation_code, "ENDORSEMENT_POLICY_FAILURE");
+
+        let err = client
+            .wait_for_commit("movies", "tx3", Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, FabricCoreError::CommitTimeout { ref transaction_id } if transaction_id == "tx3")
+        );
+    }
+
+    #[test]
+    fn test_commit_receipt_needs_validation_evidence() {
+        let receipt = |body: serde_json::Value| commit_receipt("tx1", &body);
+
+        let valid = receipt(serde_json::json!({
+            "headers": {"type": "TransactionSuccess"},
+            "status": 0,
+        }))
+        .unwrap()
+        .unwrap();
+        assert_eq!(valid.status, TransactionStatus::Committed);
+        assert_eq!(valid.validation_code, "VALID");
+        let conflict = receipt(serde_json::json!({"result": {"status": "MVCC_READ_CONFLICT"}}))
+            .unwrap()
+            .unwrap();
+        assert_eq!(conflict.status, TransactionStatus::Invalid);
+        let failed = receipt(serde_json::json!({"headers": {"type": "Error"}}))
+            .unwrap()
+            .unwrap();
+        assert_eq!(failed.validation_code, "INVALID_OTHER_REASON");
+
+        // No evidence either way: keep waiting
+        assert_eq!(receipt(serde_json::json!({})).unwrap(), None);
+        assert_eq!(receipt(serde_json::json!({"status": "Pending"})).unwrap(), None);
+        // Never assumed valid
+        assert!(receipt(serde_json::json!({"headers": {"type": "TransactionSuccess"}})).is_err());
+        assert!(receipt(serde_json::json!({"status": "SUCCESS"})).is_err());
+        assert!(receipt(serde_json::json!({"headers": {"type": "Receipt"}})).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_commit_requires_connection() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(200, serde_json::json!({"status": "VALID"}))
+        })
+        .await;
+        let mut client = server.connected_client().await;
+        client.disconnect().await.unwrap();
+
+        let err = client
+            .wait_for_commit("movies", "tx1", Duration::from_secs(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FabricCoreError::ConnectionError(_)));
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invoke_fails_on_error_receipt() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/channels/movies/chaincodes/movie-chaincode/CreateMovie" => MockResponse::json(
+                200,
+                serde_json::json!({
+                    "headers": {"type": "Error"},
+                    "transactionId": "tx1",
+                    "errorMessage": "ENDORSEMENT_POLICY_FAILURE",
+                }),
+            ),
+            _ => MockResponse::json(200, serde_json::json!({"result": {}})),
+        })
+        .await;
+        let client = server.connected_client().await;
+
+        let err = client
+            .invoke_chaincode("movies", "movie-chaincode", "CreateMovie", Vec::new())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, FabricCoreError::InvocationError(ref m) if m.contains("tx1") && m.contains("ENDORSEMENT_POLICY_FAILURE"))
+        );
+        // No transaction ID means nothing can be confirmed
+        let err = client
+            .invoke_chaincode("movies", "movie-chaincode", "RateMovie", Vec::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FabricCoreError::InvocationError(ref m) if m.contains("transaction ID")));
+    }
+
+    #[test]
+    fn test_chaincode_args_encoding() {
+        #[derive(Serialize)]
+        struct Rating {
+            stars: u8,
+        }
+
+        let args = ChaincodeArgs::new()
+            .with_string("imdb_id", "tt0111161")
+            .with_value("year", &1994)
+            .unwrap()
+            .with_value("rating", &Rating { stars: 5 })
+            .unwrap()
+            .with_bytes("hash", b"\x00\xff")
+            .with_value("tags", &["drama"])
+            .unwrap();
+        assert_eq!(
+            args.to_fabric_args(),
+            vec!["tt0111161", "1994", r#"{"stars":5}"#, "AP8=", r#"["drama"]"#]
+        );
+        assert_eq!(args.args()[2].key, "rating");
+
+        let json = serde_json::json!(["tt0111161", 1994, {"stars": 5}, true]);
+        assert_eq!(
+            ChaincodeArgs::from_json(json).to_fabric_args(),
+            vec!["tt0111161", "1994", r#"{"stars":5}"#, "true"]
+        );
+        assert_eq!(
+            ChaincodeArgs::from_json(serde_json::json!({"stars": 5})).to_fabric_args(),
+            vec![r#"{"stars":5}"#]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_typed_decodes_result() {
+        #[derive(Debug, Default, PartialEq, Deserialize)]
+        #[serde(default)]
+        struct Movie {
+            title: String,
+            year: u16,
+        }
+
+        let server = MockServer::start(|request| {
+            assert_eq!(request.json()["args"], serde_json::json!(["tt0113277"]));
+            match request.path.as_str() {
+                "/channels/movies/chaincodes/movie-chaincode/GetMovie" => MockResponse::json(
+                    200,
+                    serde_json::json!({"result": {"title": "Heat", "year": 1995}}),
+                ),
+                _ => MockResponse::json(200, serde_json::json!({"error": "Movie not found"})),
+            }
+        })
+        .await;
+        let client = server.connected_client().await;
+        let args = ChaincodeArgs::new().with_string("imdb_id", "tt0113277");
+
+        let movie: Movie = client
+            .query_typed("movies", "movie-chaincode", "GetMovie", &args)
+            .await
+            .unwrap();
+        assert_eq!(
+            movie,
+            Movie {
+                title: "Heat".to_string(),
+                year: 1995,
+            }
+        );
+        let err = client
+            .query_typed::<Vec<Movie>>("movies", "movie-chaincode", "GetMovie", &args)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FabricCoreError::QueryError(ref m) if m.contains("GetMovie")));
+        // An unwrapped body is not taken for the payload
+        let err = client
+            .query_typed::<Movie>("movies", "movie-chaincode", "GetMissing", &args)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FabricCoreError::QueryError(ref m) if m.contains("Movie not found")));
+    }
+
+    #[tokio::test]
+    async fn test_private_calls_send_base64_transient_map() {
+        let server = MockServer::start(|request| {
+            let body = request.json();
+            assert_eq!(body["transient_map"]["contact"], "YWxpY2VAZXhhbXBsZS5jb20=");
+            assert_eq!(body["private_data"]["collection"], "submitterContacts");
+            assert_eq!(body["private_data"]["endorsing_organizations"][0], "Org1MSP");
+            MockResponse::json(
+                200,
+                serde_json::json!({"transactionId": "tx1", "result": {}}),
+            )
+        })
+        .await;
+        let client = server.connected_client().await;
+        let private = PrivateData::new()
+            .with_transient("contact", "alice@example.com")
+            .with_collection("submitterContacts", &["Org1MSP"]);
+
+        client
+            .query_chaincode_private(
+                "movies",
+                "movie-chaincode",
+                "ReadContact",
+                Vec::new(),
+                &private,
+            )
+            .await
+            .unwrap();
+        let result = client
+            .invoke_chaincode_private(
+                "movies",
+                "movie-chaincode",
+                "SubmitMovie",
+                Vec::new(),
+                &private,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.transaction_id, "tx1");
+    }
+
+    #[tokio::test]
+    async fn test_offline_fallback_is_opt_in() {
+        let server = MockServer::start(|_| MockResponse::json(503, serde_json::json!({}))).await;
+        let client = server.connected_client().await;
+        assert!(matches!(
+            client.get_channels().await,
+            Err(FabricCoreError::ChannelError(_))
+        ));
+
+        let mut config = client.config().clone();
+        config.offline_fallback = true;
+        let mut client = KaleidoFabricClient::new(config);
+        client.connect(&TestCert::identity()).await.unwrap();
+        let channels = client.get_channels().await.unwrap();
+        assert_eq!(
+            channels.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(),
+            vec!["movies", "tv-shows", "games", "voting"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mspid_mismatch_is_opt_in() {
+        let server = MockServer::start(|_| MockResponse::json(200, serde_json::json!({}))).await;
+        let mut config = server.connected_client().await.config().clone();
+        let mut identity = TestCert::identity();
+        identity.mspid = "Org2MSP".to_string();
+
+        let mut client = KaleidoFabricClient::new(config.clone());
+        assert!(matches!(
+            client.connect(&identity).await,
+            Err(FabricCoreError::MspIdMismatch { .. })
+        ));
+        config.allow_mspid_mismatch = true;
+        let mut client = KaleidoFabricClient::new(config);
+        client.connect(&identity).await.unwrap();
+    }
 }
@@ -0,0 +1,426 @@
+/// PKCS#12, JWK and PEM bundle import/export for identities
+///
+/// These are methods on `crypto::FabricIdentity`, so callers reach them
+/// through `fabric_core::crypto` like the rest of the identity API; only the
+/// encoding code lives here.
+use crate::certificate::CertificateInfo;
+use crate::crypto::{EcCurve, FabricIdentity};
+use crate::error::{FabricCoreError, Result};
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64URL};
+use base64::Engine;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::error::ErrorStack;
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::{PKey, Private};
+use openssl::sha::sha256;
+use openssl::stack::Stack;
+use openssl::symm::Cipher;
+use openssl::x509::X509;
+use serde::Deserialize;
+
+/// Private EC key in JWK form, with the certificate chain in `x5c`
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    crv: String,
+    x: String,
+    y: String,
+    d: Option<String>,
+    #[serde(default)]
+    x5c: Vec<String>,
+}
+
+impl FabricIdentity {
+    /// Encode as a password-protected PKCS#12 archive
+    ///
+    /// The archive holds the private key, the certificate under the user ID
+    /// as friendly name, and every certificate of `ca_certificate`.
+    pub fn to_pkcs12(&self, password: &str) -> Result<Vec<u8>> {
+        let key = self.exportable_key()?;
+        let cert = X509::from_pem(self.certificate.as_bytes()).map_err(format_err)?;
+        let mut ca = Stack::new().map_err(format_err)?;
+        for cert in ca_chain(&self.ca_certificate)? {
+            ca.push(cert).map_err(format_err)?;
+        }
+
+        let pkcs12 = Pkcs12::builder()
+            .name(&self.user_id)
+            .pkey(&key)
+            .cert(&cert)
+            .ca(ca)
+            .build2(password)
+            .map_err(format_err)?;
+        pkcs12.to_der().map_err(format_err)
+    }
+
+    /// Decode a PKCS#12 archive
+    ///
+    /// User ID and organization come from the certificate subject; the MSP
+    /// ID defaults to `<organization>MSP`.
+    pub fn from_pkcs12(der: &[u8], password: &str, mspid: Option<&str>) -> Result<Self> {
+        let parsed = Pkcs12::from_der(der)
+            .map_err(format_err)?
+            .parse2(password)
+            .map_err(|e| {
+                if is_wrong_password(&e) {
+                    FabricCoreError::InvalidPassphrase
+                } else {
+                    format_err(e)
+                }
+            })?;
+        let key = parsed
+            .pkey
+            .ok_or_else(|| format_err("PKCS#12 archive has no private key"))?;
+        let cert = parsed
+            .cert
+            .ok_or_else(|| format_err("PKCS#12 archive has no certificate"))?;
+        let mut ca_certificate = String::new();
+        for ca in parsed.ca.into_iter().flatten() {
+            ca_certificate.push_str(&pem_string(ca.to_pem().map_err(format_err)?)?);
+        }
+
+        identity_from_parts(
+            &key,
+            pem_string(cert.to_pem().map_err(format_err)?)?,
+            ca_certificate,
+            mspid,
+        )
+    }
+
+    /// Encode as a private EC JWK (RFC 7517)
+    ///
+    /// `x5c` holds the certificate followed by the CA chain, and `kid` is
+    /// the RFC 7638 thumbprint of the public key.
+    pub fn to_jwk(&self) -> Result<String> {
+        let key = self.exportable_key()?;
+        let ec = key.ec_key().map_err(format_err)?;
+        let curve = ec
+            .group()
+            .curve_name()
+            .and_then(EcCurve::from_nid)
+            .ok_or_else(|| format_err("Unsupported elliptic curve"))?;
+        let size = curve.field_size() as i32;
+
+        let mut context = BigNumContext::new().map_err(format_err)?;
+        let mut x = BigNum::new().map_err(format_err)?;
+        let mut y = BigNum::new().map_err(format_err)?;
+        ec.public_key()
+            .affine_coordinates(ec.group(), &mut x, &mut y, &mut context)
+            .map_err(format_err)?;
+        let x = BASE64URL.encode(x.to_vec_padded(size).map_err(format_err)?);
+        let y = BASE64URL.encode(y.to_vec_padded(size).map_err(format_err)?);
+        let d = BASE64URL.encode(ec.private_key().to_vec_padded(size).map_err(format_err)?);
+
+        let thumbprint = format!(
+            r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#,
+            curve, x, y
+        );
+        let mut x5c = vec![BASE64.encode(
+            X509::from_pem(self.certificate.as_bytes())
+                .and_then(|cert| cert.to_der())
+                .map_err(format_err)?,
+        )];
+        for cert in ca_chain(&self.ca_certificate)? {
+            x5c.push(BASE64.encode(cert.to_der().map_err(format_err)?));
+        }
+
+        let jwk = serde_json::json!({
+            "kty": "EC",
+            "crv": curve.to_string(),
+            "x": x,
+            "y": y,
+            "d": d,
+            "use": "sig",
+            "alg": jws_algorithm(curve),
+            "kid": BASE64URL.encode(sha256(thumbprint.as_bytes())),
+            "x5c": x5c,
+        });
+        Ok(serde_json::to_string_pretty(&jwk)?)
+    }
+
+    /// Decode a private EC JWK whose `x5c` carries the certificate chain
+    pub fn from_jwk(json: &str, mspid: Option<&str>) -> Result<Self> {
+        let jwk: Jwk = serde_json::from_str(json)?;
+        if jwk.kty != "EC" {
+            return Err(format_err(format!("Unsupported JWK key type: {}", jwk.kty)));
+        }
+        let curve: EcCurve = jwk.crv.parse()?;
+        let d = jwk
+            .d
+            .ok_or_else(|| format_err("JWK has no private key (d)"))?;
+        let mut certs = jwk.x5c.iter();
+        let certificate = certs
+            .next()
+            .ok_or_else(|| format_err("JWK has no certificate (x5c)"))
+            .and_then(|der| der_to_pem(der))?;
+        let ca_certificate = certs
+            .map(|der| der_to_pem(der))
+            .collect::<Result<String>>()?;
+
+        let group = EcGroup::from_curve_name(curve.nid()).map_err(format_err)?;
+        let x = BigNum::from_slice(&base64url(&jwk.x)?).map_err(format_err)?;
+        let y = BigNum::from_slice(&base64url(&jwk.y)?).map_err(format_err)?;
+        let public = EcKey::from_public_key_affine_coordinates(&group, &x, &y)
+            .map_err(|e| FabricCoreError::InvalidPrivateKey(e.to_string()))?;
+        let d = BigNum::from_slice(&base64url(&d)?).map_err(format_err)?;
+        let key = EcKey::from_private_components(&group, &d, public.public_key())
+            .and_then(|key| key.check_key().map(|_| key))
+            .and_then(PKey::from_ec_key)
+            .map_err(|e| FabricCoreError::InvalidPrivateKey(e.to_string()))?;
+
+        identity_from_parts(&key, certificate, ca_certificate, mspid)
+    }
+
+    /// Concatenate the private key, certificate and CA chain as PEM
+    ///
+    /// With a passphrase, the key is written as encrypted PKCS#8.
+    pub fn to_pem_bundle(&self, passphrase: Option<&str>) -> Result<String> {
+        let key = self.exportable_key()?;
+        let key_pem = match passphrase {
+            Some(passphrase) => key
+                .private_key_to_pem_pkcs8_passphrase(Cipher::aes_256_cbc(), passphrase.as_bytes()),
+            None => key.private_key_to_pem_pkcs8(),
+        }
+        .map_err(format_err)?;
+
+        let mut bundle = pem_string(key_pem)?;
+        bundle.push_str(self.certificate.trim_end());
+        bundle.push('\n');
+        for cert in ca_chain(&self.ca_certificate)? {
+            bundle.push_str(&pem_string(cert.to_pem().map_err(format_err)?)?);
+        }
+        Ok(bundle)
+    }
+
+    /// Decode a PEM bundle: a private key, the certificate, then CA certificates
+    pub fn from_pem_bundle(
+        pem: &str,
+        passphrase: Option<&str>,
+        mspid: Option<&str>,
+    ) -> Result<Self> {
+        let key = match passphrase {
+            Some(passphrase) => {
+                PKey::private_key_from_pem_passphrase(pem.as_bytes(), passphrase.as_bytes())
+                    .map_err(|_| FabricCoreError::InvalidPassphrase)?
+            }
+            // Checked up front, as OpenSSL would otherwise prompt on the terminal
+            None if pem.contains("ENCRYPTED PRIVATE KEY") => {
+                return Err(FabricCoreError::PassphraseRequired)
+            }
+            None => PKey::private_key_from_pem(pem.as_bytes())
+                .map_err(|e| FabricCoreError::InvalidPrivateKey(e.to_string()))?,
+        };
+        let mut certs = X509::stack_from_pem(pem.as_bytes())
+            .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))?
+            .into_iter();
+        let certificate = certs
+            .next()
+            .ok_or_else(|| format_err("PEM bundle has no certificate"))?;
+        let mut ca_certificate = String::new();
+        for cert in certs {
+            ca_certificate.push_str(&pem_string(cert.to_pem().map_err(format_err)?)?);
+        }
+
+        identity_from_parts(
+            &key,
+            pem_string(certificate.to_pem().map_err(format_err)?)?,
+            ca_certificate,
+            mspid,
+        )
+    }
+
+    /// The software private key, refusing identities whose key lives elsewhere
+    fn exportable_key(&self) -> Result<PKey<Private>> {
+        if self.private_key.is_empty() {
+            return Err(FabricCoreError::KeyManagementError(
+                "Identity has no exportable private key".to_string(),
+            ));
+        }
+        PKey::private_key_from_pem(self.private_key.expose_secret().as_bytes())
+            .map_err(|e| FabricCoreError::InvalidPrivateKey(e.to_string()))
+    }
+}
+
+/// Build an identity, naming it after the certificate subject
+pub(crate) fn identity_from_parts(
+    key: &PKey<Private>,
+    certificate: String,
+    ca_certificate: String,
+    mspid: Option<&str>,
+) -> Result<FabricIdentity> {
+    let info = CertificateInfo::from_pem(&certificate)?;
+    let user_id = info
+        .common_name
+        .clone()
+        .ok_or_else(|| format_err("Certificate has no common name"))?;
+    let org_name = info.organization.clone().unwrap_or_default();
+    let mspid = mspid
+        .map(|m| m.to_string())
+        .unwrap_or_else(|| format!("{}MSP", org_name));
+
+    let identity = FabricIdentity::new(
+        pem_string(key.private_key_to_pem_pkcs8().map_err(format_err)?)?,
+        pem_string(key.public_key_to_pem().map_err(format_err)?)?,
+        certificate,
+        ca_certificate,
+        user_id,
+        org_name,
+        mspid,
+    );
+    // The MSP ID is checked when connecting, per `allow_mspid_mismatch`
+    identity.validate_allowing_mspid_mismatch()?;
+    Ok(identity)
+}
+
+fn ca_chain(pem: &str) -> Result<Vec<X509>> {
+    if pem.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    X509::stack_from_pem(pem.as_bytes())
+        .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))
+}
+
+fn jws_algorithm(curve: EcCurve) -> &'static str {
+    match curve {
+        EcCurve::P256 => "ES256",
+        EcCurve::P384 => "ES384",
+    }
+}
+
+fn der_to_pem(der_base64: &str) -> Result<String> {
+    let der = BASE64.decode(der_base64).map_err(format_err)?;
+    let cert =
+        X509::from_der(&der).map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))?;
+    pem_string(cert.to_pem().map_err(format_err)?)
+}
+
+fn base64url(value: &str) -> Result<Vec<u8>> {
+    BASE64URL
+        .decode(value.trim_end_matches('='))
+        .map_err(format_err)
+}
+
+fn pem_string(pem: Vec<u8>) -> Result<String> {
+    String::from_utf8(pem).map_err(format_err)
+}
+
+/// Whether a PKCS#12 parse failed on the MAC or on decryption, the ways a
+/// wrong password shows up
+fn is_wrong_password(errors: &ErrorStack) -> bool {
+    errors.errors().iter().any(|e| {
+        matches!(
+            e.reason(),
+            Some("mac verify failure" | "pkcs12 cipherfinal error" | "bad decrypt")
+        )
+    })
+}
+
+fn format_err(e: impl std::fmt::Display) -> FabricCoreError {
+    FabricCoreError::KeyManagementError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{CertSpec, TestCert};
+
+    fn identity() -> (FabricIdentity, TestCert) {
+        let root = TestCert::root("ca.org1.example.com");
+        let intermediate = root.issue(CertSpec {
+            common_name: "ica.org1.example.com",
+            ca: true,
+            ..Default::default()
+        });
+        let leaf = intermediate.issue(CertSpec::default());
+        let identity = FabricIdentity::new(
+            leaf.key_pem(),
+            leaf.public_key_pem(),
+            leaf.cert_pem(),
+            root.cert_pem() + &intermediate.cert_pem(),
+            "user1".to_string(),
+            "Org1".to_string(),
+            "Org1MSP".to_string(),
+        );
+        (identity, root)
+    }
+
+    fn chain(pem: &str) -> Vec<Vec<u8>> {
+        let mut certs: Vec<_> = ca_chain(pem)
+            .unwrap()
+            .iter()
+            .map(|cert| cert.to_der().unwrap())
+            .collect();
+        certs.sort();
+        certs
+    }
+
+    fn assert_same(imported: &FabricIdentity, original: &FabricIdentity) {
+        assert_eq!(imported.private_key, original.private_key);
+        assert_eq!(imported.certificate, original.certificate);
+        assert_eq!(
+            chain(&imported.ca_certificate),
+            chain(&original.ca_certificate)
+        );
+        assert_eq!(imported.user_id, "user1");
+        assert_eq!(imported.mspid, "Org1MSP");
+    }
+
+    #[test]
+    fn test_pkcs12_round_trip_keeps_chain() {
+        let (identity, _) = identity();
+        let der = identity.to_pkcs12("p12-secret").unwrap();
+
+        let imported = FabricIdentity::from_pkcs12(&der, "p12-secret", None).unwrap();
+        assert_same(&imported, &identity);
+        assert_eq!(chain(&imported.ca_certificate).len(), 2);
+
+        assert!(matches!(
+            FabricIdentity::from_pkcs12(&der, "wrong", None),
+            Err(FabricCoreError::InvalidPassphrase)
+        ));
+        // A damaged archive is a format error, not a wrong password
+        assert!(matches!(
+            FabricIdentity::from_pkcs12(&der[..der.len() / 2], "p12-secret", None),
+            Err(FabricCoreError::KeyManagementError(_))
+        ));
+    }
+
+    #[test]
+    fn test_jwk_round_trip() {
+        let (identity, _) = identity();
+        let json = identity.to_jwk().unwrap();
+        let jwk: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(jwk["crv"], "P-256");
+        assert_eq!(jwk["alg"], "ES256");
+        assert_eq!(jwk["x"].as_str().unwrap().len(), 43);
+        assert_eq!(jwk["x5c"].as_array().unwrap().len(), 3);
+
+        let imported = FabricIdentity::from_jwk(&json, Some("Org1MSP")).unwrap();
+        assert_same(&imported, &identity);
+
+        let mut public_only = jwk.clone();
+        public_only.as_object_mut().unwrap().remove("d");
+        assert!(FabricIdentity::from_jwk(&public_only.to_string(), None).is_err());
+    }
+
+    #[test]
+    fn test_pem_bundle_round_trip() {
+        let (identity, _) = identity();
+        let plain = identity.to_pem_bundle(None).unwrap();
+        assert_same(
+            &FabricIdentity::from_pem_bundle(&plain, None, None).unwrap(),
+            &identity,
+        );
+
+        let encrypted = identity.to_pem_bundle(Some("bundle-secret")).unwrap();
+        assert!(encrypted.contains("ENCRYPTED PRIVATE KEY"));
+        assert!(matches!(
+            FabricIdentity::from_pem_bundle(&encrypted, None, None),
+            Err(FabricCoreError::PassphraseRequired)
+        ));
+        let imported =
+            FabricIdentity::from_pem_bundle(&encrypted, Some("bundle-secret"), None).unwrap();
+        assert_same(&imported, &identity);
+    }
+}
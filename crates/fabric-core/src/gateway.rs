@@ -0,0 +1,1433 @@
+/// Fabric Gateway gRPC client
+use crate::crypto::FabricIdentity;
+use crate::error::{FabricCoreError, Result};
+use crate::events::{
+    event_stream, resume_position, BlockEvent, BlockStream, ChaincodeEvent, Checkpointer,
+    EventFilter, EventStream,
+};
+use crate::fabric::{
+    discovered_or_offline, validate_identity, CommitReceipt, FabricChannel, FabricNetworkClient,
+    FabricNetworkConfig, SharedIdentity, TransactionResult, TransactionStatus,
+};
+use crate::history::{
+    collect_history, BlockSource, HistoryQuery, TransactionPage, TransactionRecord,
+};
+use crate::private_data::PrivateData;
+use crate::protos::common::{
+    self, Block, BlockMetadataIndex, BlockchainInfo, ChannelHeader, Envelope, Header, HeaderType,
+    Payload, SignatureHeader, Timestamp,
+};
+use crate::protos::gateway::{
+    CommitStatusRequest, CommitStatusResponse, EndorseRequest, EndorseResponse, EvaluateRequest,
+    EvaluateResponse, SignedCommitStatusRequest, SubmitRequest, SubmitResponse,
+};
+use crate::protos::lifecycle::{QueryChaincodeDefinitionsArgs, QueryChaincodeDefinitionsResult};
+use crate::protos::msp::SerializedIdentity;
+use crate::protos::orderer::{seek_position, SeekBehavior, SeekInfo, SeekPosition, SeekSpecified};
+use crate::protos::peer::{
+    self, deliver_response, ChaincodeAction, ChaincodeActionPayload, ChaincodeHeaderExtension,
+    ChaincodeId, ChaincodeInput, ChaincodeInvocationSpec, ChaincodeProposalPayload, ChaincodeSpec,
+    ChannelQueryResponse, DeliverResponse, Proposal, ProposalResponse, ProposalResponsePayload,
+    SignedProposal, Transaction, TxValidationCode,
+};
+use crate::tls::{self, TlsClientAuth};
+use async_trait::async_trait;
+use openssl::rand::rand_bytes;
+use openssl::sha::sha256;
+use prost::Message;
+use rustls::ServerName;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_stream::StreamExt;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::codegen::http::Uri;
+use tonic::transport::{Channel, Endpoint};
+use tonic::Status;
+
+pub(crate) const EVALUATE: &str = "/gateway.Gateway/Evaluate";
+pub(crate) const ENDORSE: &str = "/gateway.Gateway/Endorse";
+pub(crate) const SUBMIT: &str = "/gateway.Gateway/Submit";
+pub(crate) const COMMIT_STATUS: &str = "/gateway.Gateway/CommitStatus";
+pub(crate) const PROCESS_PROPOSAL: &str = "/protos.Endorser/ProcessProposal";
+pub(crate) const DELIVER: &str = "/protos.Deliver/Deliver";
+
+/// Transaction endorsed by the gateway and ready to submit
+#[derive(Debug, Clone)]
+pub struct PreparedTransaction {
+    pub transaction_id: String,
+    pub channel_id: String,
+    /// Chaincode response payload agreed by the endorsers
+    pub result: Vec<u8>,
+    envelope: Envelope,
+}
+
+/// Commit outcome of a submitted transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitStatus {
+    pub code: TxValidationCode,
+    pub block_number: u64,
+}
+
+impl CommitStatus {
+    pub fn is_valid(&self) -> bool {
+        self.code == TxValidationCode::Valid
+    }
+}
+
+/// `FabricNetworkClient` speaking the peer Gateway service over gRPC
+///
+/// `gateway_url` in the network configuration is the peer's gateway
+/// endpoint, e.g. `https://peer0.org1.example.com:7051`. Channel discovery
+/// uses the peer's Endorser service on the same endpoint. Proposals,
+/// transactions and commit status requests are signed with the connected
+/// identity's signer, so HSM-backed identities work unchanged.
+pub struct FabricGatewayClient {
+    config: FabricNetworkConfig,
+    connected: bool,
+    identity: SharedIdentity,
+    /// gRPC channel and the certificate it was opened with
+    channel: Mutex<Option<(String, Channel)>>,
+    endorsing_organizations: Vec<String>,
+}
+
+impl FabricGatewayClient {
+    pub fn new(config: FabricNetworkConfig) -> Self {
+        Self {
+            config,
+            connected: false,
+            identity: SharedIdentity::default(),
+            channel: Mutex::new(None),
+            endorsing_organizations: Vec::new(),
+        }
+    }
+
+    /// Restrict endorsement to peers of these MSP IDs instead of letting
+    /// the gateway choose from the endorsement policy
+    pub fn with_endorsing_organizations(mut self, mspids: Vec<String>) -> Self {
+        self.endorsing_organizations = mspids;
+        self
+    }
+
+    /// Get the network configuration
+    pub fn config(&self) -> &FabricNetworkConfig {
+        &self.config
+    }
+
+    /// Check if connected
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// The identity currently used for requests
+    pub fn identity(&self) -> Option<FabricIdentity> {
+        self.identity.get()
+    }
+
+    /// Handle to the client's identity slot, for background tasks
+    pub fn shared_identity(&self) -> SharedIdentity {
+        self.identity.clone()
+    }
+
+    /// Run a transaction function on one peer without ordering it
+    pub async fn evaluate(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: &[Vec<u8>],
+    ) -> Result<Vec<u8>> {
+        self.evaluate_private(
+            channel_id,
+            chaincode_id,
+            function,
+            args,
+            &PrivateData::new(),
+        )
+        .await
+    }
+
+    /// `evaluate` with transient data, on a peer of `private.organizations`
+    /// when set
+    pub async fn evaluate_private(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: &[Vec<u8>],
+        private: &PrivateData,
+    ) -> Result<Vec<u8>> {
+        let (mut grpc, identity) = self.session().await?;
+        let proposal = SignedTransactionProposal::new(
+            &identity,
+            channel_id,
+            chaincode_id,
+            function,
+            args,
+            private.transient_map(),
+        )?;
+        let request = EvaluateRequest {
+            transaction_id: proposal.transaction_id,
+            channel_id: channel_id.to_string(),
+            proposed_transaction: Some(proposal.signed),
+            target_organizations: private.organizations.clone(),
+        };
+
+        tracing::debug!(
+            "Evaluating chaincode: channel={}, id={}, function={}",
+            channel_id,
+            chaincode_id,
+            function
+        );
+        let response: EvaluateResponse = grpc
+            .unary(EVALUATE, request)
+            .await
+            .map_err(|s| FabricCoreError::QueryError(status_message(&s)))?;
+        let result = response.result.unwrap_or_default();
+        if result.status >= 400 {
+            return Err(FabricCoreError::QueryError(format!(
+                "Chaincode returned status {}: {}",
+                result.status, result.message
+            )));
+        }
+        Ok(result.payload)
+    }
+
+    /// Collect endorsements for a transaction and sign the result
+    pub async fn endorse(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: &[Vec<u8>],
+    ) -> Result<PreparedTransaction> {
+        self.endorse_private(
+            channel_id,
+            chaincode_id,
+            function,
+            args,
+            &PrivateData::new(),
+        )
+        .await
+    }
+
+    /// `endorse` with transient data
+    ///
+    /// The gateway strips the transient map from the prepared transaction.
+    /// `private.organizations`, when set, replaces the client's endorsing
+    /// organizations; otherwise the gateway plans endorsement from the
+    /// chaincode's collection configuration.
+    pub async fn endorse_private(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: &[Vec<u8>],
+        private: &PrivateData,
+    ) -> Result<PreparedTransaction> {
+        let (mut grpc, identity) = self.session().await?;
+        let proposal = SignedTransactionProposal::new(
+            &identity,
+            channel_id,
+            chaincode_id,
+            function,
+            args,
+            private.transient_map(),
+        )?;
+        let transaction_id = proposal.transaction_id.clone();
+        let endorsing_organizations = if private.organizations.is_empty() {
+            self.endorsing_organizations.clone()
+        } else {
+            private.organizations.clone()
+        };
+        let request = EndorseRequest {
+            transaction_id: proposal.transaction_id,
+            channel_id: channel_id.to_string(),
+            proposed_transaction: Some(proposal.signed),
+            endorsing_organizations,
+        };
+
+        tracing::info!(
+            "Endorsing transaction: channel={}, id={}, function={}, txn_id={}",
+            channel_id,
+            chaincode_id,
+            function,
+            transaction_id
+        );
+        let response: EndorseResponse = grpc
+            .unary(ENDORSE, request)
+            .await
+            .map_err(|s| FabricCoreError::InvocationError(status_message(&s)))?;
+        let mut envelope = response.prepared_transaction.ok_or_else(|| {
+            FabricCoreError::InvocationError("Gateway returned no transaction".to_string())
+        })?;
+        let result = endorsed_result(&envelope)?;
+        envelope.signature = identity.signer()?.sign(&envelope.payload)?;
+
+        Ok(PreparedTransaction {
+            transaction_id,
+            channel_id: channel_id.to_string(),
+            result,
+            envelope,
+        })
+    }
+
+    /// Send an endorsed transaction to the orderer
+    pub async fn submit(&self, transaction: &PreparedTransaction) -> Result<()> {
+        let (mut grpc, _) = self.session().await?;
+        let request = SubmitRequest {
+            transaction_id: transaction.transaction_id.clone(),
+            channel_id: transaction.channel_id.clone(),
+            prepared_transaction: Some(transaction.envelope.clone()),
+        };
+        let _: SubmitResponse = grpc
+            .unary(SUBMIT, request)
+            .await
+            .map_err(|s| FabricCoreError::InvocationError(status_message(&s)))?;
+        Ok(())
+    }
+
+    /// Wait for a submitted transaction to be committed and report its
+    /// validation code
+    pub async fn commit_status(
+        &self,
+        channel_id: &str,
+        transaction_id: &str,
+    ) -> Result<CommitStatus> {
+        let (mut grpc, identity) = self.session().await?;
+        let request = CommitStatusRequest {
+            transaction_id: transaction_id.to_string(),
+            channel_id: channel_id.to_string(),
+            identity: creator(&identity),
+        }
+        .encode_to_vec();
+        let signature = identity.signer()?.sign(&request)?;
+        let response: CommitStatusResponse = grpc
+            .unary(
+                COMMIT_STATUS,
+                SignedCommitStatusRequest { request, signature },
+            )
+            .await
+            .map_err(|s| FabricCoreError::InvocationError(status_message(&s)))?;
+        Ok(CommitStatus {
+            code: TxValidationCode::try_from(response.result)
+                .unwrap_or(TxValidationCode::InvalidOtherReason),
+            block_number: response.block_number,
+        })
+    }
+
+    /// Channels the peer has joined, via CSCC `GetChannels`, each with the
+    /// chaincodes committed through `_lifecycle`
+    async fn discover_channels(&self) -> Result<Vec<FabricChannel>> {
+        let joined = self
+            .process_proposal(
+                "",
+                "cscc",
+                "GetChannels",
+                &[],
+                FabricCoreError::ChannelError,
+            )
+            .await?;
+        let joined = ChannelQueryResponse::decode(joined.as_slice())
+            .map_err(|e| FabricCoreError::ChannelError(e.to_string()))?;
+
+        let mut channels = Vec::with_capacity(joined.channels.len());
+        for channel in joined.channels {
+            let definitions = self
+                .process_proposal(
+                    &channel.channel_id,
+                    "_lifecycle",
+                    "QueryChaincodeDefinitions",
+                    &[QueryChaincodeDefinitionsArgs {}.encode_to_vec()],
+                    FabricCoreError::ChannelError,
+                )
+                .await?;
+            let definitions = QueryChaincodeDefinitionsResult::decode(definitions.as_slice())
+                .map_err(|e| FabricCoreError::ChannelError(e.to_string()))?;
+            let chaincodes = definitions
+                .chaincode_definitions
+                .into_iter()
+                .map(|definition| definition.name)
+                .collect();
+            channels.push(FabricChannel::discovered(channel.channel_id, chaincodes));
+        }
+        Ok(channels)
+    }
+
+    /// Query a system chaincode on the connected peer directly
+    async fn process_proposal(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: &[Vec<u8>],
+        error: fn(String) -> FabricCoreError,
+    ) -> Result<Vec<u8>> {
+        let (mut grpc, identity) = self.session().await?;
+        let proposal = SignedTransactionProposal::new(
+            &identity,
+            channel_id,
+            chaincode_id,
+            function,
+            args,
+            HashMap::new(),
+        )?;
+        let response: ProposalResponse = grpc
+            .unary(PROCESS_PROPOSAL, proposal.signed)
+            .await
+            .map_err(|s| error(status_message(&s)))?;
+        let response = response.response.unwrap_or_default();
+        if response.status >= 400 {
+            return Err(error(format!(
+                "{} {} returned status {}: {}",
+                chaincode_id, function, response.status, response.message
+            )));
+        }
+        Ok(response.payload)
+    }
+
+    /// Stream blocks from `start` on through the peer's Deliver service
+    async fn deliver_blocks(&self, channel_id: &str, start: u64) -> Result<BlockStream> {
+        let (mut grpc, identity) = self.session().await?;
+        let request = seek_envelope(&identity, channel_id, start)?;
+        let responses = grpc
+            .server_streaming::<_, DeliverResponse>(DELIVER, request)
+            .await
+            .map_err(|s| FabricCoreError::EventError(status_message(&s)))?;
+
+        let channel_id = channel_id.to_string();
+        Ok(Box::pin(responses.map(move |response| {
+            let response = response.map_err(|s| FabricCoreError::EventError(status_message(&s)))?;
+            match response.r#type {
+                Some(deliver_response::Type::Block(block)) => Ok(block_event(&channel_id, block)),
+                Some(deliver_response::Type::Status(status)) => {
+                    Err(FabricCoreError::EventError(format!(
+                        "Deliver stream ended with status {:?}",
+                        common::Status::try_from(status).unwrap_or(common::Status::Unknown)
+                    )))
+                }
+                None => Err(FabricCoreError::EventError(
+                    "Empty Deliver response".to_string(),
+                )),
+            }
+        })))
+    }
+
+    /// Stub and identity for one request
+    ///
+    /// With identity mutual TLS the channel is reopened once the identity
+    /// has been swapped, e.g. by reenrollment, so the new certificate is
+    /// presented.
+    async fn session(&self) -> Result<(GatewayStub, FabricIdentity)> {
+        let cached = self
+            .channel
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let (certificate, channel) = match (cached, self.connected) {
+            (Some(cached), true) => cached,
+            _ => {
+                return Err(FabricCoreError::ConnectionError(
+                    "Not connected to network".to_string(),
+                ))
+            }
+        };
+        let identity = self
+            .identity
+            .get()
+            .ok_or_else(|| FabricCoreError::ConnectionError("No identity loaded".to_string()))?;
+
+        let channel = if self.config.tls.client_auth == TlsClientAuth::Identity
+            && identity.certificate != certificate
+        {
+            let channel = connect_channel(&self.config, &identity).await?;
+            *self.channel.lock().unwrap_or_else(|e| e.into_inner()) =
+                Some((identity.certificate.clone(), channel.clone()));
+            channel
+        } else {
+            channel
+        };
+        Ok((GatewayStub(tonic::client::Grpc::new(channel)), identity))
+    }
+}
+
+#[async_trait]
+impl FabricNetworkClient for FabricGatewayClient {
+    async fn connect(&mut self, identity: &FabricIdentity) -> Result<()> {
+        validate_identity(&self.config, identity)?;
+
+        let channel = connect_channel(&self.config, identity).await?;
+        *self.channel.get_mut().unwrap_or_else(|e| e.into_inner()) =
+            Some((identity.certificate.clone(), channel));
+        self.identity.replace(identity.clone());
+        self.connected = true;
+
+        tracing::info!(
+            "Connected to Fabric Gateway: {} ({})",
+            self.config.name,
+            self.config.gateway_url
+        );
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        self.connected = false;
+        self.identity.clear();
+        self.channel
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+
+        tracing::info!("Disconnected from Fabric Gateway");
+        Ok(())
+    }
+
+    async fn get_channels(&self) -> Result<Vec<FabricChannel>> {
+        self.session().await?;
+        let channels = discovered_or_offline(&self.config, self.discover_channels().await)?;
+
+        tracing::debug!("Retrieved {} channels", channels.len());
+        Ok(channels)
+    }
+
+    async fn query_chaincode(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+    ) -> Result<serde_json::Value> {
+        self.query_chaincode_private(
+            channel_id,
+            chaincode_id,
+            function,
+            args,
+            &PrivateData::new(),
+        )
+        .await
+    }
+
+    async fn query_chaincode_private(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+        private: &PrivateData,
+    ) -> Result<serde_json::Value> {
+        let args: Vec<Vec<u8>> = args.into_iter().map(String::into_bytes).collect();
+        let payload = self
+            .evaluate_private(channel_id, chaincode_id, function, &args, private)
+            .await?;
+        Ok(payload_to_json(&payload))
+    }
+
+    async fn invoke_chaincode(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+    ) -> Result<TransactionResult> {
+        self.invoke_chaincode_private(
+            channel_id,
+            chaincode_id,
+            function,
+            args,
+            &PrivateData::new(),
+        )
+        .await
+    }
+
+    async fn invoke_chaincode_private(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+        private: &PrivateData,
+    ) -> Result<TransactionResult> {
+        let args: Vec<Vec<u8>> = args.into_iter().map(String::into_bytes).collect();
+        let transaction = self
+            .endorse_private(channel_id, chaincode_id, function, &args, private)
+            .await?;
+        self.submit(&transaction).await?;
+        let status = self
+            .commit_status(channel_id, &transaction.transaction_id)
+            .await?;
+        if !status.is_valid() {
+            return Err(FabricCoreError::InvocationError(format!(
+                "Transaction {} failed validation with code {}",
+                transaction.transaction_id,
+                status.code.as_str_name()
+            )));
+        }
+
+        tracing::info!(
+            "Transaction committed: txn_id={}, block={}",
+            transaction.transaction_id,
+            status.block_number
+        );
+
+        Ok(TransactionResult {
+            transaction_id: transaction.transaction_id,
+            status: TransactionStatus::Committed,
+            payload: payload_to_json(&transaction.result),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    async fn submit_chaincode(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: Vec<String>,
+    ) -> Result<TransactionResult> {
+        let args: Vec<Vec<u8>> = args.into_iter().map(String::into_bytes).collect();
+        let transaction = self
+            .endorse(channel_id, chaincode_id, function, &args)
+            .await?;
+        self.submit(&transaction).await?;
+
+        Ok(TransactionResult {
+            transaction_id: transaction.transaction_id,
+            status: TransactionStatus::Submitted,
+            payload: payload_to_json(&transaction.result),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// The gateway's `CommitStatus` call blocks until the commit, so the
+    /// timeout bounds that single call
+    async fn wait_for_commit(
+        &self,
+        channel_id: &str,
+        transaction_id: &str,
+        timeout: Duration,
+    ) -> Result<CommitReceipt> {
+        let status = tokio::time::timeout(timeout, self.commit_status(channel_id, transaction_id))
+            .await
+            .map_err(|_| FabricCoreError::CommitTimeout {
+                transaction_id: transaction_id.to_string(),
+            })??;
+        let validation_code = status.code.as_str_name().to_string();
+        Ok(CommitReceipt {
+            transaction_id: transaction_id.to_string(),
+            status: TransactionStatus::from_validation_code(&validation_code),
+            validation_code,
+            block_number: Some(status.block_number),
+        })
+    }
+
+    async fn get_transaction_history(
+        &self,
+        channel_id: &str,
+        chaincode_id: &str,
+        query: &HistoryQuery,
+    ) -> Result<TransactionPage> {
+        self.session().await?;
+
+        tracing::debug!(
+            "Fetching transaction history: channel={}, chaincode={}",
+            channel_id,
+            chaincode_id
+        );
+
+        collect_history(self, channel_id, chaincode_id, query).await
+    }
+
+    /// Events are read from the peer's Deliver service
+    async fn subscribe_events(
+        &self,
+        channel_id: &str,
+        filter: EventFilter,
+        checkpointer: Arc<dyn Checkpointer>,
+    ) -> Result<EventStream> {
+        self.session().await?;
+        let (start, resume_after) =
+            resume_position(self, channel_id, &filter, checkpointer.as_ref()).await?;
+        tracing::debug!(
+            "Subscribing to events: channel={}, start block={}",
+            channel_id,
+            start
+        );
+        let blocks = self.deliver_blocks(channel_id, start).await?;
+        Ok(event_stream(blocks, filter, resume_after, checkpointer))
+    }
+}
+
+/// Blocks through QSCC `GetChainInfo` and `GetBlockByNumber`
+#[async_trait]
+impl BlockSource for FabricGatewayClient {
+    async fn height(&self, channel_id: &str) -> Result<u64> {
+        let info = self
+            .process_proposal(
+                channel_id,
+                "qscc",
+                "GetChainInfo",
+                &[channel_id.as_bytes().to_vec()],
+                FabricCoreError::QueryError,
+            )
+            .await?;
+        BlockchainInfo::decode(info.as_slice())
+            .map(|info| info.height)
+            .map_err(|e| FabricCoreError::QueryError(e.to_string()))
+    }
+
+    async fn block(&self, channel_id: &str, block_number: u64) -> Result<BlockEvent> {
+        let block = self
+            .process_proposal(
+                channel_id,
+                "qscc",
+                "GetBlockByNumber",
+                &[
+                    channel_id.as_bytes().to_vec(),
+                    block_number.to_string().into_bytes(),
+                ],
+                FabricCoreError::QueryError,
+            )
+            .await?;
+        Block::decode(block.as_slice())
+            .map(|block| block_event(channel_id, block))
+            .map_err(|e| FabricCoreError::QueryError(e.to_string()))
+    }
+}
+
+/// Transactions and chaincode events of a block, with the validation codes
+/// from its transactions filter
+fn block_event(channel_id: &str, block: Block) -> BlockEvent {
+    let block_number = block.header.unwrap_or_default().number;
+    let filter = block
+        .metadata
+        .unwrap_or_default()
+        .metadata
+        .into_iter()
+        .nth(BlockMetadataIndex::TransactionsFilter as usize)
+        .unwrap_or_default();
+
+    let mut event = BlockEvent {
+        channel_id: channel_id.to_string(),
+        block_number,
+        ..Default::default()
+    };
+    for (index, envelope) in block.data.unwrap_or_default().data.iter().enumerate() {
+        let code = filter
+            .get(index)
+            .and_then(|code| TxValidationCode::try_from(*code as i32).ok())
+            .unwrap_or(TxValidationCode::NotValidated);
+        if let Some((record, chaincode_event)) =
+            transaction_record(channel_id, block_number, envelope, code)
+        {
+            if code == TxValidationCode::Valid {
+                event.chaincode_events.extend(chaincode_event);
+            }
+            event.transactions.push(record);
+        }
+    }
+    event
+}
+
+/// History entry for an endorser transaction envelope from a block, with
+/// the chaincode event it set
+fn transaction_record(
+    channel_id: &str,
+    block_number: u64,
+    envelope: &[u8],
+    code: TxValidationCode,
+) -> Option<(TransactionRecord, Option<ChaincodeEvent>)> {
+    let envelope = Envelope::decode(envelope).ok()?;
+    let payload = Payload::decode(envelope.payload.as_slice()).ok()?;
+    let header = payload.header?;
+    let channel_header = ChannelHeader::decode(header.channel_header.as_slice()).ok()?;
+    if channel_header.r#type != HeaderType::EndorserTransaction as i32 {
+        return None;
+    }
+    let signature_header = SignatureHeader::decode(header.signature_header.as_slice()).ok()?;
+    let creator = SerializedIdentity::decode(signature_header.creator.as_slice()).ok()?;
+
+    let transaction = Transaction::decode(payload.data.as_slice()).ok()?;
+    let action =
+        ChaincodeActionPayload::decode(transaction.actions.first()?.payload.as_slice()).ok()?;
+    let proposal_payload =
+        ChaincodeProposalPayload::decode(action.chaincode_proposal_payload.as_slice()).ok()?;
+    let spec = ChaincodeInvocationSpec::decode(proposal_payload.input.as_slice())
+        .ok()?
+        .chaincode_spec?;
+    let mut args = spec
+        .input
+        .unwrap_or_default()
+        .args
+        .into_iter()
+        .map(|arg| String::from_utf8_lossy(&arg).into_owned());
+    let timestamp = channel_header.timestamp.unwrap_or_default();
+
+    let chaincode_event = action
+        .action
+        .and_then(|endorsed| {
+            ProposalResponsePayload::decode(endorsed.proposal_response_payload.as_slice()).ok()
+        })
+        .and_then(|response| ChaincodeAction::decode(response.extension.as_slice()).ok())
+        .and_then(|chaincode_action| {
+            peer::ChaincodeEvent::decode(chaincode_action.events.as_slice()).ok()
+        })
+        .filter(|event| !event.event_name.is_empty())
+        .map(|event| ChaincodeEvent {
+            channel_id: channel_id.to_string(),
+            chaincode_id: event.chaincode_id,
+            event_name: event.event_name,
+            transaction_id: channel_header.tx_id.clone(),
+            block_number,
+            payload: event.payload,
+        });
+
+    let record = TransactionRecord {
+        transaction_id: channel_header.tx_id,
+        block_number,
+        channel_id: channel_id.to_string(),
+        chaincode_id: spec.chaincode_id.unwrap_or_default().name,
+        function: args.next().unwrap_or_default(),
+        args: args.collect(),
+        mspid: creator.mspid,
+        timestamp: chrono::DateTime::from_timestamp(timestamp.seconds, timestamp.nanos as u32)
+            .unwrap_or_default(),
+        validation_code: code.as_str_name().to_string(),
+    };
+    Some((record, chaincode_event))
+}
+
+/// Calls on the peer's gRPC services
+struct GatewayStub(tonic::client::Grpc<Channel>);
+
+impl GatewayStub {
+    /// Send a single request on a streaming method and return the replies
+    async fn server_streaming<Req, Resp>(
+        &mut self,
+        method: &'static str,
+        request: Req,
+    ) -> std::result::Result<tonic::Streaming<Resp>, Status>
+    where
+        Req: Message + Send + Sync + 'static,
+        Resp: Message + Default + Send + Sync + 'static,
+    {
+        self.0
+            .ready()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+        let response = self
+            .0
+            .streaming(
+                tonic::Request::new(tokio_stream::once(request)),
+                PathAndQuery::from_static(method),
+                ProstCodec::<Req, Resp>::default(),
+            )
+            .await?;
+        Ok(response.into_inner())
+    }
+
+    async fn unary<Req, Resp>(
+        &mut self,
+        method: &'static str,
+        request: Req,
+    ) -> std::result::Result<Resp, Status>
+    where
+        Req: Message + Send + Sync + 'static,
+        Resp: Message + Default + Send + Sync + 'static,
+    {
+        self.0
+            .ready()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+        let response = self
+            .0
+            .unary(
+                tonic::Request::new(request),
+                PathAndQuery::from_static(method),
+                ProstCodec::<Req, Resp>::default(),
+            )
+            .await?;
+        Ok(response.into_inner())
+    }
+}
+
+/// Open a gRPC channel, using the network's TLS settings for `https` URLs
+async fn connect_channel(
+    config: &FabricNetworkConfig,
+    identity: &FabricIdentity,
+) -> Result<Channel> {
+    let endpoint = Endpoint::from_shared(config.gateway_url.clone())
+        .map_err(|e| FabricCoreError::ConfigError(format!("Invalid gateway URL: {}", e)))?;
+    let uri = endpoint.uri().clone();
+    let channel = if uri.scheme_str() == Some("https") {
+        let mut tls = tls::client_config(config, identity)?;
+        tls.alpn_protocols = vec![b"h2".to_vec()];
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(tls));
+        let server_name = ServerName::try_from(uri.host().unwrap_or_default())
+            .map_err(|e| FabricCoreError::ConfigError(format!("Invalid gateway host: {}", e)))?;
+        endpoint
+            .connect_with_connector(tower::service_fn(move |uri: Uri| {
+                let connector = connector.clone();
+                let server_name = server_name.clone();
+                async move {
+                    let address = format!(
+                        "{}:{}",
+                        uri.host().unwrap_or_default(),
+                        uri.port_u16().unwrap_or(443)
+                    );
+                    let stream = tokio::net::TcpStream::connect(address).await?;
+                    connector.connect(server_name, stream).await
+                }
+            }))
+            .await
+    } else {
+        endpoint.connect().await
+    };
+    channel.map_err(|e| {
+        FabricCoreError::ConnectionError(format!(
+            "Failed to connect to gateway {}: {}",
+            config.gateway_url, e
+        ))
+    })
+}
+
+/// Signed proposal for a chaincode transaction function
+pub(crate) struct SignedTransactionProposal {
+    pub transaction_id: String,
+    pub signed: SignedProposal,
+}
+
+impl SignedTransactionProposal {
+    pub fn new(
+        identity: &FabricIdentity,
+        channel_id: &str,
+        chaincode_id: &str,
+        function: &str,
+        args: &[Vec<u8>],
+        transient_map: HashMap<String, Vec<u8>>,
+    ) -> Result<Self> {
+        let chaincode = ChaincodeId {
+            name: chaincode_id.to_string(),
+            ..Default::default()
+        };
+        let extension = ChaincodeHeaderExtension {
+            chaincode_id: Some(chaincode.clone()),
+        };
+        let (transaction_id, header) = transaction_header(
+            identity,
+            HeaderType::EndorserTransaction,
+            channel_id,
+            extension.encode_to_vec(),
+        )?;
+
+        let mut chaincode_args = vec![function.as_bytes().to_vec()];
+        chaincode_args.extend_from_slice(args);
+        let input = ChaincodeInvocationSpec {
+            chaincode_spec: Some(ChaincodeSpec {
+                chaincode_id: Some(chaincode),
+                input: Some(ChaincodeInput {
+                    args: chaincode_args,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+        };
+        let proposal_bytes = Proposal {
+            header: header.encode_to_vec(),
+            payload: ChaincodeProposalPayload {
+                input: input.encode_to_vec(),
+                transient_map,
+            }
+            .encode_to_vec(),
+            extension: Vec::new(),
+        }
+        .encode_to_vec();
+        let signature = identity.signer()?.sign(&proposal_bytes)?;
+
+        Ok(Self {
+            transaction_id,
+            signed: SignedProposal {
+                proposal_bytes,
+                signature,
+            },
+        })
+    }
+}
+
+/// Header for a new transaction ID of `identity`, returned with the ID
+fn transaction_header(
+    identity: &FabricIdentity,
+    header_type: HeaderType,
+    channel_id: &str,
+    extension: Vec<u8>,
+) -> Result<(String, Header)> {
+    let creator = creator(identity);
+    let mut nonce = [0u8; 24];
+    rand_bytes(&mut nonce).map_err(|e| FabricCoreError::CryptoError(e.to_string()))?;
+    let transaction_id = transaction_id(&nonce, &creator);
+
+    let now = chrono::Utc::now();
+    let channel_header = ChannelHeader {
+        r#type: header_type as i32,
+        timestamp: Some(Timestamp {
+            seconds: now.timestamp(),
+            nanos: now.timestamp_subsec_nanos() as i32,
+        }),
+        channel_id: channel_id.to_string(),
+        tx_id: transaction_id.clone(),
+        extension,
+        ..Default::default()
+    };
+    let header = Header {
+        channel_header: channel_header.encode_to_vec(),
+        signature_header: SignatureHeader {
+            creator,
+            nonce: nonce.to_vec(),
+        }
+        .encode_to_vec(),
+    };
+    Ok((transaction_id, header))
+}
+
+/// Signed Deliver request for every block from `start` on, waiting for
+/// blocks not yet committed
+fn seek_envelope(identity: &FabricIdentity, channel_id: &str, start: u64) -> Result<Envelope> {
+    let (_, header) = transaction_header(
+        identity,
+        HeaderType::DeliverSeekInfo,
+        channel_id,
+        Vec::new(),
+    )?;
+    let position = |number| SeekPosition {
+        r#type: Some(seek_position::Type::Specified(SeekSpecified { number })),
+    };
+    let seek = SeekInfo {
+        start: Some(position(start)),
+        stop: Some(position(u64::MAX)),
+        behavior: SeekBehavior::BlockUntilReady as i32,
+    };
+    let payload = Payload {
+        header: Some(header),
+        data: seek.encode_to_vec(),
+    }
+    .encode_to_vec();
+    let signature = identity.signer()?.sign(&payload)?;
+    Ok(Envelope { payload, signature })
+}
+
+/// Serialized `SerializedIdentity` naming the identity's MSP and certificate
+pub(crate) fn creator(identity: &FabricIdentity) -> Vec<u8> {
+    SerializedIdentity {
+        mspid: identity.mspid.clone(),
+        id_bytes: identity.certificate.as_bytes().to_vec(),
+    }
+    .encode_to_vec()
+}
+
+/// Fabric transaction ID: hex SHA-256 of the nonce followed by the creator
+pub(crate) fn transaction_id(nonce: &[u8], creator: &[u8]) -> String {
+    sha256(&[nonce, creator].concat())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Chaincode response payload inside a prepared transaction envelope
+fn endorsed_result(envelope: &Envelope) -> Result<Vec<u8>> {
+    let malformed = |e: prost::DecodeError| {
+        FabricCoreError::InvocationError(format!("Malformed prepared transaction: {}", e))
+    };
+    let payload = Payload::decode(envelope.payload.as_slice()).map_err(malformed)?;
+    let transaction = Transaction::decode(payload.data.as_slice()).map_err(malformed)?;
+    let Some(action) = transaction.actions.first() else {
+        return Err(FabricCoreError::InvocationError(
+            "Prepared transaction has no actions".to_string(),
+        ));
+    };
+    let action_payload =
+        ChaincodeActionPayload::decode(action.payload.as_slice()).map_err(malformed)?;
+    let response_payload = ProposalResponsePayload::decode(
+        action_payload
+            .action
+            .unwrap_or_default()
+            .proposal_response_payload
+            .as_slice(),
+    )
+    .map_err(malformed)?;
+    let chaincode_action =
+        ChaincodeAction::decode(response_payload.extension.as_slice()).map_err(malformed)?;
+    Ok(chaincode_action.response.unwrap_or_default().payload)
+}
+
+/// Chaincode payloads are usually JSON; anything else is returned as a string
+fn payload_to_json(payload: &[u8]) -> serde_json::Value {
+    if payload.is_empty() {
+        return serde_json::Value::Null;
+    }
+    serde_json::from_slice(payload).unwrap_or_else(|_| {
+        serde_json::Value::String(String::from_utf8_lossy(payload).into_owned())
+    })
+}
+
+fn status_message(status: &Status) -> String {
+    format!("{:?}: {}", status.code(), status.message())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fabric::{ChaincodeArgs, FabricNetworkClientExt};
+    use crate::test_util::{MockGateway, TestCert};
+
+    async fn connected(gateway: &MockGateway) -> FabricGatewayClient {
+        let mut config =
+            crate::fabric::KaleidoFabricClient::from_kaleido_endpoint(&gateway.url, "")
+                .config()
+                .clone();
+        config.name = "Org1 peer".to_string();
+        let mut client = FabricGatewayClient::new(config);
+        client.connect(&TestCert::identity()).await.unwrap();
+        client
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_sends_signed_proposal() {
+        let gateway = MockGateway::start(|call| match call.function.as_str() {
+            "ReadMovie" => {
+                Ok(format!(r#"{{"id":"{}","title":"Heat"}}"#, call.args[0]).into_bytes())
+            }
+            "ReadWrapped" => Ok(br#"{"result":"raw"}"#.to_vec()),
+            _ => Err(format!("unknown function {}", call.function)),
+        })
+        .await;
+        let client = connected(&gateway).await;
+
+        let movie = client
+            .query_chaincode(
+                "movies",
+                "movie-chaincode",
+                "ReadMovie",
+                vec!["m1".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(movie["title"], "Heat");
+
+        let calls = gateway.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].channel_id, "movies");
+        assert_eq!(calls[0].chaincode_id, "movie-chaincode");
+        assert_eq!(calls[0].mspid, "Org1MSP");
+        assert_eq!(calls[0].args, vec!["m1".to_string()]);
+        assert_eq!(calls[0].transaction_id.len(), 64);
+
+        let err = client
+            .query_chaincode("movies", "movie-chaincode", "DeleteAll", Vec::new())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, FabricCoreError::QueryError(ref m) if m.contains("unknown function"))
+        );
+
+        // The gateway returns the payload itself, never unwrapped
+        let wrapped: HashMap<String, String> = client
+            .query_typed(
+                "movies",
+                "movie-chaincode",
+                "ReadWrapped",
+                &ChaincodeArgs::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(wrapped["result"], "raw");
+    }
+
+    #[tokio::test]
+    async fn test_invoke_endorses_submits_and_waits_for_commit() {
+        let gateway = MockGateway::start(|_| Ok(br#"{"ok":true}"#.to_vec())).await;
+        let client = connected(&gateway).await;
+
+        let result = client
+            .invoke_chaincode(
+                "movies",
+                "movie-chaincode",
+                "CreateMovie",
+                vec!["m2".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.payload["ok"], true);
+        assert_eq!(gateway.submitted(), vec![result.transaction_id.clone()]);
+        let status = client
+            .commit_status("movies", &result.transaction_id)
+            .await
+            .unwrap();
+        assert!(status.is_valid());
+        assert_eq!(status.block_number, 1);
+
+        gateway.set_validation_code(TxValidationCode::MvccReadConflict);
+        let err = client
+            .invoke_chaincode(
+                "movies",
+                "movie-chaincode",
+                "CreateMovie",
+                vec!["m2".to_string()],
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, FabricCoreError::InvocationError(ref m) if m.contains("MVCC_READ_CONFLICT"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_private_calls_keep_transient_data_off_the_ledger() {
+        let gateway = MockGateway::start(|_| Ok(br#"{"ok":true}"#.to_vec())).await;
+        let client = connected(&gateway).await;
+        let private = PrivateData::new()
+            .with_transient("contact", "alice@example.com")
+            .with_collection("submitterContacts", &["Org1MSP"]);
+
+        let result = client
+            .invoke_chaincode_private(
+                "movies",
+                "movie-chaincode",
+                "SubmitMovie",
+                vec!["m3".to_string()],
+                &private,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.status, TransactionStatus::Committed);
+        client
+            .query_chaincode_private(
+                "movies",
+                "movie-chaincode",
+                "ReadContact",
+                vec!["m3".to_string()],
+                &private,
+            )
+            .await
+            .unwrap();
+
+        let calls = gateway.calls();
+        assert_eq!(calls.len(), 2);
+        for call in &calls {
+            assert_eq!(call.transient["contact"], "alice@example.com");
+            assert_eq!(call.organizations, vec!["Org1MSP".to_string()]);
+        }
+        let envelope = gateway.committed_envelope(0);
+        assert!(!envelope
+            .windows(b"alice@example.com".len())
+            .any(|w| w == b"alice@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_submit_then_wait_for_commit_reports_validation_code() {
+        let gateway = MockGateway::start(|_| Ok(br#"{"ok":true}"#.to_vec())).await;
+        let client = connected(&gateway).await;
+        let submit = || {
+            client.submit_chaincode(
+                "movies",
+                "movie-chaincode",
+                "RateMovie",
+                vec!["m1".to_string()],
+            )
+        };
+        let timeout = Duration::from_secs(5);
+
+        let submitted = submit().await.unwrap();
+        assert_eq!(submitted.status, TransactionStatus::Submitted);
+        assert_eq!(submitted.payload["ok"], true);
+        let receipt = client
+            .wait_for_commit("movies", &submitted.transaction_id, timeout)
+            .await
+            .unwrap();
+        assert_eq!(receipt.status, TransactionStatus::Committed);
+        assert_eq!(receipt.validation_code, "VALID");
+        assert_eq!(receipt.block_number, Some(1));
+
+        gateway.set_validation_code(TxValidationCode::MvccReadConflict);
+        let submitted = submit().await.unwrap();
+        let receipt = client
+            .wait_for_commit("movies", &submitted.transaction_id, timeout)
+            .await
+            .unwrap();
+        assert_eq!(receipt.status, TransactionStatus::Invalid);
+        assert_eq!(receipt.validation_code, "MVCC_READ_CONFLICT");
+    }
+
+    #[tokio::test]
+    async fn test_discovers_channels_through_system_chaincodes() {
+        use crate::protos::lifecycle::ChaincodeDefinition;
+        use crate::protos::peer::ChannelInfo;
+
+        let gateway = MockGateway::start(|call| {
+            match (call.chaincode_id.as_str(), call.channel_id.as_str()) {
+                ("cscc", "") => Ok(ChannelQueryResponse {
+                    channels: ["movies", "voting"]
+                        .iter()
+                        .map(|id| ChannelInfo {
+                            channel_id: id.to_string(),
+                        })
+                        .collect(),
+                }
+                .encode_to_vec()),
+                ("_lifecycle", "movies") => Ok(QueryChaincodeDefinitionsResult {
+                    chaincode_definitions: vec![ChaincodeDefinition {
+                        name: "movie-chaincode".to_string(),
+                        sequence: 1,
+                        ..Default::default()
+                    }],
+                }
+                .encode_to_vec()),
+                ("_lifecycle", _) => Ok(Vec::new()),
+                _ => Err("unexpected call".to_string()),
+            }
+        })
+        .await;
+        let client = connected(&gateway).await;
+
+        let channels = client.get_channels().await.unwrap();
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0].id, "movies");
+        assert_eq!(channels[0].chaincode_id, "movie-chaincode");
+        assert_eq!(channels[1].id, "voting");
+        assert!(channels[1].chaincodes.is_empty());
+        assert_eq!(gateway.calls()[0].function, "GetChannels");
+    }
+
+    #[tokio::test]
+    async fn test_history_reads_committed_blocks() {
+        let gateway = MockGateway::start(|_| Ok(Vec::new())).await;
+        let client = connected(&gateway).await;
+        for (function, id) in [
+            ("CreateMovie", "m1"),
+            ("RateMovie", "m1"),
+            ("CreateMovie", "m2"),
+        ] {
+            client
+                .invoke_chaincode("movies", "movie-chaincode", function, vec![id.to_string()])
+                .await
+                .unwrap();
+        }
+
+        let mut query = HistoryQuery {
+            function: Some("CreateMovie".to_string()),
+            limit: Some(1),
+            ..Default::default()
+        };
+        let first = client
+            .get_transaction_history("movies", "movie-chaincode", &query)
+            .await
+            .unwrap();
+        assert_eq!(first.transactions.len(), 1);
+        let newest = &first.transactions[0];
+        assert_eq!(newest.args, vec!["m2".to_string()]);
+        assert_eq!(newest.block_number, 3);
+        assert_eq!(newest.mspid, "Org1MSP");
+        assert_eq!(newest.validation_code, "VALID");
+        assert_eq!(newest.transaction_id, gateway.submitted()[2]);
+
+        query.cursor = first.next_cursor;
+        let second = client
+            .get_transaction_history("movies", "movie-chaincode", &query)
+            .await
+            .unwrap();
+        assert_eq!(second.transactions[0].args, vec!["m1".to_string()]);
+
+        query.cursor = second.next_cursor;
+        let last = client
+            .get_transaction_history("movies", "movie-chaincode", &query)
+            .await
+            .unwrap();
+        assert!(last.transactions.is_empty());
+        assert_eq!(last.next_cursor, None);
+
+        let other_org = HistoryQuery {
+            mspid: Some("Org2MSP".to_string()),
+            ..Default::default()
+        };
+        let page = client
+            .get_transaction_history("movies", "movie-chaincode", &other_org)
+            .await
+            .unwrap();
+        assert!(page.transactions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_events_follow_commits_and_resume_from_checkpoint() {
+        use crate::events::{Checkpoint, FabricEvent, MemoryCheckpointer};
+        use std::time::Duration;
+
+        let gateway = MockGateway::start(|call| {
+            Ok(format!(r#"{{"imdbId":"{}"}}"#, call.args[0]).into_bytes())
+        })
+        .await;
+        gateway.emit_event("ApproveContentRequest", "ContentApproved");
+        let client = connected(&gateway).await;
+        let invoke = |function: &'static str, id: &'static str| {
+            client.invoke_chaincode("movies", "movie-chaincode", function, vec![id.to_string()])
+        };
+        // Committed before subscribing, so not delivered
+        invoke("ApproveContentRequest", "tt1").await.unwrap();
+
+        let checkpointer = Arc::new(MemoryCheckpointer::default());
+        let subscribe = || {
+            client.subscribe_events(
+                "movies",
+                EventFilter::chaincode("movie-chaincode"),
+                checkpointer.clone(),
+            )
+        };
+        let mut events = subscribe().await.unwrap();
+        invoke("RequestContent", "tt2").await.unwrap();
+        invoke("ApproveContentRequest", "tt2").await.unwrap();
+
+        async fn next(events: &mut EventStream) -> FabricEvent {
+            let event = tokio::time::timeout(Duration::from_secs(5), events.next());
+            event.await.unwrap().unwrap().unwrap()
+        }
+        let FabricEvent::Chaincode(event) = next(&mut events).await else {
+            panic!("expected a chaincode event");
+        };
+        assert_eq!(event.event_name, "ContentApproved");
+        assert_eq!(event.block_number, 3);
+        assert_eq!(event.transaction_id, gateway.submitted()[2]);
+        assert_eq!(event.payload_json().unwrap()["imdbId"], "tt2");
+
+        // The event was never acknowledged by asking for the next one, so a
+        // new subscription delivers it again
+        drop(events);
+        assert_eq!(
+            checkpointer.load().unwrap(),
+            Some(Checkpoint {
+                block_number: 3,
+                transaction_id: None,
+            })
+        );
+        let mut events = subscribe().await.unwrap();
+        assert_eq!(next(&mut events).await, FabricEvent::Chaincode(event));
+    }
+
+    #[tokio::test]
+    async fn test_requires_connection() {
+        let client = FabricGatewayClient::new(
+            crate::fabric::KaleidoFabricClient::from_kaleido_endpoint("http://127.0.0.1:1", "")
+                .config()
+                .clone(),
+        );
+        assert!(matches!(
+            client
+                .evaluate("movies", "movie-chaincode", "ReadMovie", &[])
+                .await,
+            Err(FabricCoreError::ConnectionError(_))
+        ));
+
+        let mut client = client;
+        assert!(matches!(
+            client.connect(&TestCert::identity()).await,
+            Err(FabricCoreError::ConnectionError(_))
+        ));
+    }
+}
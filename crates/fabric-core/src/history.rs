@@ -0,0 +1,299 @@
+/// Transaction history assembled from ledger blocks
+use crate::error::{FabricCoreError, Result};
+use crate::events::BlockEvent;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Page size used when `HistoryQuery::limit` is not set
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Blocks read per page when `HistoryQuery::max_blocks` is not set
+pub const DEFAULT_MAX_BLOCKS: u64 = 1000;
+
+/// Filters and position for a transaction history request
+///
+/// All filters are optional and combine with AND. `from` and `to` bound the
+/// transaction timestamp, inclusive.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryQuery {
+    pub function: Option<String>,
+    pub mspid: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Maximum number of transactions per page
+    pub limit: Option<usize>,
+    /// Maximum number of blocks read per page, so a filter that matches
+    /// nothing does not walk the whole ledger in one request
+    pub max_blocks: Option<u64>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+impl HistoryQuery {
+    fn matches(&self, chaincode_id: &str, record: &TransactionRecord) -> bool {
+        record.chaincode_id == chaincode_id
+            && self.function.as_ref().is_none_or(|f| *f == record.function)
+            && self.mspid.as_ref().is_none_or(|m| *m == record.mspid)
+            && self.to.is_none_or(|to| record.timestamp <= to)
+    }
+}
+
+/// A chaincode transaction recorded on the ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionRecord {
+    pub transaction_id: String,
+    pub block_number: u64,
+    pub channel_id: String,
+    pub chaincode_id: String,
+    pub function: String,
+    pub args: Vec<String>,
+    /// MSP ID of the submitting identity
+    pub mspid: String,
+    pub timestamp: DateTime<Utc>,
+    /// Fabric validation code name, e.g. `VALID` or `MVCC_READ_CONFLICT`
+    pub validation_code: String,
+}
+
+/// One page of history, newest transaction first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<TransactionRecord>,
+    /// Cursor for the next (older) page, `None` once the ledger start or
+    /// `from` was reached. A page cut short by `max_blocks` may hold fewer
+    /// transactions than `limit`, or none.
+    pub next_cursor: Option<String>,
+}
+
+/// Ledger access needed to walk a channel's blocks
+#[async_trait]
+pub(crate) trait BlockSource: Send + Sync {
+    /// Number of blocks on the channel
+    async fn height(&self, channel_id: &str) -> Result<u64>;
+
+    /// Endorser transactions and chaincode events of a block
+    async fn block(&self, channel_id: &str, block_number: u64) -> Result<BlockEvent>;
+}
+
+/// Walk blocks from the newest (or the cursor) backwards, collecting
+/// transactions of `chaincode_id` that match the query
+///
+/// Stops after `max_blocks` blocks with a cursor to the next unread block.
+pub(crate) async fn collect_history(
+    source: &dyn BlockSource,
+    channel_id: &str,
+    chaincode_id: &str,
+    query: &HistoryQuery,
+) -> Result<TransactionPage> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+    let max_blocks = query.max_blocks.unwrap_or(DEFAULT_MAX_BLOCKS).max(1);
+    let (mut block, mut before) = match &query.cursor {
+        Some(cursor) => match parse_cursor(cursor)? {
+            // Nothing left before the start of `block`
+            (0, 0) => return Ok(TransactionPage::default()),
+            (block, 0) => (block - 1, None),
+            (block, index) => (block, Some(index)),
+        },
+        None => match source.height(channel_id).await? {
+            0 => return Ok(TransactionPage::default()),
+            height => (height - 1, None),
+        },
+    };
+
+    let mut page = TransactionPage::default();
+    let mut scanned = 0;
+    loop {
+        let transactions = source.block(channel_id, block).await?.transactions;
+        let end = before
+            .take()
+            .unwrap_or(transactions.len())
+            .min(transactions.len());
+        for (index, record) in transactions[..end].iter().enumerate().rev() {
+            if query.from.is_some_and(|from| record.timestamp < from) {
+                return Ok(page);
+            }
+            if !query.matches(chaincode_id, record) {
+                continue;
+            }
+            page.transactions.push(record.clone());
+            if page.transactions.len() == limit {
+                if block > 0 || index > 0 {
+                    page.next_cursor = Some(format!("{}:{}", block, index));
+                }
+                return Ok(page);
+            }
+        }
+        if block == 0 {
+            return Ok(page);
+        }
+        scanned += 1;
+        if scanned == max_blocks {
+            // Every transaction of `block` was read
+            page.next_cursor = Some(format!("{}:0", block));
+            return Ok(page);
+        }
+        block -= 1;
+    }
+}
+
+/// Cursors are `block:index` of the last transaction returned
+fn parse_cursor(cursor: &str) -> Result<(u64, usize)> {
+    cursor
+        .split_once(':')
+        .and_then(|(block, index)| Some((block.parse().ok()?, index.parse().ok()?)))
+        .ok_or_else(|| FabricCoreError::QueryError(format!("Invalid history cursor: {}", cursor)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Block `n` holds transactions `n*10 + i`, one minute apart per block
+    struct Blocks(Vec<Vec<(&'static str, &'static str)>>);
+
+    #[async_trait]
+    impl BlockSource for Blocks {
+        async fn height(&self, _channel_id: &str) -> Result<u64> {
+            Ok(self.0.len() as u64)
+        }
+
+        async fn block(&self, channel_id: &str, block_number: u64) -> Result<BlockEvent> {
+            let transactions = self.0[block_number as usize]
+                .iter()
+                .enumerate()
+                .map(|(i, (function, mspid))| TransactionRecord {
+                    transaction_id: format!("{}", block_number * 10 + i as u64),
+                    block_number,
+                    channel_id: channel_id.to_string(),
+                    chaincode_id: "movie-chaincode".to_string(),
+                    function: function.to_string(),
+                    args: Vec::new(),
+                    mspid: mspid.to_string(),
+                    timestamp: Utc.timestamp_opt(block_number as i64 * 60, 0).unwrap(),
+                    validation_code: "VALID".to_string(),
+                })
+                .collect();
+            Ok(BlockEvent {
+                channel_id: channel_id.to_string(),
+                block_number,
+                transactions,
+                chaincode_events: Vec::new(),
+            })
+        }
+    }
+
+    fn ids(page: &TransactionPage) -> Vec<&str> {
+        page.transactions
+            .iter()
+            .map(|t| t.transaction_id.as_str())
+            .collect()
+    }
+
+    fn ledger() -> Blocks {
+        Blocks(vec![
+            vec![],
+            vec![("CreateMovie", "Org1MSP"), ("CreateMovie", "Org2MSP")],
+            vec![("RateMovie", "Org1MSP")],
+            vec![
+                ("CreateMovie", "Org1MSP"),
+                ("RateMovie", "Org2MSP"),
+                ("CreateMovie", "Org1MSP"),
+            ],
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_pages_follow_cursor_newest_first() {
+        let ledger = ledger();
+        let mut query = HistoryQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut pages = Vec::new();
+        loop {
+            let page = collect_history(&ledger, "movies", "movie-chaincode", &query)
+                .await
+                .unwrap();
+            pages.push(ids(&page).join(","));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        // A full page always carries a cursor, so the last one can be empty
+        assert_eq!(pages, vec!["32,31", "30,20", "11,10", ""]);
+
+        query.cursor = Some("garbage".to_string());
+        assert!(
+            collect_history(&ledger, "movies", "movie-chaincode", &query)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_stops_after_max_blocks() {
+        let ledger = ledger();
+        let mut query = HistoryQuery {
+            function: Some("DeleteMovie".to_string()),
+            max_blocks: Some(2),
+            ..Default::default()
+        };
+        let page = collect_history(&ledger, "movies", "movie-chaincode", &query)
+            .await
+            .unwrap();
+        assert!(page.transactions.is_empty());
+        assert_eq!(page.next_cursor.as_deref(), Some("2:0"));
+
+        query.cursor = page.next_cursor;
+        let page = collect_history(&ledger, "movies", "movie-chaincode", &query)
+            .await
+            .unwrap();
+        assert!(page.transactions.is_empty());
+        assert_eq!(page.next_cursor, None);
+
+        query.function = Some("RateMovie".to_string());
+        query.cursor = None;
+        let page = collect_history(&ledger, "movies", "movie-chaincode", &query)
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), vec!["31", "20"]);
+        assert_eq!(page.next_cursor.as_deref(), Some("2:0"));
+    }
+
+    #[tokio::test]
+    async fn test_filters_combine() {
+        let ledger = ledger();
+        let query = HistoryQuery {
+            function: Some("CreateMovie".to_string()),
+            mspid: Some("Org1MSP".to_string()),
+            ..Default::default()
+        };
+        let page = collect_history(&ledger, "movies", "movie-chaincode", &query)
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), vec!["32", "30", "10"]);
+        assert_eq!(page.next_cursor, None);
+
+        let query = HistoryQuery {
+            from: Some(Utc.timestamp_opt(120, 0).unwrap()),
+            to: Some(Utc.timestamp_opt(120, 0).unwrap()),
+            ..Default::default()
+        };
+        let page = collect_history(&ledger, "movies", "movie-chaincode", &query)
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), vec!["20"]);
+
+        let page = collect_history(
+            &ledger,
+            "movies",
+            "other-chaincode",
+            &HistoryQuery::default(),
+        )
+        .await
+        .unwrap();
+        assert!(page.transactions.is_empty());
+    }
+}
@@ -0,0 +1,429 @@
+/// Encrypted-at-rest storage for identity files
+use crate::crypto::FabricIdentity;
+use crate::error::{FabricCoreError, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use openssl::rand::rand_bytes;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+/// Marker stored in the `format` field of encrypted identity files
+pub const ENCRYPTED_IDENTITY_FORMAT: &str = "fabric-encrypted-identity";
+const ENCRYPTED_IDENTITY_VERSION: u32 = 1;
+
+const KDF_ALGORITHM: &str = "scrypt";
+const CIPHER_ALGORITHM: &str = "aes-256-gcm";
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Storage format of an identity file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentityFileFormat {
+    /// Legacy JSON with the private key in the clear
+    Plaintext,
+    /// Private key wrapped with a passphrase-derived key
+    Encrypted,
+}
+
+impl IdentityFileFormat {
+    /// Detect the format of an identity JSON document
+    pub fn detect(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        match value.get("format").and_then(|v| v.as_str()) {
+            Some(ENCRYPTED_IDENTITY_FORMAT) => Ok(IdentityFileFormat::Encrypted),
+            Some(other) => Err(FabricCoreError::KeyManagementError(format!(
+                "Unknown identity file format: {}",
+                other
+            ))),
+            None => Ok(IdentityFileFormat::Plaintext),
+        }
+    }
+
+    /// Detect the format of an identity file on disk
+    pub fn detect_file(path: &Path) -> Result<Self> {
+        Self::detect(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Key derivation parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub algorithm: String,
+    /// Base64 salt
+    pub salt: String,
+    /// scrypt cost as log2(N)
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+/// AEAD parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CipherParams {
+    pub algorithm: String,
+    /// Base64 nonce
+    pub nonce: String,
+}
+
+/// Identity file whose private key is AEAD-encrypted under a passphrase
+///
+/// The certificate and metadata stay readable so tools can list and
+/// inspect identities without unlocking them. They are bound to the
+/// ciphertext as associated data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedIdentity {
+    pub format: String,
+    pub version: u32,
+    pub public_key: String,
+    pub certificate: String,
+    pub ca_certificate: String,
+    pub user_id: String,
+    pub org_name: String,
+    pub mspid: String,
+    pub kdf: KdfParams,
+    pub cipher: CipherParams,
+    /// Base64 ciphertext followed by the authentication tag
+    pub encrypted_private_key: String,
+}
+
+impl EncryptedIdentity {
+    /// Encrypt an identity's private key under a passphrase
+    pub fn encrypt(identity: &FabricIdentity, passphrase: &str) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(FabricCoreError::KeyManagementError(
+                "Passphrase must not be empty".to_string(),
+            ));
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand_bytes(&mut salt).map_err(keystore_err)?;
+        rand_bytes(&mut nonce).map_err(keystore_err)?;
+
+        let kdf = KdfParams {
+            algorithm: KDF_ALGORITHM.to_string(),
+            salt: BASE64.encode(salt),
+            log_n: SCRYPT_LOG_N,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+        };
+        let key = derive_key(passphrase, &kdf)?;
+
+        let mut encrypted = Self {
+            format: ENCRYPTED_IDENTITY_FORMAT.to_string(),
+            version: ENCRYPTED_IDENTITY_VERSION,
+            public_key: identity.public_key.clone(),
+            certificate: identity.certificate.clone(),
+            ca_certificate: identity.ca_certificate.clone(),
+            user_id: identity.user_id.clone(),
+            org_name: identity.org_name.clone(),
+            mspid: identity.mspid.clone(),
+            kdf,
+            cipher: CipherParams {
+                algorithm: CIPHER_ALGORITHM.to_string(),
+                nonce: BASE64.encode(nonce),
+            },
+            encrypted_private_key: String::new(),
+        };
+
+        let mut tag = [0u8; TAG_LEN];
+        let mut ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key,
+            Some(&nonce),
+            &encrypted.associated_data(),
+            identity.private_key.expose_secret().as_bytes(),
+            &mut tag,
+        )
+        .map_err(keystore_err)?;
+        ciphertext.extend_from_slice(&tag);
+        encrypted.encrypted_private_key = BASE64.encode(ciphertext);
+
+        Ok(encrypted)
+    }
+
+    /// Decrypt the private key and rebuild the identity
+    pub fn decrypt(&self, passphrase: &str) -> Result<FabricIdentity> {
+        if self.format != ENCRYPTED_IDENTITY_FORMAT || self.version != ENCRYPTED_IDENTITY_VERSION {
+            return Err(FabricCoreError::KeyManagementError(format!(
+                "Unsupported identity file: {} v{}",
+                self.format, self.version
+            )));
+        }
+        if self.kdf.algorithm != KDF_ALGORITHM || self.cipher.algorithm != CIPHER_ALGORITHM {
+            return Err(FabricCoreError::KeyManagementError(format!(
+                "Unsupported encryption: {}/{}",
+                self.kdf.algorithm, self.cipher.algorithm
+            )));
+        }
+
+        let key = derive_key(passphrase, &self.kdf)?;
+        let nonce = BASE64.decode(&self.cipher.nonce).map_err(keystore_err)?;
+        let data = BASE64
+            .decode(&self.encrypted_private_key)
+            .map_err(keystore_err)?;
+        if data.len() < TAG_LEN {
+            return Err(FabricCoreError::KeyManagementError(
+                "Encrypted private key is truncated".to_string(),
+            ));
+        }
+        let (ciphertext, tag) = data.split_at(data.len() - TAG_LEN);
+
+        let plaintext = decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key,
+            Some(&nonce),
+            &self.associated_data(),
+            ciphertext,
+            tag,
+        )
+        .map_err(|_| FabricCoreError::InvalidPassphrase)?;
+        let private_key = String::from_utf8(plaintext).map_err(keystore_err)?;
+
+        Ok(FabricIdentity::new(
+            private_key,
+            self.public_key.clone(),
+            self.certificate.clone(),
+            self.ca_certificate.clone(),
+            self.user_id.clone(),
+            self.org_name.clone(),
+            self.mspid.clone(),
+        ))
+    }
+
+    fn associated_data(&self) -> Vec<u8> {
+        serde_json::json!([
+            self.user_id,
+            self.org_name,
+            self.mspid,
+            self.public_key,
+            self.certificate,
+            self.ca_certificate,
+        ])
+        .to_string()
+        .into_bytes()
+    }
+}
+
+impl FabricIdentity {
+    /// Serialize to the encrypted storage format
+    pub fn to_encrypted_json(&self, passphrase: &str) -> Result<String> {
+        let encrypted = EncryptedIdentity::encrypt(self, passphrase)?;
+        Ok(serde_json::to_string_pretty(&encrypted)?)
+    }
+
+    /// Deserialize from either storage format
+    ///
+    /// Plaintext documents ignore the passphrase.
+    pub fn from_json_with_passphrase(json: &str, passphrase: &str) -> Result<Self> {
+        match IdentityFileFormat::detect(json)? {
+            IdentityFileFormat::Plaintext => Self::from_json(json),
+            IdentityFileFormat::Encrypted => {
+                let encrypted: EncryptedIdentity = serde_json::from_str(json)?;
+                encrypted.decrypt(passphrase)
+            }
+        }
+    }
+
+    /// Save identity to file with the private key encrypted
+    pub fn save_encrypted(&self, path: &Path, passphrase: &str) -> Result<()> {
+        let json = self.to_encrypted_json(passphrase)?;
+        write_atomic(path, json.as_bytes())
+    }
+
+    /// Load identity from a plaintext or encrypted file
+    pub fn load_with_passphrase(path: &Path, passphrase: &str) -> Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json_with_passphrase(&json, passphrase)
+    }
+}
+
+/// Upgrade a plaintext identity file to the encrypted format in place
+///
+/// Returns `false` when the file was already encrypted.
+pub fn migrate_identity_file(path: &Path, passphrase: &str) -> Result<bool> {
+    if IdentityFileFormat::detect_file(path)? == IdentityFileFormat::Encrypted {
+        return Ok(false);
+    }
+
+    let identity = FabricIdentity::load_from_file(path)?;
+    identity.save_encrypted(path, passphrase)?;
+    tracing::info!("Encrypted identity file {:?}", path);
+    Ok(true)
+}
+
+fn derive_key(passphrase: &str, kdf: &KdfParams) -> Result<Vec<u8>> {
+    if kdf.log_n == 0 || kdf.log_n > 20 {
+        return Err(FabricCoreError::KeyManagementError(format!(
+            "Unsupported scrypt cost: 2^{}",
+            kdf.log_n
+        )));
+    }
+    let salt = BASE64.decode(&kdf.salt).map_err(keystore_err)?;
+    let n = 1u64 << kdf.log_n;
+    // scrypt needs 128 * r * N bytes, plus headroom
+    let max_memory = 256 * kdf.r as u64 * n;
+
+    let mut key = vec![0u8; KEY_LEN];
+    openssl::pkcs5::scrypt(
+        passphrase.as_bytes(),
+        &salt,
+        n,
+        kdf.r as u64,
+        kdf.p as u64,
+        max_memory,
+        &mut key,
+    )
+    .map_err(keystore_err)?;
+    Ok(key)
+}
+
+/// Write a file readable only by the current user
+///
+/// Writes go through a temporary file so a crash never leaves a partial
+/// identity. The temporary file is created with mode 0600 rather than
+/// chmodded after the write, so the key is never readable by other users.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = std::path::PathBuf::from(tmp);
+
+    // Left over from an interrupted write; `create_new` would refuse it
+    match std::fs::remove_file(&tmp) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn keystore_err(e: impl std::fmt::Display) -> FabricCoreError {
+    FabricCoreError::KeyManagementError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoManager;
+
+    fn identity() -> FabricIdentity {
+        let (private_key, public_key) = CryptoManager::generate_keypair().unwrap();
+        FabricIdentity::new(
+            private_key,
+            public_key,
+            "cert".to_string(),
+            "ca_cert".to_string(),
+            "user1".to_string(),
+            "Org1".to_string(),
+            "Org1MSP".to_string(),
+        )
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.json", name, uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_encrypted_round_trip() {
+        let identity = identity();
+        let json = identity.to_encrypted_json("correct horse").unwrap();
+
+        assert!(!json.contains("PRIVATE KEY"));
+        assert!(json.contains("\"certificate\": \"cert\""));
+        assert_eq!(
+            IdentityFileFormat::detect(&json).unwrap(),
+            IdentityFileFormat::Encrypted
+        );
+
+        let restored = FabricIdentity::from_json_with_passphrase(&json, "correct horse").unwrap();
+        assert_eq!(restored.private_key, identity.private_key);
+        assert_eq!(restored.mspid, "Org1MSP");
+    }
+
+    #[test]
+    fn test_wrong_passphrase_and_tampering() {
+        let json = identity().to_encrypted_json("correct horse").unwrap();
+        assert!(matches!(
+            FabricIdentity::from_json_with_passphrase(&json, "battery staple"),
+            Err(FabricCoreError::InvalidPassphrase)
+        ));
+
+        let mut encrypted: EncryptedIdentity = serde_json::from_str(&json).unwrap();
+        encrypted.mspid = "Org2MSP".to_string();
+        assert!(matches!(
+            encrypted.decrypt("correct horse"),
+            Err(FabricCoreError::InvalidPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_load_from_file_requires_passphrase() {
+        let path = temp_path("encrypted-identity");
+        identity().save_encrypted(&path, "correct horse").unwrap();
+
+        assert!(matches!(
+            FabricIdentity::load_from_file(&path),
+            Err(FabricCoreError::PassphraseRequired)
+        ));
+        assert!(FabricIdentity::load_with_passphrase(&path, "correct horse").is_ok());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // A stale temporary file from an interrupted write is replaced
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, "partial").unwrap();
+        identity().save_encrypted(&path, "correct horse").unwrap();
+        assert!(!std::path::Path::new(&tmp).exists());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_plaintext_file() {
+        let path = temp_path("plaintext-identity");
+        let identity = identity();
+        identity.save_plaintext_insecure(&path).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert_eq!(
+            IdentityFileFormat::detect_file(&path).unwrap(),
+            IdentityFileFormat::Plaintext
+        );
+
+        assert!(migrate_identity_file(&path, "correct horse").unwrap());
+        assert_eq!(
+            IdentityFileFormat::detect_file(&path).unwrap(),
+            IdentityFileFormat::Encrypted
+        );
+        assert!(!migrate_identity_file(&path, "correct horse").unwrap());
+
+        let restored = FabricIdentity::load_with_passphrase(&path, "correct horse").unwrap();
+        assert_eq!(restored.private_key, identity.private_key);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,8 +1,32 @@
+pub mod backup;
+pub mod ca;
+pub mod certificate;
+pub mod contracts;
+pub mod crl;
 pub mod crypto;
+pub mod events;
+pub mod expiry;
 pub mod fabric;
+pub mod formats;
+pub mod gateway;
+pub mod history;
+pub mod keystore;
+pub mod msp;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+pub mod private_data;
+pub mod protos;
+pub mod secret;
+pub mod signer;
+pub mod tls;
 pub mod torrent;
+pub mod wallet;
 pub mod error;
 
+#[cfg(test)]
+#[allow(clippy::result_large_err)]
+pub(crate) mod test_util;
+
 pub use error::{Result, FabricCoreError};
 
 /// Library version
@@ -10,8 +34,6 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
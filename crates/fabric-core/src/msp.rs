@@ -0,0 +1,495 @@
+/// Fabric MSP directory import and export
+use crate::crl::CrlStore;
+use crate::crypto::{name_to_string, FabricIdentity};
+use crate::error::{FabricCoreError, Result};
+use crate::keystore::write_atomic;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::x509::{X509NameRef, X509VerifyResult, X509};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const SIGNCERTS: &str = "signcerts";
+const KEYSTORE: &str = "keystore";
+const CACERTS: &str = "cacerts";
+const INTERMEDIATECERTS: &str = "intermediatecerts";
+const TLSCACERTS: &str = "tlscacerts";
+const CRLS: &str = "crls";
+const CONFIG_FILE: &str = "config.yaml";
+
+/// OU identifier entry of a NodeOUs configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OuIdentifier {
+    /// CA certificate path relative to the MSP directory
+    #[serde(rename = "Certificate", default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<String>,
+    #[serde(rename = "OrganizationalUnitIdentifier")]
+    pub organizational_unit_identifier: String,
+}
+
+/// NodeOUs section of an MSP `config.yaml`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeOus {
+    #[serde(rename = "Enable", default)]
+    pub enable: bool,
+    #[serde(rename = "ClientOUIdentifier", default, skip_serializing_if = "Option::is_none")]
+    pub client_ou_identifier: Option<OuIdentifier>,
+    #[serde(rename = "PeerOUIdentifier", default, skip_serializing_if = "Option::is_none")]
+    pub peer_ou_identifier: Option<OuIdentifier>,
+    #[serde(rename = "AdminOUIdentifier", default, skip_serializing_if = "Option::is_none")]
+    pub admin_ou_identifier: Option<OuIdentifier>,
+    #[serde(rename = "OrdererOUIdentifier", default, skip_serializing_if = "Option::is_none")]
+    pub orderer_ou_identifier: Option<OuIdentifier>,
+}
+
+impl NodeOus {
+    /// Standard NodeOUs trusting the given CA certificate path
+    pub fn standard(ca_certificate: &str) -> Self {
+        let ou = |name: &str| {
+            Some(OuIdentifier {
+                certificate: Some(ca_certificate.to_string()),
+                organizational_unit_identifier: name.to_string(),
+            })
+        };
+        Self {
+            enable: true,
+            client_ou_identifier: ou("client"),
+            peer_ou_identifier: ou("peer"),
+            admin_ou_identifier: ou("admin"),
+            orderer_ou_identifier: ou("orderer"),
+        }
+    }
+
+    /// OU identifiers by role name
+    pub fn roles(&self) -> Vec<(&'static str, &OuIdentifier)> {
+        [
+            ("client", &self.client_ou_identifier),
+            ("peer", &self.peer_ou_identifier),
+            ("admin", &self.admin_ou_identifier),
+            ("orderer", &self.orderer_ou_identifier),
+        ]
+        .into_iter()
+        .filter_map(|(role, ou)| ou.as_ref().map(|ou| (role, ou)))
+        .collect()
+    }
+}
+
+/// Contents of an MSP `config.yaml`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MspConfig {
+    /// MSP ID, when the issuing tooling records it
+    #[serde(rename = "MSPID", alias = "MspID", default, skip_serializing_if = "Option::is_none")]
+    pub mspid: Option<String>,
+    #[serde(rename = "NodeOUs", default, skip_serializing_if = "Option::is_none")]
+    pub node_ous: Option<NodeOus>,
+}
+
+/// Parsed contents of a Fabric MSP directory
+#[derive(Debug, Clone)]
+pub struct MspDirectory {
+    pub path: PathBuf,
+    /// PEM signing certificates from `signcerts/`
+    pub signcerts: Vec<String>,
+    /// PEM private keys from `keystore/`
+    pub keystore: Vec<String>,
+    /// PEM root CA certificates from `cacerts/`
+    pub cacerts: Vec<String>,
+    /// PEM intermediate CA certificates from `intermediatecerts/`
+    pub intermediatecerts: Vec<String>,
+    /// PEM TLS root CA certificates from `tlscacerts/`
+    pub tlscacerts: Vec<String>,
+    /// PEM certificate revocation lists from `crls/`
+    pub crls: Vec<String>,
+    pub config: MspConfig,
+}
+
+impl MspDirectory {
+    /// Read every folder of an MSP directory
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.is_dir() {
+            return Err(msp_err(format!("MSP directory not found: {:?}", path)));
+        }
+
+        let config_path = path.join(CONFIG_FILE);
+        let config = if config_path.is_file() {
+            let yaml = std::fs::read_to_string(&config_path)?;
+            serde_yaml::from_str(&yaml)
+                .map_err(|e| msp_err(format!("Invalid {}: {}", CONFIG_FILE, e)))?
+        } else {
+            MspConfig::default()
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            signcerts: read_pem_folder(&path.join(SIGNCERTS))?,
+            keystore: read_pem_folder(&path.join(KEYSTORE))?,
+            cacerts: read_pem_folder(&path.join(CACERTS))?,
+            intermediatecerts: read_pem_folder(&path.join(INTERMEDIATECERTS))?,
+            tlscacerts: read_pem_folder(&path.join(TLSCACERTS))?,
+            crls: read_pem_folder(&path.join(CRLS))?,
+            config,
+        })
+    }
+
+    /// Find the keystore key whose public half matches a certificate
+    pub fn matching_key(&self, cert_pem: &str) -> Result<PKey<Private>> {
+        let cert = X509::from_pem(cert_pem.as_bytes()).map_err(msp_err)?;
+        let cert_key = cert.public_key().map_err(msp_err)?;
+
+        self.keystore
+            .iter()
+            .filter_map(|pem| PKey::private_key_from_pem(pem.as_bytes()).ok())
+            .find(|key| key.public_eq(&cert_key))
+            .ok_or_else(|| msp_err("No keystore key matches the signing certificate"))
+    }
+
+    /// Resolve the NodeOU role of a certificate from its OU attributes
+    pub fn node_ou_role(&self, cert_pem: &str) -> Option<&'static str> {
+        let node_ous = self.config.node_ous.as_ref().filter(|n| n.enable)?;
+        let cert = X509::from_pem(cert_pem.as_bytes()).ok()?;
+        let ous = name_entries(cert.subject_name(), Nid::ORGANIZATIONALUNITNAME);
+
+        node_ous
+            .roles()
+            .into_iter()
+            .find(|(_, ou)| ous.contains(&ou.organizational_unit_identifier))
+            .map(|(role, _)| role)
+    }
+}
+
+impl FabricIdentity {
+    /// Assemble an identity from a Fabric MSP directory
+    ///
+    /// The MSP ID comes from `mspid` or, failing that, from `config.yaml`.
+    /// `tlscacerts/` and the certificate's NodeOU role are kept on the
+    /// identity, and CRLs in `crls/` are attached so `validate` rejects a
+    /// revoked certificate.
+    pub fn from_msp_dir(path: &Path, mspid: Option<&str>) -> Result<Self> {
+        let msp = MspDirectory::load(path)?;
+
+        let certificate = msp
+            .signcerts
+            .first()
+            .cloned()
+            .ok_or_else(|| msp_err("signcerts/ is empty"))?;
+        let key = msp.matching_key(&certificate)?;
+        let private_key = pem_string(key.private_key_to_pem_pkcs8().map_err(msp_err)?)?;
+        let public_key = pem_string(key.public_key_to_pem().map_err(msp_err)?)?;
+
+        if msp.cacerts.is_empty() {
+            return Err(msp_err("cacerts/ is empty"));
+        }
+        let ca_certificate = msp
+            .cacerts
+            .iter()
+            .chain(msp.intermediatecerts.iter())
+            .map(|pem| pem.trim_end().to_string() + "\n")
+            .collect::<String>();
+
+        let mspid = mspid
+            .map(|m| m.to_string())
+            .or_else(|| msp.config.mspid.clone())
+            .ok_or_else(|| msp_err("MSP ID not given and not present in config.yaml"))?;
+        let node_ou_role = msp.node_ou_role(&certificate).map(str::to_string);
+        let tls_ca_certificate = msp
+            .tlscacerts
+            .iter()
+            .map(|pem| pem.trim_end().to_string() + "\n")
+            .collect::<String>();
+
+        let cert = X509::from_pem(certificate.as_bytes()).map_err(msp_err)?;
+        let user_id = name_entries(cert.subject_name(), Nid::COMMONNAME)
+            .into_iter()
+            .next()
+            .ok_or_else(|| msp_err("Signing certificate has no common name"))?;
+        let org_name = name_entries(cert.subject_name(), Nid::ORGANIZATIONNAME)
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+
+        tracing::debug!(
+            "Loaded MSP identity {} from {:?}",
+            name_to_string(cert.subject_name()),
+            path
+        );
+        let mut identity = FabricIdentity::new(
+            private_key,
+            public_key,
+            certificate,
+            ca_certificate,
+            user_id,
+            org_name,
+            mspid,
+        );
+        identity.tls_ca_certificate = tls_ca_certificate;
+        identity.node_ou_role = node_ou_role;
+        if !msp.crls.is_empty() {
+            identity = identity.with_crls(Arc::new(CrlStore::from_msp(&msp)?));
+        }
+        Ok(identity)
+    }
+
+    /// Write the identity as a Fabric MSP directory
+    ///
+    /// `config.yaml` records the MSP ID, so `from_msp_dir` needs none, and
+    /// enables NodeOUs when the identity has a NodeOU role or the
+    /// certificate carries a client, peer, admin or orderer OU.
+    pub fn export_msp_dir(&self, path: &Path) -> Result<()> {
+        let ca_certs = X509::stack_from_pem(self.ca_certificate.as_bytes())
+            .map_err(|e| msp_err(format!("Invalid CA certificate: {}", e)))?;
+        if ca_certs.is_empty() {
+            return Err(msp_err("Identity has no CA certificate"));
+        }
+        let key = PKey::private_key_from_pem(self.private_key.expose_secret().as_bytes()).map_err(msp_err)?;
+
+        for folder in [SIGNCERTS, KEYSTORE, CACERTS] {
+            std::fs::create_dir_all(path.join(folder))?;
+        }
+        std::fs::write(path.join(SIGNCERTS).join("cert.pem"), &self.certificate)?;
+        write_atomic(
+            &path.join(KEYSTORE).join("priv_sk"),
+            &key.private_key_to_pem_pkcs8().map_err(msp_err)?,
+        )?;
+
+        if !self.tls_ca_certificate.is_empty() {
+            let tls_certs = X509::stack_from_pem(self.tls_ca_certificate.as_bytes())
+                .map_err(|e| msp_err(format!("Invalid TLS CA certificate: {}", e)))?;
+            std::fs::create_dir_all(path.join(TLSCACERTS))?;
+            for (index, cert) in tls_certs.iter().enumerate() {
+                let name = numbered("tlsca", index);
+                std::fs::write(path.join(TLSCACERTS).join(name), cert.to_pem().map_err(msp_err)?)?;
+            }
+        }
+
+        let mut root_index = 0;
+        let mut intermediate_index = 0;
+        for cert in &ca_certs {
+            let pem = cert.to_pem().map_err(msp_err)?;
+            if cert.issued(cert) == X509VerifyResult::OK {
+                let name = numbered("ca", root_index);
+                std::fs::write(path.join(CACERTS).join(name), pem)?;
+                root_index += 1;
+            } else {
+                std::fs::create_dir_all(path.join(INTERMEDIATECERTS))?;
+                let name = numbered("ica", intermediate_index);
+                std::fs::write(path.join(INTERMEDIATECERTS).join(name), pem)?;
+                intermediate_index += 1;
+            }
+        }
+
+        let cert = X509::from_pem(self.certificate.as_bytes()).map_err(msp_err)?;
+        let ous = name_entries(cert.subject_name(), Nid::ORGANIZATIONALUNITNAME);
+        let has_node_ou = self.node_ou_role.is_some()
+            || ous
+                .iter()
+                .any(|ou| matches!(ou.as_str(), "client" | "peer" | "admin" | "orderer"));
+        let config = MspConfig {
+            mspid: Some(self.mspid.clone()),
+            node_ous: (has_node_ou && root_index > 0)
+                .then(|| NodeOus::standard(&format!("{}/ca.pem", CACERTS))),
+        };
+        let yaml = serde_yaml::to_string(&config).map_err(msp_err)?;
+        std::fs::write(path.join(CONFIG_FILE), yaml)?;
+
+        tracing::info!("Exported MSP directory to {:?}", path);
+        Ok(())
+    }
+}
+
+fn numbered(prefix: &str, index: usize) -> String {
+    if index == 0 {
+        format!("{}.pem", prefix)
+    } else {
+        format!("{}-{}.pem", prefix, index)
+    }
+}
+
+/// Read every PEM file in a folder, sorted by name; a missing folder is empty
+fn read_pem_folder(path: &Path) -> Result<Vec<String>> {
+    if !path.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+
+    let mut pems = Vec::new();
+    for file in files {
+        let content = std::fs::read_to_string(&file)?;
+        if content.contains("-----BEGIN") {
+            pems.push(content);
+        }
+    }
+    Ok(pems)
+}
+
+pub(crate) fn name_entries(name: &X509NameRef, nid: Nid) -> Vec<String> {
+    name.entries_by_nid(nid)
+        .map(|entry| String::from_utf8_lossy(entry.data().as_slice()).to_string())
+        .collect()
+}
+
+fn pem_string(pem: Vec<u8>) -> Result<String> {
+    String::from_utf8(pem).map_err(msp_err)
+}
+
+fn msp_err(e: impl std::fmt::Display) -> FabricCoreError {
+    FabricCoreError::KeyManagementError(format!("MSP: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoManager;
+    use crate::test_util::{CertSpec, TestCert};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, file: &str, contents: &str) {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    /// Lay out an MSP directory the way the Fabric CA client does
+    fn fabric_ca_msp() -> (PathBuf, TestCert, TestCert) {
+        let root = TestCert::root("ca.org1.example.com");
+        let user = root.issue(CertSpec {
+            common_name: "moderator1",
+            ..Default::default()
+        });
+        let decoy = root.issue(CertSpec::default());
+
+        let dir = temp_dir("msp");
+        write(&dir, "signcerts/cert.pem", &user.cert_pem());
+        write(&dir, "keystore/0a1b2c_sk", &decoy.key_pem());
+        write(&dir, "keystore/9f8e7d_sk", &user.key_pem());
+        write(&dir, "cacerts/localhost-7054.pem", &root.cert_pem());
+        write(&dir, "tlscacerts/tls-localhost-7054.pem", &root.cert_pem());
+        write(
+            &dir,
+            "config.yaml",
+            "MSPID: Org1MSP\n\
+             NodeOUs:\n  Enable: true\n\
+             \x20 ClientOUIdentifier:\n    Certificate: cacerts/localhost-7054.pem\n    OrganizationalUnitIdentifier: client\n\
+             \x20 AdminOUIdentifier:\n    Certificate: cacerts/localhost-7054.pem\n    OrganizationalUnitIdentifier: admin\n",
+        );
+        (dir, root, user)
+    }
+
+    #[test]
+    fn test_from_msp_dir() {
+        let (dir, root, user) = fabric_ca_msp();
+        let identity = FabricIdentity::from_msp_dir(&dir, None).unwrap();
+
+        assert_eq!(identity.user_id, "moderator1");
+        assert_eq!(identity.org_name, "Org1");
+        assert_eq!(identity.mspid, "Org1MSP");
+        assert_eq!(identity.certificate, user.cert_pem());
+        assert_eq!(identity.ca_certificate, root.cert_pem());
+        assert_eq!(identity.public_key, user.public_key_pem());
+        assert_eq!(
+            CryptoManager::public_key_from_private(identity.private_key.expose_secret()).unwrap(),
+            user.public_key_pem()
+        );
+
+        assert_eq!(identity.tls_ca_certificate, root.cert_pem());
+        assert_eq!(identity.node_ou_role.as_deref(), Some("client"));
+
+        let overridden = FabricIdentity::from_msp_dir(&dir, Some("Org9MSP")).unwrap();
+        assert_eq!(overridden.mspid, "Org9MSP");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_msp_dir_without_matching_key() {
+        let (dir, _, _) = fabric_ca_msp();
+        std::fs::remove_file(dir.join("keystore/9f8e7d_sk")).unwrap();
+
+        let error = FabricIdentity::from_msp_dir(&dir, None).unwrap_err();
+        assert!(error.to_string().contains("No keystore key matches"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_msp_dir_requires_mspid() {
+        let (dir, _, _) = fabric_ca_msp();
+        std::fs::remove_file(dir.join("config.yaml")).unwrap();
+
+        assert!(FabricIdentity::from_msp_dir(&dir, None).is_err());
+        assert!(FabricIdentity::from_msp_dir(&dir, Some("Org1MSP")).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_msp_dir_attaches_crls() {
+        let (dir, root, user) = fabric_ca_msp();
+        FabricIdentity::from_msp_dir(&dir, None).unwrap().validate().unwrap();
+
+        write(&dir, "crls/crl.pem", &root.crl(&[&user]));
+        let identity = FabricIdentity::from_msp_dir(&dir, None).unwrap();
+        assert!(matches!(
+            identity.validate(),
+            Err(FabricCoreError::CertificateRevoked { .. })
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_msp_dir_round_trip() {
+        let root = TestCert::root("ca.org1.example.com");
+        let intermediate = root.issue(CertSpec {
+            common_name: "ica.org1.example.com",
+            organizational_units: Vec::new(),
+            ca: true,
+            ..Default::default()
+        });
+        let user = intermediate.issue(CertSpec::default());
+        let mut identity = FabricIdentity::new(
+            user.key_pem(),
+            user.public_key_pem(),
+            user.cert_pem(),
+            format!("{}{}", root.cert_pem(), intermediate.cert_pem()),
+            "user1".to_string(),
+            "Org1".to_string(),
+            "Org1MSP".to_string(),
+        );
+        identity.tls_ca_certificate = root.cert_pem();
+
+        let dir = temp_dir("msp-export");
+        identity.export_msp_dir(&dir).unwrap();
+        assert!(dir.join("signcerts/cert.pem").is_file());
+        assert!(dir.join("keystore/priv_sk").is_file());
+        assert!(dir.join("cacerts/ca.pem").is_file());
+        assert!(dir.join("intermediatecerts/ica.pem").is_file());
+        assert!(dir.join("tlscacerts/tlsca.pem").is_file());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.join("keystore/priv_sk")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let msp = MspDirectory::load(&dir).unwrap();
+        assert_eq!(msp.config.mspid.as_deref(), Some("Org1MSP"));
+        assert!(msp.config.node_ous.as_ref().unwrap().enable);
+        assert_eq!(msp.node_ou_role(&identity.certificate), Some("client"));
+
+        let imported = FabricIdentity::from_msp_dir(&dir, None).unwrap();
+        assert_eq!(imported.mspid, "Org1MSP");
+        assert_eq!(imported.private_key, identity.private_key);
+        assert_eq!(imported.certificate, identity.certificate);
+        assert_eq!(imported.ca_certificate, identity.ca_certificate);
+        assert_eq!(imported.tls_ca_certificate, identity.tls_ca_certificate);
+        assert_eq!(imported.node_ou_role.as_deref(), Some("client"));
+        assert_eq!(imported.user_id, "user1");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
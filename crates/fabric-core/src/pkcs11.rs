@@ -0,0 +1,355 @@
+/// PKCS#11 HSM signing backend
+use crate::crypto::{normalize_low_s, EcCurve};
+use crate::error::{FabricCoreError, Result};
+use crate::secret::SecretString;
+use crate::signer::{SignatureAlgorithm, Signer};
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::error::{Error as CryptokiError, RvError};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::slot::Slot;
+use cryptoki::types::AuthPin;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey, EcPoint};
+use openssl::ecdsa::EcdsaSig;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// DER-encoded named curve OIDs as stored in CKA_EC_PARAMS
+const P256_PARAMS: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const P384_PARAMS: &[u8] = &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22];
+
+/// Location of a signing key inside a PKCS#11 token
+#[derive(Debug, Clone)]
+pub struct Pkcs11Config {
+    /// PKCS#11 module, e.g. `/usr/lib/softhsm/libsofthsm2.so`
+    pub module_path: PathBuf,
+    pub token_label: String,
+    /// User PIN of the token
+    pub pin: SecretString,
+    /// CKA_LABEL shared by the private and public key objects
+    pub key_label: String,
+}
+
+impl Pkcs11Config {
+    pub fn new(
+        module_path: impl Into<PathBuf>,
+        token_label: &str,
+        pin: impl Into<SecretString>,
+        key_label: &str,
+    ) -> Self {
+        Self {
+            module_path: module_path.into(),
+            token_label: token_label.to_string(),
+            pin: pin.into(),
+            key_label: key_label.to_string(),
+        }
+    }
+}
+
+/// Signer whose private key stays inside a PKCS#11 token
+pub struct Pkcs11Signer {
+    session: Mutex<Session>,
+    key: ObjectHandle,
+    key_label: String,
+    curve: EcCurve,
+    public_key_pem: String,
+}
+
+impl Pkcs11Signer {
+    /// Log in to the token and look up an existing key pair
+    pub fn open(config: &Pkcs11Config) -> Result<Self> {
+        let session = login(config)?;
+        let key = find_key(&session, ObjectClass::PRIVATE_KEY, &config.key_label)?;
+        let public = find_key(&session, ObjectClass::PUBLIC_KEY, &config.key_label)?;
+        Self::from_handles(session, key, public, &config.key_label)
+    }
+
+    /// Generate a non-extractable key pair in the token
+    pub fn generate(config: &Pkcs11Config, curve: EcCurve) -> Result<Self> {
+        let session = login(config)?;
+        if find_key(&session, ObjectClass::PRIVATE_KEY, &config.key_label).is_ok() {
+            return Err(pkcs11_err(format!(
+                "Key {} already exists in token {}",
+                config.key_label, config.token_label
+            )));
+        }
+
+        let params = match curve {
+            EcCurve::P256 => P256_PARAMS,
+            EcCurve::P384 => P384_PARAMS,
+        };
+        let label = config.key_label.as_bytes().to_vec();
+        let public_template = [
+            Attribute::Token(true),
+            Attribute::Verify(true),
+            Attribute::EcParams(params.to_vec()),
+            Attribute::Label(label.clone()),
+        ];
+        let private_template = [
+            Attribute::Token(true),
+            Attribute::Private(true),
+            Attribute::Sensitive(true),
+            Attribute::Extractable(false),
+            Attribute::Sign(true),
+            Attribute::Label(label),
+        ];
+        let (public, key) = session
+            .generate_key_pair(&Mechanism::EccKeyPairGen, &public_template, &private_template)
+            .map_err(pkcs11_err)?;
+
+        tracing::info!("Generated {} key {} in PKCS#11 token", curve, config.key_label);
+        Self::from_handles(session, key, public, &config.key_label)
+    }
+
+    fn from_handles(
+        session: Session,
+        key: ObjectHandle,
+        public: ObjectHandle,
+        key_label: &str,
+    ) -> Result<Self> {
+        let (curve, public_key_pem) = read_public_key(&session, public)?;
+        Ok(Self {
+            session: Mutex::new(session),
+            key,
+            key_label: key_label.to_string(),
+            curve,
+            public_key_pem,
+        })
+    }
+}
+
+impl fmt::Debug for Pkcs11Signer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkcs11Signer")
+            .field("key_label", &self.key_label)
+            .field("curve", &self.curve)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Signer for Pkcs11Signer {
+    fn public_key_pem(&self) -> Result<String> {
+        Ok(self.public_key_pem.clone())
+    }
+
+    fn sign_digest(&self, digest: &[u8]) -> Result<Vec<u8>> {
+        let session = self
+            .session
+            .lock()
+            .map_err(|_| pkcs11_err("PKCS#11 session lock poisoned"))?;
+        let raw = session
+            .sign(&Mechanism::Ecdsa, self.key, digest)
+            .map_err(pkcs11_err)?;
+        raw_signature_to_der(&raw, self.curve)
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::EcdsaSha256(self.curve)
+    }
+}
+
+fn login(config: &Pkcs11Config) -> Result<Session> {
+    let pkcs11 = Pkcs11::new(&config.module_path).map_err(pkcs11_err)?;
+    match pkcs11.initialize(CInitializeArgs::OsThreads) {
+        Ok(()) | Err(CryptokiError::Pkcs11(RvError::CryptokiAlreadyInitialized, _)) => {}
+        Err(e) => return Err(pkcs11_err(e)),
+    }
+
+    let slot = find_slot(&pkcs11, &config.token_label)?;
+    let session = pkcs11.open_rw_session(slot).map_err(pkcs11_err)?;
+    let pin = AuthPin::new(config.pin.expose_secret().clone());
+    match session.login(UserType::User, Some(&pin)) {
+        Ok(()) | Err(CryptokiError::Pkcs11(RvError::UserAlreadyLoggedIn, _)) => Ok(session),
+        Err(e) => Err(pkcs11_err(e)),
+    }
+}
+
+fn find_slot(pkcs11: &Pkcs11, token_label: &str) -> Result<Slot> {
+    for slot in pkcs11.get_slots_with_token().map_err(pkcs11_err)? {
+        let info = pkcs11.get_token_info(slot).map_err(pkcs11_err)?;
+        if info.label().trim_end() == token_label {
+            return Ok(slot);
+        }
+    }
+    Err(pkcs11_err(format!("Token not found: {}", token_label)))
+}
+
+fn find_key(session: &Session, class: ObjectClass, label: &str) -> Result<ObjectHandle> {
+    let template = [
+        Attribute::Class(class),
+        Attribute::KeyType(KeyType::EC),
+        Attribute::Label(label.as_bytes().to_vec()),
+    ];
+    session
+        .find_objects(&template)
+        .map_err(pkcs11_err)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| pkcs11_err(format!("Key not found: {}", label)))
+}
+
+/// Rebuild the SPKI PEM of a token public key from CKA_EC_PARAMS and CKA_EC_POINT
+fn read_public_key(session: &Session, public: ObjectHandle) -> Result<(EcCurve, String)> {
+    let attributes = session
+        .get_attributes(public, &[AttributeType::EcParams, AttributeType::EcPoint])
+        .map_err(pkcs11_err)?;
+
+    let mut params = None;
+    let mut point = None;
+    for attribute in attributes {
+        match attribute {
+            Attribute::EcParams(value) => params = Some(value),
+            Attribute::EcPoint(value) => point = Some(value),
+            _ => {}
+        }
+    }
+
+    let curve = match params.as_deref() {
+        Some(P256_PARAMS) => EcCurve::P256,
+        Some(P384_PARAMS) => EcCurve::P384,
+        _ => return Err(pkcs11_err("Unsupported or missing CKA_EC_PARAMS")),
+    };
+    let point = point.ok_or_else(|| pkcs11_err("Public key has no CKA_EC_POINT"))?;
+
+    let group = EcGroup::from_curve_name(curve.nid()).map_err(pkcs11_err)?;
+    let mut ctx = BigNumContext::new().map_err(pkcs11_err)?;
+    let point = EcPoint::from_bytes(&group, unwrap_octet_string(&point), &mut ctx)
+        .map_err(pkcs11_err)?;
+    let key = EcKey::from_public_key(&group, &point).map_err(pkcs11_err)?;
+    let pem = key.public_key_to_pem().map_err(pkcs11_err)?;
+    Ok((curve, String::from_utf8(pem).map_err(pkcs11_err)?))
+}
+
+/// Strip the DER OCTET STRING that most tokens wrap around CKA_EC_POINT
+fn unwrap_octet_string(point: &[u8]) -> &[u8] {
+    match point {
+        [0x04, len, rest @ ..] if *len < 0x80 && rest.len() == *len as usize => rest,
+        [0x04, 0x81, len, rest @ ..] if rest.len() == *len as usize => rest,
+        _ => point,
+    }
+}
+
+/// Convert a raw `r || s` ECDSA signature, as returned by CKM_ECDSA, into low-S DER
+fn raw_signature_to_der(raw: &[u8], curve: EcCurve) -> Result<Vec<u8>> {
+    if raw.is_empty() || !raw.len().is_multiple_of(2) {
+        return Err(pkcs11_err(format!(
+            "Unexpected raw signature length {}",
+            raw.len()
+        )));
+    }
+    let (r, s) = raw.split_at(raw.len() / 2);
+    let r = BigNum::from_slice(r).map_err(pkcs11_err)?;
+    let s = BigNum::from_slice(s).map_err(pkcs11_err)?;
+    let signature = EcdsaSig::from_private_components(r, s).map_err(pkcs11_err)?;
+
+    let group = EcGroup::from_curve_name(curve.nid()).map_err(pkcs11_err)?;
+    normalize_low_s(signature, &group)?
+        .to_der()
+        .map_err(pkcs11_err)
+}
+
+fn pkcs11_err(e: impl fmt::Display) -> FabricCoreError {
+    FabricCoreError::CryptoError(format!("PKCS#11: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoManager;
+    use crate::signer::SoftwareSigner;
+
+    /// SoftHSM module path the ignored HSM tests need; run them with
+    /// `cargo test --features pkcs11 -- --ignored`
+    const MODULE_ENV: &str = "PKCS11_SOFTHSM2_MODULE";
+    const SO_PIN: &str = "fabric-so";
+    const USER_PIN: &str = "fabric-user";
+
+    /// Initialize a fresh SoftHSM token and return a config pointing into it
+    fn softhsm_token(key_label: &str) -> Pkcs11Config {
+        let module = std::env::var(MODULE_ENV)
+            .unwrap_or_else(|_| panic!("{} must point at the SoftHSM module", MODULE_ENV));
+
+        let pkcs11 = Pkcs11::new(&module).unwrap();
+        match pkcs11.initialize(CInitializeArgs::OsThreads) {
+            Ok(()) | Err(CryptokiError::Pkcs11(RvError::CryptokiAlreadyInitialized, _)) => {}
+            Err(e) => panic!("{}", e),
+        }
+        let slot = pkcs11
+            .get_all_slots()
+            .unwrap()
+            .into_iter()
+            .find(|slot| {
+                pkcs11
+                    .get_token_info(*slot)
+                    .map(|info| !info.token_initialized())
+                    .unwrap_or(false)
+            })
+            .expect("no free SoftHSM slot");
+
+        let token_label = format!("fabric-{}", uuid::Uuid::new_v4().simple())[..32].to_string();
+        let so_pin = AuthPin::new(SO_PIN.to_string());
+        pkcs11.init_token(slot, &so_pin, &token_label).unwrap();
+        let session = pkcs11.open_rw_session(slot).unwrap();
+        session.login(UserType::So, Some(&so_pin)).unwrap();
+        session.init_pin(&AuthPin::new(USER_PIN.to_string())).unwrap();
+
+        Pkcs11Config::new(module, &token_label, USER_PIN, key_label)
+    }
+
+    #[test]
+    fn test_raw_signature_conversion_normalizes_s() {
+        let signer = SoftwareSigner::generate(EcCurve::P256).unwrap();
+        let der = signer.sign(b"payload").unwrap();
+        let parsed = EcdsaSig::from_der(&der).unwrap();
+
+        // Re-encode with the high-S twin, as an HSM may return it
+        let group = EcGroup::from_curve_name(EcCurve::P256.nid()).unwrap();
+        let mut order = BigNum::new().unwrap();
+        group
+            .order(&mut order, &mut BigNumContext::new().unwrap())
+            .unwrap();
+        let mut high_s = BigNum::new().unwrap();
+        high_s.checked_sub(&order, parsed.s()).unwrap();
+
+        let mut raw = parsed.r().to_vec_padded(32).unwrap();
+        raw.extend(high_s.to_vec_padded(32).unwrap());
+        assert_eq!(raw_signature_to_der(&raw, EcCurve::P256).unwrap(), der);
+        assert!(raw_signature_to_der(&raw[..63], EcCurve::P256).is_err());
+    }
+
+    #[test]
+    fn test_unwrap_ec_point() {
+        let point = [0x04u8; 65];
+        let mut wrapped = vec![0x04, 65];
+        wrapped.extend_from_slice(&point);
+        assert_eq!(unwrap_octet_string(&wrapped), &point[..]);
+        assert_eq!(unwrap_octet_string(&point), &point[..]);
+    }
+
+    #[test]
+    #[ignore = "needs SoftHSM"]
+    fn test_softhsm_generate_sign_and_reopen() {
+        let config = softhsm_token("enrollment-key");
+
+        let signer = Pkcs11Signer::generate(&config, EcCurve::P256).unwrap();
+        let public_key = signer.public_key_pem().unwrap();
+        let signature = signer.sign(b"proposal").unwrap();
+        assert!(CryptoManager::verify(&public_key, b"proposal", &signature).unwrap());
+        assert!(Pkcs11Signer::generate(&config, EcCurve::P256).is_err());
+        drop(signer);
+
+        let reopened = Pkcs11Signer::open(&config).unwrap();
+        assert_eq!(reopened.public_key_pem().unwrap(), public_key);
+        assert_eq!(reopened.algorithm(), SignatureAlgorithm::EcdsaSha256(EcCurve::P256));
+        drop(reopened);
+
+        let wrong_pin = Pkcs11Config {
+            pin: "wrong".into(),
+            ..config.clone()
+        };
+        assert!(Pkcs11Signer::open(&wrong_pin).is_err());
+    }
+}
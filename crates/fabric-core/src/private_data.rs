@@ -0,0 +1,137 @@
+/// Transient data and private data collection targeting for chaincode calls
+use crate::error::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Private inputs of a chaincode call
+///
+/// Transient values reach the chaincode with the proposal but are stripped
+/// from the transaction, so they never land on the ledger. Chaincode that
+/// stores them in a private data collection keeps the data on the
+/// collection members' peers and only a hash on the ledger.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PrivateData {
+    /// Transient map; values are base64 encoded in JSON
+    #[serde(default, with = "base64_values")]
+    pub transient: BTreeMap<String, Vec<u8>>,
+    /// Private data collection the call reads or writes
+    pub collection: Option<String>,
+    /// MSP IDs to endorse or evaluate on, usually the collection members
+    #[serde(default)]
+    pub organizations: Vec<String>,
+}
+
+impl PrivateData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a transient value
+    pub fn with_transient(mut self, key: &str, value: impl Into<Vec<u8>>) -> Self {
+        self.transient.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Add a transient value serialized as JSON
+    pub fn with_transient_json<T: Serialize>(self, key: &str, value: &T) -> Result<Self> {
+        Ok(self.with_transient(key, serde_json::to_vec(value)?))
+    }
+
+    /// Target a private data collection held by `organizations`
+    pub fn with_collection(mut self, collection: &str, organizations: &[&str]) -> Self {
+        self.collection = Some(collection.to_string());
+        self.organizations = organizations.iter().map(|org| org.to_string()).collect();
+        self
+    }
+
+    /// Transient map with base64 values, as the REST gateway expects it
+    pub(crate) fn transient_base64(&self) -> BTreeMap<String, String> {
+        self.transient
+            .iter()
+            .map(|(key, value)| (key.clone(), BASE64.encode(value)))
+            .collect()
+    }
+
+    /// Transient map for a chaincode proposal payload
+    pub(crate) fn transient_map(&self) -> HashMap<String, Vec<u8>> {
+        self.transient.clone().into_iter().collect()
+    }
+
+    /// `private_data` object of a REST gateway request
+    pub(crate) fn targeting(&self) -> serde_json::Value {
+        let mut targeting = serde_json::Map::new();
+        if let Some(collection) = &self.collection {
+            targeting.insert("collection".to_string(), collection.clone().into());
+        }
+        if !self.organizations.is_empty() {
+            targeting.insert(
+                "endorsing_organizations".to_string(),
+                self.organizations.clone().into(),
+            );
+        }
+        serde_json::Value::Object(targeting)
+    }
+}
+
+mod base64_values {
+    use super::BASE64;
+    use base64::Engine;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(
+        map: &BTreeMap<String, Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(map.iter().map(|(key, value)| (key, BASE64.encode(value))))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<String, Vec<u8>>, D::Error> {
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| {
+                BASE64
+                    .decode(&value)
+                    .map(|value| (key.clone(), value))
+                    .map_err(|e| D::Error::custom(format!("transient value {}: {}", key, e)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_values_are_base64_in_json() {
+        let private = PrivateData::new()
+            .with_transient("contact", "alice@example.com")
+            .with_transient_json("note", &serde_json::json!({"reason": "spoilers"}))
+            .unwrap()
+            .with_collection("moderationNotes", &["Org1MSP"]);
+
+        let json = serde_json::to_value(&private).unwrap();
+        assert_eq!(json["transient"]["contact"], "YWxpY2VAZXhhbXBsZS5jb20=");
+        assert_eq!(json["collection"], "moderationNotes");
+        assert_eq!(
+            serde_json::from_value::<PrivateData>(json).unwrap(),
+            private
+        );
+        assert_eq!(
+            private.targeting(),
+            serde_json::json!({
+                "collection": "moderationNotes",
+                "endorsing_organizations": ["Org1MSP"],
+            })
+        );
+        assert_eq!(PrivateData::new().targeting(), serde_json::json!({}));
+
+        let invalid = serde_json::json!({"transient": {"contact": "not base64!"}});
+        assert!(serde_json::from_value::<PrivateData>(invalid).is_err());
+    }
+}
@@ -0,0 +1,629 @@
+//! Protobuf messages of the Fabric Gateway service
+//!
+//! Hand-written `prost` equivalents of the subset of `fabric-protos` used by
+//! the gateway client. Field numbers and package paths follow the upstream
+//! `.proto` files so the messages are wire compatible with Fabric peers.
+
+/// `common` package
+pub mod common {
+    /// `google.protobuf.Timestamp`
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Timestamp {
+        #[prost(int64, tag = "1")]
+        pub seconds: i64,
+        #[prost(int32, tag = "2")]
+        pub nanos: i32,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum HeaderType {
+        Message = 0,
+        Config = 1,
+        ConfigUpdate = 2,
+        EndorserTransaction = 3,
+        DeliverSeekInfo = 5,
+    }
+
+    /// Status codes of the Deliver service
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Status {
+        Unknown = 0,
+        Success = 200,
+        BadRequest = 400,
+        Forbidden = 403,
+        NotFound = 404,
+        RequestEntityTooLarge = 413,
+        InternalServerError = 500,
+        NotImplemented = 501,
+        ServiceUnavailable = 503,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Envelope {
+        #[prost(bytes = "vec", tag = "1")]
+        pub payload: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub signature: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Payload {
+        #[prost(message, optional, tag = "1")]
+        pub header: Option<Header>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub data: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Header {
+        #[prost(bytes = "vec", tag = "1")]
+        pub channel_header: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub signature_header: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChannelHeader {
+        #[prost(int32, tag = "1")]
+        pub r#type: i32,
+        #[prost(int32, tag = "2")]
+        pub version: i32,
+        #[prost(message, optional, tag = "3")]
+        pub timestamp: Option<Timestamp>,
+        #[prost(string, tag = "4")]
+        pub channel_id: String,
+        #[prost(string, tag = "5")]
+        pub tx_id: String,
+        #[prost(uint64, tag = "6")]
+        pub epoch: u64,
+        #[prost(bytes = "vec", tag = "7")]
+        pub extension: Vec<u8>,
+        #[prost(bytes = "vec", tag = "8")]
+        pub tls_cert_hash: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SignatureHeader {
+        #[prost(bytes = "vec", tag = "1")]
+        pub creator: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub nonce: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Block {
+        #[prost(message, optional, tag = "1")]
+        pub header: Option<BlockHeader>,
+        #[prost(message, optional, tag = "2")]
+        pub data: Option<BlockData>,
+        #[prost(message, optional, tag = "3")]
+        pub metadata: Option<BlockMetadata>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct BlockHeader {
+        #[prost(uint64, tag = "1")]
+        pub number: u64,
+        #[prost(bytes = "vec", tag = "2")]
+        pub previous_hash: Vec<u8>,
+        #[prost(bytes = "vec", tag = "3")]
+        pub data_hash: Vec<u8>,
+    }
+
+    /// Serialized `Envelope`s of the block's transactions
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct BlockData {
+        #[prost(bytes = "vec", repeated, tag = "1")]
+        pub data: Vec<Vec<u8>>,
+    }
+
+    /// Metadata entries indexed by `BlockMetadataIndex`
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct BlockMetadata {
+        #[prost(bytes = "vec", repeated, tag = "1")]
+        pub metadata: Vec<Vec<u8>>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum BlockMetadataIndex {
+        Signatures = 0,
+        LastConfig = 1,
+        /// One `TxValidationCode` byte per transaction
+        TransactionsFilter = 2,
+        Orderer = 3,
+        CommitHash = 4,
+    }
+
+    /// Result of the QSCC `GetChainInfo` query
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct BlockchainInfo {
+        #[prost(uint64, tag = "1")]
+        pub height: u64,
+        #[prost(bytes = "vec", tag = "2")]
+        pub current_block_hash: Vec<u8>,
+        #[prost(bytes = "vec", tag = "3")]
+        pub previous_block_hash: Vec<u8>,
+    }
+}
+
+/// `msp` package
+pub mod msp {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SerializedIdentity {
+        #[prost(string, tag = "1")]
+        pub mspid: String,
+        #[prost(bytes = "vec", tag = "2")]
+        pub id_bytes: Vec<u8>,
+    }
+}
+
+/// `protos` package (peer messages)
+pub mod peer {
+    use std::collections::HashMap;
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SignedProposal {
+        #[prost(bytes = "vec", tag = "1")]
+        pub proposal_bytes: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub signature: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Proposal {
+        #[prost(bytes = "vec", tag = "1")]
+        pub header: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub payload: Vec<u8>,
+        #[prost(bytes = "vec", tag = "3")]
+        pub extension: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeHeaderExtension {
+        #[prost(message, optional, tag = "2")]
+        pub chaincode_id: Option<ChaincodeId>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeProposalPayload {
+        #[prost(bytes = "vec", tag = "1")]
+        pub input: Vec<u8>,
+        #[prost(map = "string, bytes", tag = "2")]
+        pub transient_map: HashMap<String, Vec<u8>>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeId {
+        #[prost(string, tag = "1")]
+        pub path: String,
+        #[prost(string, tag = "2")]
+        pub name: String,
+        #[prost(string, tag = "3")]
+        pub version: String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeInput {
+        #[prost(bytes = "vec", repeated, tag = "1")]
+        pub args: Vec<Vec<u8>>,
+        #[prost(map = "string, bytes", tag = "2")]
+        pub decorations: HashMap<String, Vec<u8>>,
+        #[prost(bool, tag = "3")]
+        pub is_init: bool,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeSpec {
+        #[prost(int32, tag = "1")]
+        pub r#type: i32,
+        #[prost(message, optional, tag = "2")]
+        pub chaincode_id: Option<ChaincodeId>,
+        #[prost(message, optional, tag = "3")]
+        pub input: Option<ChaincodeInput>,
+        #[prost(int32, tag = "4")]
+        pub timeout: i32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeInvocationSpec {
+        #[prost(message, optional, tag = "1")]
+        pub chaincode_spec: Option<ChaincodeSpec>,
+    }
+
+    /// Chaincode response carried in proposal responses
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Response {
+        #[prost(int32, tag = "1")]
+        pub status: i32,
+        #[prost(string, tag = "2")]
+        pub message: String,
+        #[prost(bytes = "vec", tag = "3")]
+        pub payload: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProposalResponsePayload {
+        #[prost(bytes = "vec", tag = "1")]
+        pub proposal_hash: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub extension: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeAction {
+        #[prost(bytes = "vec", tag = "1")]
+        pub results: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub events: Vec<u8>,
+        #[prost(message, optional, tag = "3")]
+        pub response: Option<Response>,
+        #[prost(message, optional, tag = "4")]
+        pub chaincode_id: Option<ChaincodeId>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Endorsement {
+        #[prost(bytes = "vec", tag = "1")]
+        pub endorser: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub signature: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Transaction {
+        #[prost(message, repeated, tag = "1")]
+        pub actions: Vec<TransactionAction>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct TransactionAction {
+        #[prost(bytes = "vec", tag = "1")]
+        pub header: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub payload: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeActionPayload {
+        #[prost(bytes = "vec", tag = "1")]
+        pub chaincode_proposal_payload: Vec<u8>,
+        #[prost(message, optional, tag = "2")]
+        pub action: Option<ChaincodeEndorsedAction>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeEndorsedAction {
+        #[prost(bytes = "vec", tag = "1")]
+        pub proposal_response_payload: Vec<u8>,
+        #[prost(message, repeated, tag = "2")]
+        pub endorsements: Vec<Endorsement>,
+    }
+
+    /// Peer answer to `Endorser/ProcessProposal`
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProposalResponse {
+        #[prost(int32, tag = "1")]
+        pub version: i32,
+        #[prost(message, optional, tag = "2")]
+        pub timestamp: Option<super::common::Timestamp>,
+        #[prost(message, optional, tag = "4")]
+        pub response: Option<Response>,
+        #[prost(bytes = "vec", tag = "5")]
+        pub payload: Vec<u8>,
+        #[prost(message, optional, tag = "6")]
+        pub endorsement: Option<Endorsement>,
+    }
+
+    /// Result of the CSCC `GetChannels` query
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChannelQueryResponse {
+        #[prost(message, repeated, tag = "1")]
+        pub channels: Vec<ChannelInfo>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChannelInfo {
+        #[prost(string, tag = "1")]
+        pub channel_id: String,
+    }
+
+    /// Event set by chaincode, carried in `ChaincodeAction::events`
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeEvent {
+        #[prost(string, tag = "1")]
+        pub chaincode_id: String,
+        #[prost(string, tag = "2")]
+        pub tx_id: String,
+        #[prost(string, tag = "3")]
+        pub event_name: String,
+        #[prost(bytes = "vec", tag = "4")]
+        pub payload: Vec<u8>,
+    }
+
+    /// Message streamed by `Deliver/Deliver`
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DeliverResponse {
+        #[prost(oneof = "deliver_response::Type", tags = "1, 2")]
+        pub r#type: Option<deliver_response::Type>,
+    }
+
+    pub mod deliver_response {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum Type {
+            #[prost(enumeration = "super::super::common::Status", tag = "1")]
+            Status(i32),
+            #[prost(message, tag = "2")]
+            Block(super::super::common::Block),
+        }
+    }
+
+    /// Validation result recorded for each transaction in a block
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum TxValidationCode {
+        Valid = 0,
+        NilEnvelope = 1,
+        BadPayload = 2,
+        BadCommonHeader = 3,
+        BadCreatorSignature = 4,
+        InvalidEndorserTransaction = 5,
+        InvalidConfigTransaction = 6,
+        UnsupportedTxPayload = 7,
+        BadProposalTxid = 8,
+        DuplicateTxid = 9,
+        EndorsementPolicyFailure = 10,
+        MvccReadConflict = 11,
+        PhantomReadConflict = 12,
+        UnknownTxType = 13,
+        TargetChainNotFound = 14,
+        MarshalTxError = 15,
+        NilTxaction = 16,
+        ExpiredChaincode = 17,
+        ChaincodeVersionConflict = 18,
+        BadHeaderExtension = 19,
+        BadChannelHeader = 20,
+        BadResponsePayload = 21,
+        BadRwset = 22,
+        IllegalWriteset = 23,
+        InvalidWriteset = 24,
+        InvalidChaincode = 25,
+        NotValidated = 254,
+        InvalidOtherReason = 255,
+    }
+
+    impl TxValidationCode {
+        /// Name of the value as it appears in the `.proto` definition
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Self::Valid => "VALID",
+                Self::NilEnvelope => "NIL_ENVELOPE",
+                Self::BadPayload => "BAD_PAYLOAD",
+                Self::BadCommonHeader => "BAD_COMMON_HEADER",
+                Self::BadCreatorSignature => "BAD_CREATOR_SIGNATURE",
+                Self::InvalidEndorserTransaction => "INVALID_ENDORSER_TRANSACTION",
+                Self::InvalidConfigTransaction => "INVALID_CONFIG_TRANSACTION",
+                Self::UnsupportedTxPayload => "UNSUPPORTED_TX_PAYLOAD",
+                Self::BadProposalTxid => "BAD_PROPOSAL_TXID",
+                Self::DuplicateTxid => "DUPLICATE_TXID",
+                Self::EndorsementPolicyFailure => "ENDORSEMENT_POLICY_FAILURE",
+                Self::MvccReadConflict => "MVCC_READ_CONFLICT",
+                Self::PhantomReadConflict => "PHANTOM_READ_CONFLICT",
+                Self::UnknownTxType => "UNKNOWN_TX_TYPE",
+                Self::TargetChainNotFound => "TARGET_CHAIN_NOT_FOUND",
+                Self::MarshalTxError => "MARSHAL_TX_ERROR",
+                Self::NilTxaction => "NIL_TXACTION",
+                Self::ExpiredChaincode => "EXPIRED_CHAINCODE",
+                Self::ChaincodeVersionConflict => "CHAINCODE_VERSION_CONFLICT",
+                Self::BadHeaderExtension => "BAD_HEADER_EXTENSION",
+                Self::BadChannelHeader => "BAD_CHANNEL_HEADER",
+                Self::BadResponsePayload => "BAD_RESPONSE_PAYLOAD",
+                Self::BadRwset => "BAD_RWSET",
+                Self::IllegalWriteset => "ILLEGAL_WRITESET",
+                Self::InvalidWriteset => "INVALID_WRITESET",
+                Self::InvalidChaincode => "INVALID_CHAINCODE",
+                Self::NotValidated => "NOT_VALIDATED",
+                Self::InvalidOtherReason => "INVALID_OTHER_REASON",
+            }
+        }
+
+        /// Value for a name as it appears in the `.proto` definition
+        pub fn from_str_name(value: &str) -> Option<Self> {
+            match value {
+                "VALID" => Some(Self::Valid),
+                "NIL_ENVELOPE" => Some(Self::NilEnvelope),
+                "BAD_PAYLOAD" => Some(Self::BadPayload),
+                "BAD_COMMON_HEADER" => Some(Self::BadCommonHeader),
+                "BAD_CREATOR_SIGNATURE" => Some(Self::BadCreatorSignature),
+                "INVALID_ENDORSER_TRANSACTION" => Some(Self::InvalidEndorserTransaction),
+                "INVALID_CONFIG_TRANSACTION" => Some(Self::InvalidConfigTransaction),
+                "UNSUPPORTED_TX_PAYLOAD" => Some(Self::UnsupportedTxPayload),
+                "BAD_PROPOSAL_TXID" => Some(Self::BadProposalTxid),
+                "DUPLICATE_TXID" => Some(Self::DuplicateTxid),
+                "ENDORSEMENT_POLICY_FAILURE" => Some(Self::EndorsementPolicyFailure),
+                "MVCC_READ_CONFLICT" => Some(Self::MvccReadConflict),
+                "PHANTOM_READ_CONFLICT" => Some(Self::PhantomReadConflict),
+                "UNKNOWN_TX_TYPE" => Some(Self::UnknownTxType),
+                "TARGET_CHAIN_NOT_FOUND" => Some(Self::TargetChainNotFound),
+                "MARSHAL_TX_ERROR" => Some(Self::MarshalTxError),
+                "NIL_TXACTION" => Some(Self::NilTxaction),
+                "EXPIRED_CHAINCODE" => Some(Self::ExpiredChaincode),
+                "CHAINCODE_VERSION_CONFLICT" => Some(Self::ChaincodeVersionConflict),
+                "BAD_HEADER_EXTENSION" => Some(Self::BadHeaderExtension),
+                "BAD_CHANNEL_HEADER" => Some(Self::BadChannelHeader),
+                "BAD_RESPONSE_PAYLOAD" => Some(Self::BadResponsePayload),
+                "BAD_RWSET" => Some(Self::BadRwset),
+                "ILLEGAL_WRITESET" => Some(Self::IllegalWriteset),
+                "INVALID_WRITESET" => Some(Self::InvalidWriteset),
+                "INVALID_CHAINCODE" => Some(Self::InvalidChaincode),
+                "NOT_VALIDATED" => Some(Self::NotValidated),
+                "INVALID_OTHER_REASON" => Some(Self::InvalidOtherReason),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// `orderer` package
+pub mod orderer {
+    /// Range of blocks requested from a Deliver service
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SeekInfo {
+        #[prost(message, optional, tag = "1")]
+        pub start: Option<SeekPosition>,
+        #[prost(message, optional, tag = "2")]
+        pub stop: Option<SeekPosition>,
+        #[prost(enumeration = "SeekBehavior", tag = "3")]
+        pub behavior: i32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SeekPosition {
+        #[prost(oneof = "seek_position::Type", tags = "1, 2, 3")]
+        pub r#type: Option<seek_position::Type>,
+    }
+
+    pub mod seek_position {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum Type {
+            #[prost(message, tag = "1")]
+            Newest(super::SeekNewest),
+            #[prost(message, tag = "2")]
+            Oldest(super::SeekOldest),
+            #[prost(message, tag = "3")]
+            Specified(super::SeekSpecified),
+        }
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SeekNewest {}
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SeekOldest {}
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SeekSpecified {
+        #[prost(uint64, tag = "1")]
+        pub number: u64,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum SeekBehavior {
+        BlockUntilReady = 0,
+        FailIfNotReady = 1,
+    }
+}
+
+/// `lifecycle` package
+pub mod lifecycle {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct QueryChaincodeDefinitionsArgs {}
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct QueryChaincodeDefinitionsResult {
+        #[prost(message, repeated, tag = "1")]
+        pub chaincode_definitions: Vec<ChaincodeDefinition>,
+    }
+
+    /// Committed chaincode definition, as listed by `_lifecycle`
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ChaincodeDefinition {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(int64, tag = "2")]
+        pub sequence: i64,
+        #[prost(string, tag = "3")]
+        pub version: String,
+        #[prost(string, tag = "4")]
+        pub endorsement_plugin: String,
+        #[prost(string, tag = "5")]
+        pub validation_plugin: String,
+        #[prost(bool, tag = "8")]
+        pub init_required: bool,
+    }
+}
+
+/// `gateway` package
+pub mod gateway {
+    use super::common::Envelope;
+    use super::peer::{Response, SignedProposal};
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct EvaluateRequest {
+        #[prost(string, tag = "1")]
+        pub transaction_id: String,
+        #[prost(string, tag = "2")]
+        pub channel_id: String,
+        #[prost(message, optional, tag = "3")]
+        pub proposed_transaction: Option<SignedProposal>,
+        #[prost(string, repeated, tag = "4")]
+        pub target_organizations: Vec<String>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct EvaluateResponse {
+        #[prost(message, optional, tag = "1")]
+        pub result: Option<Response>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct EndorseRequest {
+        #[prost(string, tag = "1")]
+        pub transaction_id: String,
+        #[prost(string, tag = "2")]
+        pub channel_id: String,
+        #[prost(message, optional, tag = "3")]
+        pub proposed_transaction: Option<SignedProposal>,
+        #[prost(string, repeated, tag = "4")]
+        pub endorsing_organizations: Vec<String>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct EndorseResponse {
+        #[prost(message, optional, tag = "1")]
+        pub prepared_transaction: Option<Envelope>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubmitRequest {
+        #[prost(string, tag = "1")]
+        pub transaction_id: String,
+        #[prost(string, tag = "2")]
+        pub channel_id: String,
+        #[prost(message, optional, tag = "3")]
+        pub prepared_transaction: Option<Envelope>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubmitResponse {}
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SignedCommitStatusRequest {
+        #[prost(bytes = "vec", tag = "1")]
+        pub request: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub signature: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct CommitStatusRequest {
+        #[prost(string, tag = "1")]
+        pub transaction_id: String,
+        #[prost(string, tag = "2")]
+        pub channel_id: String,
+        #[prost(bytes = "vec", tag = "3")]
+        pub identity: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct CommitStatusResponse {
+        #[prost(enumeration = "super::peer::TxValidationCode", tag = "1")]
+        pub result: i32,
+        #[prost(uint64, tag = "2")]
+        pub block_number: u64,
+    }
+}
@@ -0,0 +1,142 @@
+/// Pluggable signing backends for Fabric identities
+use crate::crypto::{normalize_low_s, EcCurve};
+use crate::error::{FabricCoreError, Result};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::ecdsa::EcdsaSig;
+use openssl::pkey::{PKey, Private};
+use openssl::sha::sha256;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Signature scheme produced by a `Signer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignatureAlgorithm {
+    /// ECDSA over a SHA-256 digest, the Fabric default
+    EcdsaSha256(EcCurve),
+}
+
+impl SignatureAlgorithm {
+    pub fn curve(self) -> EcCurve {
+        match self {
+            SignatureAlgorithm::EcdsaSha256(curve) => curve,
+        }
+    }
+}
+
+impl fmt::Display for SignatureAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureAlgorithm::EcdsaSha256(curve) => write!(f, "ECDSA-SHA256 ({})", curve),
+        }
+    }
+}
+
+/// A private key that can produce Fabric signatures
+///
+/// Implementations return DER-encoded ECDSA signatures with S in the lower
+/// half of the curve order, which is what Fabric peers and CAs accept.
+pub trait Signer: fmt::Debug + Send + Sync {
+    /// SPKI PEM of the matching public key
+    fn public_key_pem(&self) -> Result<String>;
+
+    /// Sign a message digest produced with the algorithm's hash
+    fn sign_digest(&self, digest: &[u8]) -> Result<Vec<u8>>;
+
+    fn algorithm(&self) -> SignatureAlgorithm;
+
+    /// Hash and sign a message
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self.algorithm() {
+            SignatureAlgorithm::EcdsaSha256(_) => self.sign_digest(&sha256(data)),
+        }
+    }
+}
+
+/// Signer backed by a private key held in process memory
+pub struct SoftwareSigner {
+    key: EcKey<Private>,
+    curve: EcCurve,
+}
+
+impl SoftwareSigner {
+    /// Load a PKCS#8 or SEC1 PEM private key
+    pub fn from_pem(private_key_pem: &str) -> Result<Self> {
+        let pkey = PKey::private_key_from_pem(private_key_pem.as_bytes())
+            .map_err(|e| FabricCoreError::InvalidPrivateKey(e.to_string()))?;
+        let key = pkey
+            .ec_key()
+            .map_err(|e| FabricCoreError::InvalidPrivateKey(e.to_string()))?;
+        let curve = key
+            .group()
+            .curve_name()
+            .and_then(EcCurve::from_nid)
+            .ok_or_else(|| {
+                FabricCoreError::InvalidPrivateKey("Unsupported elliptic curve".to_string())
+            })?;
+        Ok(Self { key, curve })
+    }
+
+    /// Generate a fresh key pair
+    pub fn generate(curve: EcCurve) -> Result<Self> {
+        let group = EcGroup::from_curve_name(curve.nid()).map_err(signer_err)?;
+        let key = EcKey::generate(&group).map_err(signer_err)?;
+        Ok(Self { key, curve })
+    }
+
+    /// PKCS#8 PEM of the private key
+    pub fn private_key_pem(&self) -> Result<String> {
+        let pkey = PKey::from_ec_key(self.key.clone()).map_err(signer_err)?;
+        let pem = pkey.private_key_to_pem_pkcs8().map_err(signer_err)?;
+        String::from_utf8(pem).map_err(signer_err)
+    }
+}
+
+impl fmt::Debug for SoftwareSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SoftwareSigner")
+            .field("curve", &self.curve)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Signer for SoftwareSigner {
+    fn public_key_pem(&self) -> Result<String> {
+        let pem = self.key.public_key_to_pem().map_err(signer_err)?;
+        String::from_utf8(pem).map_err(signer_err)
+    }
+
+    fn sign_digest(&self, digest: &[u8]) -> Result<Vec<u8>> {
+        let signature = EcdsaSig::sign(digest, &self.key).map_err(signer_err)?;
+        let signature = normalize_low_s(signature, self.key.group())?;
+        signature.to_der().map_err(signer_err)
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::EcdsaSha256(self.curve)
+    }
+}
+
+fn signer_err(e: impl fmt::Display) -> FabricCoreError {
+    FabricCoreError::CryptoError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoManager;
+
+    #[test]
+    fn test_software_signer_round_trip() {
+        let (private_key, public_key) =
+            CryptoManager::generate_keypair_with_curve(EcCurve::P384).unwrap();
+        let signer = SoftwareSigner::from_pem(&private_key).unwrap();
+
+        assert_eq!(signer.algorithm(), SignatureAlgorithm::EcdsaSha256(EcCurve::P384));
+        assert_eq!(signer.public_key_pem().unwrap(), public_key);
+        assert!(!format!("{:?}", signer).contains("PRIVATE"));
+
+        let signature = signer.sign(b"proposal").unwrap();
+        assert!(CryptoManager::verify(&public_key, b"proposal", &signature).unwrap());
+        assert!(!CryptoManager::verify(&public_key, b"tampered", &signature).unwrap());
+    }
+}
@@ -0,0 +1,1031 @@
+/// Test helpers for issuing throwaway certificate hierarchies
+use openssl::asn1::{Asn1Integer, Asn1Object, Asn1OctetString, Asn1Time};
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{HasPublic, PKey, PKeyRef, Private};
+use openssl::ssl::{SslAcceptor, SslMethod, SslStream, SslVerifyMode, SslVersion};
+use openssl::x509::extension::{
+    AuthorityKeyIdentifier, BasicConstraints, KeyUsage, SubjectAlternativeName,
+    SubjectKeyIdentifier,
+};
+use openssl::x509::{
+    CrlNumber, X509Builder, X509CrlBuilder, X509Extension, X509NameBuilder, X509Ref,
+    X509RevokedBuilder, X509,
+};
+
+use crate::crypto::FabricIdentity;
+use crate::fabric::{FabricNetworkClient, KaleidoFabricClient};
+use crate::gateway;
+use crate::protos::common::{
+    Block, BlockData, BlockHeader, BlockMetadata, BlockMetadataIndex, BlockchainInfo,
+    ChannelHeader, Envelope, Header, HeaderType, Payload, SignatureHeader,
+};
+use crate::protos::gateway::{
+    CommitStatusRequest, CommitStatusResponse, EndorseRequest, EndorseResponse, EvaluateRequest,
+    EvaluateResponse, SignedCommitStatusRequest, SubmitRequest, SubmitResponse,
+};
+use crate::protos::msp::SerializedIdentity;
+use crate::protos::orderer::{seek_position, SeekInfo};
+use crate::protos::peer::{
+    deliver_response, ChaincodeAction, ChaincodeActionPayload, ChaincodeEndorsedAction,
+    ChaincodeEvent, ChaincodeId, ChaincodeInvocationSpec, ChaincodeProposalPayload,
+    DeliverResponse, Proposal, ProposalResponse, ProposalResponsePayload, Response,
+    SignedProposal, Transaction, TransactionAction, TxValidationCode,
+};
+use prost::Message;
+use std::collections::{BTreeMap, HashMap};
+use tonic::codec::ProstCodec;
+use tonic::codegen::http;
+use tonic::Status;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// Description of a certificate to issue
+pub(crate) struct CertSpec<'a> {
+    pub common_name: &'a str,
+    pub organization: &'a str,
+    pub organizational_units: Vec<&'a str>,
+    pub ca: bool,
+    /// Validity window as day offsets from now
+    pub valid_from_days: i64,
+    pub valid_until_days: i64,
+    /// Leaf key usage; `false` issues a leaf without digitalSignature
+    pub digital_signature: bool,
+    pub dns_names: Vec<&'a str>,
+    /// Extra extensions as (OID, DER value)
+    pub extensions: Vec<(&'a str, Vec<u8>)>,
+}
+
+impl Default for CertSpec<'_> {
+    fn default() -> Self {
+        Self {
+            common_name: "user1",
+            organization: "Org1",
+            organizational_units: vec!["client"],
+            ca: false,
+            valid_from_days: -1,
+            valid_until_days: 365,
+            digital_signature: true,
+            dns_names: Vec::new(),
+            extensions: Vec::new(),
+        }
+    }
+}
+
+/// An issued certificate together with its private key
+pub(crate) struct TestCert {
+    pub cert: X509,
+    pub key: PKey<Private>,
+}
+
+impl TestCert {
+    /// Issue a self-signed root CA
+    pub fn root(common_name: &str) -> Self {
+        let key = new_key();
+        let cert = build(
+            &CertSpec {
+                common_name,
+                organizational_units: Vec::new(),
+                ca: true,
+                ..Default::default()
+            },
+            &key,
+            &key,
+            None,
+        );
+        Self { cert, key }
+    }
+
+    /// Issue a certificate signed by this one
+    pub fn issue(&self, spec: CertSpec<'_>) -> Self {
+        let key = new_key();
+        let cert = build(&spec, &key, &self.key, Some(&self.cert));
+        Self { cert, key }
+    }
+
+    /// Identity of `user1` in Org1MSP, issued by a fresh root CA
+    pub fn identity() -> FabricIdentity {
+        let root = Self::root("ca.org1.example.com");
+        let user = root.issue(CertSpec::default());
+        FabricIdentity::new(
+            user.key_pem(),
+            user.public_key_pem(),
+            user.cert_pem(),
+            root.cert_pem(),
+            "user1".to_string(),
+            "Org1".to_string(),
+            "Org1MSP".to_string(),
+        )
+    }
+
+    /// Issue a certificate for an externally held public key
+    pub fn issue_for_key<T: HasPublic>(&self, spec: CertSpec<'_>, key: &PKeyRef<T>) -> X509 {
+        build(&spec, key, &self.key, Some(&self.cert))
+    }
+
+    pub fn cert_pem(&self) -> String {
+        String::from_utf8(self.cert.to_pem().unwrap()).unwrap()
+    }
+
+    pub fn key_pem(&self) -> String {
+        String::from_utf8(self.key.private_key_to_pem_pkcs8().unwrap()).unwrap()
+    }
+
+    pub fn public_key_pem(&self) -> String {
+        String::from_utf8(self.key.public_key_to_pem().unwrap()).unwrap()
+    }
+
+    /// PEM CRL signed by this CA, valid for 30 days, revoking `revoked`
+    pub fn crl(&self, revoked: &[&TestCert]) -> String {
+        let mut builder = X509CrlBuilder::new().unwrap();
+        builder.set_issuer_name(self.cert.subject_name()).unwrap();
+        builder
+            .set_last_update(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_next_update(&Asn1Time::days_from_now(30).unwrap())
+            .unwrap();
+        let context = X509Builder::new().unwrap();
+        let authority_key_id = AuthorityKeyIdentifier::new()
+            .keyid(true)
+            .build(&context.x509v3_context(Some(&self.cert), None))
+            .unwrap();
+        builder.append_extension(authority_key_id).unwrap();
+        let number = CrlNumber::new(BigNum::from_u32(1).unwrap()).unwrap();
+        builder.append_extension(number.build().unwrap()).unwrap();
+        for cert in revoked {
+            let mut entry = X509RevokedBuilder::new().unwrap();
+            entry.set_serial_number(cert.cert.serial_number()).unwrap();
+            entry
+                .set_revocation_date(&Asn1Time::days_from_now(0).unwrap())
+                .unwrap();
+            builder.add_revoked(entry.build()).unwrap();
+        }
+        builder.sign(&self.key, MessageDigest::sha256()).unwrap();
+        String::from_utf8(builder.build().unwrap().to_pem().unwrap()).unwrap()
+    }
+}
+
+fn new_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+fn build<T: HasPublic>(
+    spec: &CertSpec<'_>,
+    key: &PKeyRef<T>,
+    signing_key: &PKeyRef<Private>,
+    issuer: Option<&X509Ref>,
+) -> X509 {
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(Nid::ORGANIZATIONNAME, spec.organization)
+        .unwrap();
+    for ou in &spec.organizational_units {
+        name.append_entry_by_nid(Nid::ORGANIZATIONALUNITNAME, ou)
+            .unwrap();
+    }
+    name.append_entry_by_nid(Nid::COMMONNAME, spec.common_name)
+        .unwrap();
+    let name = name.build();
+
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+    let serial = Asn1Integer::from_bn(&serial).unwrap();
+
+    let now = chrono::Utc::now().timestamp();
+    let not_before = Asn1Time::from_unix(now + spec.valid_from_days * DAY).unwrap();
+    let not_after = Asn1Time::from_unix(now + spec.valid_until_days * DAY).unwrap();
+
+    let mut builder = X509Builder::new().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_serial_number(&serial).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder
+        .set_issuer_name(issuer.map_or(&name, |i| i.subject_name()))
+        .unwrap();
+    builder.set_pubkey(key).unwrap();
+    builder.set_not_before(&not_before).unwrap();
+    builder.set_not_after(&not_after).unwrap();
+
+    if spec.ca {
+        builder
+            .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+            .unwrap();
+        builder
+            .append_extension(
+                KeyUsage::new()
+                    .critical()
+                    .key_cert_sign()
+                    .crl_sign()
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+    } else {
+        builder
+            .append_extension(BasicConstraints::new().critical().build().unwrap())
+            .unwrap();
+        let mut usage = KeyUsage::new();
+        usage.critical();
+        if spec.digital_signature {
+            usage.digital_signature();
+        } else {
+            usage.key_encipherment();
+        }
+        builder.append_extension(usage.build().unwrap()).unwrap();
+    }
+
+    let subject_key_id = SubjectKeyIdentifier::new()
+        .build(&builder.x509v3_context(issuer, None))
+        .unwrap();
+    builder.append_extension(subject_key_id).unwrap();
+    if let Some(issuer) = issuer {
+        let authority_key_id = AuthorityKeyIdentifier::new()
+            .keyid(true)
+            .build(&builder.x509v3_context(Some(issuer), None))
+            .unwrap();
+        builder.append_extension(authority_key_id).unwrap();
+    }
+
+    if !spec.dns_names.is_empty() {
+        let mut san = SubjectAlternativeName::new();
+        for dns in &spec.dns_names {
+            if dns.parse::<std::net::IpAddr>().is_ok() {
+                san.ip(dns);
+            } else {
+                san.dns(dns);
+            }
+        }
+        let san = san
+            .build(&builder.x509v3_context(issuer, None))
+            .unwrap();
+        builder.append_extension(san).unwrap();
+    }
+
+    for (oid, der) in &spec.extensions {
+        let oid = Asn1Object::from_str(oid).unwrap();
+        let value = Asn1OctetString::new_from_bytes(der).unwrap();
+        builder
+            .append_extension(X509Extension::new_from_der(&oid, false, &value).unwrap())
+            .unwrap();
+    }
+
+    builder.sign(signing_key, MessageDigest::sha256()).unwrap();
+    builder.build()
+}
+
+/// A request captured by [`MockServer`]
+#[derive(Debug, Clone)]
+pub(crate) struct MockRequest {
+    /// Path including any query string
+    pub path: String,
+    /// Headers keyed by lowercase name
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap_or(serde_json::Value::Null)
+    }
+}
+
+/// A canned response returned by [`MockServer`]
+pub(crate) struct MockResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            body: body.to_string(),
+        }
+    }
+}
+
+type Handler = dyn Fn(&MockRequest) -> MockResponse + Send + Sync;
+
+/// Minimal HTTP/1.1 server that answers each request through a closure
+pub(crate) struct MockServer {
+    pub url: String,
+    pub requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    pub async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&MockRequest) -> MockResponse + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let captured = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let captured = captured.clone();
+                tokio::spawn(serve_connection(stream, handler, captured));
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Kaleido client for this server, connected as `TestCert::identity()`
+    pub async fn connected_client(&self) -> KaleidoFabricClient {
+        let mut client = KaleidoFabricClient::from_kaleido_endpoint(&self.url, "");
+        client.connect(&TestCert::identity()).await.unwrap();
+        client
+    }
+}
+
+async fn serve_connection(
+    mut stream: TcpStream,
+    handler: Arc<Handler>,
+    captured: Arc<Mutex<Vec<MockRequest>>>,
+) -> Option<()> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let path = request_line.nth(1)?.to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length = headers
+        .get("content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = buffer[header_end..].to_vec();
+    while body.len() < length {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+
+    let request = MockRequest {
+        path,
+        headers,
+        body,
+    };
+    let response = handler(&request);
+    captured.lock().unwrap().push(request);
+
+    let reply = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.body.len(),
+        response.body
+    );
+    stream.write_all(reply.as_bytes()).await.ok()?;
+    stream.shutdown().await.ok()
+}
+
+/// Blocking HTTPS server answering every request with `200 {}`
+///
+/// Records the common name of each client certificate it accepts, so tests
+/// can check what was presented for mutual TLS.
+pub(crate) struct TlsServer {
+    pub url: String,
+    client_subjects: Arc<Mutex<Vec<String>>>,
+}
+
+impl TlsServer {
+    /// Serve `server` on localhost, optionally requiring client certificates
+    /// issued by `client_ca` and capping the protocol at `max_version`
+    pub fn start(
+        server: &TestCert,
+        client_ca: Option<&TestCert>,
+        max_version: Option<SslVersion>,
+    ) -> Self {
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor.set_private_key(&server.key).unwrap();
+        acceptor.set_certificate(&server.cert).unwrap();
+        acceptor.check_private_key().unwrap();
+        if let Some(ca) = client_ca {
+            acceptor.cert_store_mut().add_cert(ca.cert.clone()).unwrap();
+            acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        }
+        acceptor.set_max_proto_version(max_version).unwrap();
+        let acceptor = Arc::new(acceptor.build());
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client_subjects = Arc::new(Mutex::new(Vec::new()));
+        let subjects = client_subjects.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let acceptor = acceptor.clone();
+                let subjects = subjects.clone();
+                std::thread::spawn(move || {
+                    if let Ok(stream) = acceptor.accept(stream) {
+                        serve_tls(stream, &subjects);
+                    }
+                });
+            }
+        });
+
+        Self {
+            url: format!("https://localhost:{}", port),
+            client_subjects,
+        }
+    }
+
+    pub fn client_subjects(&self) -> Vec<String> {
+        self.client_subjects.lock().unwrap().clone()
+    }
+}
+
+fn serve_tls(mut stream: SslStream<std::net::TcpStream>, subjects: &Mutex<Vec<String>>) {
+    use std::io::{Read, Write};
+
+    if let Some(cert) = stream.ssl().peer_certificate() {
+        if let Some(cn) = cert.subject_name().entries_by_nid(Nid::COMMONNAME).next() {
+            subjects
+                .lock()
+                .unwrap()
+                .push(cn.data().to_string().unwrap());
+        }
+    }
+
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => return,
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+        }
+    };
+    let length = String::from_utf8_lossy(&buffer[..header_end])
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse::<usize>().ok())?
+        })
+        .unwrap_or(0);
+    while buffer.len() < header_end + length {
+        match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => return,
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+        }
+    }
+
+    let reply = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+    let _ = stream.write_all(reply.as_bytes());
+    let _ = stream.shutdown();
+}
+
+/// Chaincode invocation decoded by `MockGateway`
+#[derive(Debug, Clone)]
+pub(crate) struct ChaincodeCall {
+    pub transaction_id: String,
+    pub channel_id: String,
+    pub chaincode_id: String,
+    pub mspid: String,
+    pub function: String,
+    pub args: Vec<String>,
+    /// Transient map of the proposal, values as lossy UTF-8
+    pub transient: BTreeMap<String, String>,
+    /// Target or endorsing organizations named in the request
+    pub organizations: Vec<String>,
+}
+
+type ChaincodeHandler = dyn Fn(&ChaincodeCall) -> std::result::Result<Vec<u8>, String> + Send + Sync;
+
+/// In-process Fabric Gateway gRPC server
+///
+/// Serves the Gateway and Endorser services. Decodes proposals and verifies
+/// every signature against the creator's certificate. Chaincode calls,
+/// including system chaincode queries, are answered by a closure. Submitted
+/// transactions commit one per block, after a genesis block, with the
+/// configured validation code, and QSCC block queries and the Deliver
+/// service read that ledger.
+pub(crate) struct MockGateway {
+    pub url: String,
+    state: Arc<GatewayState>,
+}
+
+struct GatewayState {
+    handler: Box<ChaincodeHandler>,
+    calls: Mutex<Vec<ChaincodeCall>>,
+    committed: Mutex<Vec<CommittedTransaction>>,
+    /// Number of committed transactions, watched by Deliver streams
+    height: tokio::sync::watch::Sender<usize>,
+    validation_code: Mutex<TxValidationCode>,
+    /// Chaincode event name set by each function
+    events: Mutex<HashMap<String, String>>,
+}
+
+struct CommittedTransaction {
+    transaction_id: String,
+    code: TxValidationCode,
+    envelope: Envelope,
+}
+
+impl MockGateway {
+    pub async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&ChaincodeCall) -> std::result::Result<Vec<u8>, String> + Send + Sync + 'static,
+    {
+        let state = Arc::new(GatewayState {
+            handler: Box::new(handler),
+            calls: Mutex::new(Vec::new()),
+            committed: Mutex::new(Vec::new()),
+            height: tokio::sync::watch::channel(0).0,
+            validation_code: Mutex::new(TxValidationCode::Valid),
+            events: Mutex::new(HashMap::new()),
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(GatewayService(state.clone()))
+                .add_service(EndorserService(state.clone()))
+                .add_service(DeliverService(state.clone()))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        Self { url, state }
+    }
+
+    /// Evaluated and endorsed calls, in arrival order
+    pub fn calls(&self) -> Vec<ChaincodeCall> {
+        self.state.calls.lock().unwrap().clone()
+    }
+
+    /// IDs of submitted transactions, in commit order
+    pub fn submitted(&self) -> Vec<String> {
+        let committed = self.state.committed.lock().unwrap();
+        committed.iter().map(|tx| tx.transaction_id.clone()).collect()
+    }
+
+    /// Encoded envelope of the `index`th committed transaction
+    pub fn committed_envelope(&self, index: usize) -> Vec<u8> {
+        self.state.committed.lock().unwrap()[index].envelope.encode_to_vec()
+    }
+
+    /// Validation code for transactions submitted from now on
+    pub fn set_validation_code(&self, code: TxValidationCode) {
+        *self.state.validation_code.lock().unwrap() = code;
+    }
+
+    /// Make endorsements of `function` set a chaincode event named
+    /// `event_name`, with the chaincode's response as payload
+    pub fn emit_event(&self, function: &str, event_name: &str) {
+        let mut events = self.state.events.lock().unwrap();
+        events.insert(function.to_string(), event_name.to_string());
+    }
+}
+
+impl GatewayState {
+    fn evaluate(&self, request: EvaluateRequest) -> std::result::Result<EvaluateResponse, Status> {
+        let signed = request.proposed_transaction.unwrap_or_default();
+        let (mut call, _) = decode_proposal(&signed)?;
+        call.organizations = request.target_organizations;
+        let payload = self.invoke(call).map_err(Status::unknown)?;
+        Ok(EvaluateResponse {
+            result: Some(Response {
+                status: 200,
+                payload,
+                ..Default::default()
+            }),
+        })
+    }
+
+    fn endorse(&self, request: EndorseRequest) -> std::result::Result<EndorseResponse, Status> {
+        let signed = request.proposed_transaction.unwrap_or_default();
+        let (mut call, proposal) = decode_proposal(&signed)?;
+        call.organizations = request.endorsing_organizations;
+        let chaincode_id = Some(ChaincodeId {
+            name: call.chaincode_id.clone(),
+            ..Default::default()
+        });
+        let event_name = self.events.lock().unwrap().get(&call.function).cloned();
+        let event = event_name.map(|event_name| ChaincodeEvent {
+            chaincode_id: call.chaincode_id.clone(),
+            tx_id: call.transaction_id.clone(),
+            event_name,
+            payload: Vec::new(),
+        });
+        let payload = self.invoke(call).map_err(Status::aborted)?;
+
+        let action = ChaincodeAction {
+            events: event
+                .map(|event| ChaincodeEvent {
+                    payload: payload.clone(),
+                    ..event
+                }.encode_to_vec())
+                .unwrap_or_default(),
+            response: Some(Response {
+                status: 200,
+                payload,
+                ..Default::default()
+            }),
+            chaincode_id,
+            ..Default::default()
+        };
+        let response_payload = ProposalResponsePayload {
+            proposal_hash: openssl::sha::sha256(&signed.proposal_bytes).to_vec(),
+            extension: action.encode_to_vec(),
+        };
+        // Transient data stays out of the transaction
+        let proposal_payload = ChaincodeProposalPayload {
+            transient_map: HashMap::new(),
+            ..ChaincodeProposalPayload::decode(proposal.payload.as_slice()).unwrap()
+        };
+        let action_payload = ChaincodeActionPayload {
+            chaincode_proposal_payload: proposal_payload.encode_to_vec(),
+            action: Some(ChaincodeEndorsedAction {
+                proposal_response_payload: response_payload.encode_to_vec(),
+                endorsements: Vec::new(),
+            }),
+        };
+        let header = Header::decode(proposal.header.as_slice()).unwrap();
+        let transaction = Transaction {
+            actions: vec![TransactionAction {
+                header: header.signature_header.clone(),
+                payload: action_payload.encode_to_vec(),
+            }],
+        };
+        let payload = Payload {
+            header: Some(header),
+            data: transaction.encode_to_vec(),
+        };
+        Ok(EndorseResponse {
+            prepared_transaction: Some(Envelope {
+                payload: payload.encode_to_vec(),
+                signature: Vec::new(),
+            }),
+        })
+    }
+
+    fn submit(&self, request: SubmitRequest) -> std::result::Result<SubmitResponse, Status> {
+        let envelope = request.prepared_transaction.unwrap_or_default();
+        let payload = Payload::decode(envelope.payload.as_slice())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let header = payload.header.unwrap_or_default();
+        let signature_header = SignatureHeader::decode(header.signature_header.as_slice())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        verify_creator(&signature_header.creator, &envelope.payload, &envelope.signature)?;
+
+        let code = *self.validation_code.lock().unwrap();
+        let mut committed = self.committed.lock().unwrap();
+        committed.push(CommittedTransaction {
+            transaction_id: request.transaction_id,
+            code,
+            envelope,
+        });
+        self.height.send_replace(committed.len());
+        Ok(SubmitResponse {})
+    }
+
+    fn commit_status(
+        &self,
+        signed: SignedCommitStatusRequest,
+    ) -> std::result::Result<CommitStatusResponse, Status> {
+        let request = CommitStatusRequest::decode(signed.request.as_slice())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        verify_creator(&request.identity, &signed.request, &signed.signature)?;
+
+        let committed = self.committed.lock().unwrap();
+        committed
+            .iter()
+            .position(|tx| tx.transaction_id == request.transaction_id)
+            .map(|index| CommitStatusResponse {
+                result: committed[index].code as i32,
+                block_number: index as u64 + 1,
+            })
+            .ok_or_else(|| Status::not_found(request.transaction_id))
+    }
+
+    fn process_proposal(
+        &self,
+        signed: SignedProposal,
+    ) -> std::result::Result<ProposalResponse, Status> {
+        let (call, _) = decode_proposal(&signed)?;
+        let result = match call.chaincode_id.as_str() {
+            "qscc" => self.qscc(&call),
+            _ => self.invoke(call),
+        };
+        let response = match result {
+            Ok(payload) => Response {
+                status: 200,
+                payload,
+                ..Default::default()
+            },
+            Err(message) => Response {
+                status: 500,
+                message,
+                ..Default::default()
+            },
+        };
+        Ok(ProposalResponse {
+            response: Some(response),
+            ..Default::default()
+        })
+    }
+
+    /// Ledger queries answered from the committed transactions
+    fn qscc(&self, call: &ChaincodeCall) -> std::result::Result<Vec<u8>, String> {
+        match call.function.as_str() {
+            "GetChainInfo" => Ok(BlockchainInfo {
+                height: self.committed.lock().unwrap().len() as u64 + 1,
+                ..Default::default()
+            }
+            .encode_to_vec()),
+            "GetBlockByNumber" => {
+                let number = call.args[1].parse().map_err(|_| "bad block number")?;
+                let block = self.block(&call.args[0], number).ok_or("block not found")?;
+                Ok(block.encode_to_vec())
+            }
+            other => Err(format!("unknown qscc function {}", other)),
+        }
+    }
+
+    /// Block `number` of the ledger, if committed
+    fn block(&self, channel_id: &str, number: u64) -> Option<Block> {
+        let (envelope, filter) = match number {
+            0 => (genesis_envelope(channel_id), vec![TxValidationCode::Valid as u8]),
+            n => {
+                let committed = self.committed.lock().unwrap();
+                let tx = committed.get(n as usize - 1)?;
+                (tx.envelope.clone(), vec![tx.code as u8])
+            }
+        };
+        let mut metadata = vec![Vec::new(); 5];
+        metadata[BlockMetadataIndex::TransactionsFilter as usize] = filter;
+        Some(Block {
+            header: Some(BlockHeader {
+                number,
+                ..Default::default()
+            }),
+            data: Some(BlockData {
+                data: vec![envelope.encode_to_vec()],
+            }),
+            metadata: Some(BlockMetadata { metadata }),
+        })
+    }
+
+    /// Stream blocks from the requested start, waiting for new commits
+    /// until the client goes away
+    fn deliver(
+        self: Arc<Self>,
+        envelope: Envelope,
+    ) -> std::result::Result<
+        tokio_stream::wrappers::ReceiverStream<std::result::Result<DeliverResponse, Status>>,
+        Status,
+    > {
+        let invalid = |e: prost::DecodeError| Status::invalid_argument(e.to_string());
+        let payload = Payload::decode(envelope.payload.as_slice()).map_err(invalid)?;
+        let header = payload.header.unwrap_or_default();
+        let channel_header = ChannelHeader::decode(header.channel_header.as_slice()).map_err(invalid)?;
+        let signature_header =
+            SignatureHeader::decode(header.signature_header.as_slice()).map_err(invalid)?;
+        verify_creator(&signature_header.creator, &envelope.payload, &envelope.signature)?;
+        let seek = SeekInfo::decode(payload.data.as_slice()).map_err(invalid)?;
+        let mut next = match seek.start.and_then(|start| start.r#type) {
+            Some(seek_position::Type::Specified(specified)) => specified.number,
+            _ => 0,
+        };
+
+        let (sender, receiver) = tokio::sync::mpsc::channel(4);
+        tokio::spawn(async move {
+            let mut height = self.height.subscribe();
+            loop {
+                match self.block(&channel_header.channel_id, next) {
+                    Some(block) => {
+                        let response = DeliverResponse {
+                            r#type: Some(deliver_response::Type::Block(block)),
+                        };
+                        if sender.send(Ok(response)).await.is_err() {
+                            return;
+                        }
+                        next += 1;
+                    }
+                    None => tokio::select! {
+                        _ = sender.closed() => return,
+                        _ = height.changed() => {}
+                    },
+                }
+            }
+        });
+        Ok(tokio_stream::wrappers::ReceiverStream::new(receiver))
+    }
+
+    fn invoke(&self, call: ChaincodeCall) -> std::result::Result<Vec<u8>, String> {
+        let result = (self.handler)(&call);
+        self.calls.lock().unwrap().push(call);
+        result
+    }
+}
+
+/// Configuration transaction standing in for a channel's genesis block
+fn genesis_envelope(channel_id: &str) -> Envelope {
+    let header = ChannelHeader {
+        r#type: HeaderType::Config as i32,
+        channel_id: channel_id.to_string(),
+        ..Default::default()
+    };
+    Envelope {
+        payload: Payload {
+            header: Some(Header {
+                channel_header: header.encode_to_vec(),
+                signature_header: Vec::new(),
+            }),
+            data: Vec::new(),
+        }
+        .encode_to_vec(),
+        signature: Vec::new(),
+    }
+}
+
+/// Decode a signed proposal after checking the creator's signature
+fn decode_proposal(signed: &SignedProposal) -> std::result::Result<(ChaincodeCall, Proposal), Status> {
+    let invalid = |e: prost::DecodeError| Status::invalid_argument(e.to_string());
+    let proposal = Proposal::decode(signed.proposal_bytes.as_slice()).map_err(invalid)?;
+    let header = Header::decode(proposal.header.as_slice()).map_err(invalid)?;
+    let channel_header = ChannelHeader::decode(header.channel_header.as_slice()).map_err(invalid)?;
+    let signature_header =
+        SignatureHeader::decode(header.signature_header.as_slice()).map_err(invalid)?;
+    let mspid = verify_creator(
+        &signature_header.creator,
+        &signed.proposal_bytes,
+        &signed.signature,
+    )?;
+
+    let payload = ChaincodeProposalPayload::decode(proposal.payload.as_slice()).map_err(invalid)?;
+    let transient = payload
+        .transient_map
+        .iter()
+        .map(|(key, value)| (key.clone(), String::from_utf8_lossy(value).into_owned()))
+        .collect();
+    let spec = ChaincodeInvocationSpec::decode(payload.input.as_slice())
+        .map_err(invalid)?
+        .chaincode_spec
+        .unwrap_or_default();
+    let mut args = spec
+        .input
+        .unwrap_or_default()
+        .args
+        .into_iter()
+        .map(|arg| String::from_utf8_lossy(&arg).into_owned());
+    let call = ChaincodeCall {
+        transaction_id: channel_header.tx_id,
+        channel_id: channel_header.channel_id,
+        chaincode_id: spec.chaincode_id.unwrap_or_default().name,
+        mspid,
+        function: args.next().unwrap_or_default(),
+        args: args.collect(),
+        transient,
+        organizations: Vec::new(),
+    };
+    Ok((call, proposal))
+}
+
+/// Check `signature` over `message` with the key of a serialized identity,
+/// returning its MSP ID
+fn verify_creator(
+    creator: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> std::result::Result<String, Status> {
+    let identity = SerializedIdentity::decode(creator)
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+    let public_key = X509::from_pem(&identity.id_bytes)
+        .and_then(|cert| cert.public_key())
+        .and_then(|key| key.public_key_to_pem())
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+    let public_key = String::from_utf8(public_key).unwrap();
+    match crate::crypto::CryptoManager::verify(&public_key, message, signature) {
+        Ok(true) => Ok(identity.mspid),
+        _ => Err(Status::permission_denied("Invalid signature")),
+    }
+}
+
+/// gRPC service of the mock peer, dispatching on the method path
+macro_rules! mock_service {
+    ($service:ident, $name:literal) => {
+        #[derive(Clone)]
+        struct $service(Arc<GatewayState>);
+
+        impl tonic::server::NamedService for $service {
+            const NAME: &'static str = $name;
+        }
+
+        impl tonic::codegen::Service<http::Request<tonic::transport::Body>> for $service {
+            type Response = http::Response<tonic::body::BoxBody>;
+            type Error = std::convert::Infallible;
+            type Future = tonic::codegen::BoxFuture<Self::Response, Self::Error>;
+
+            fn poll_ready(
+                &mut self,
+                _cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<std::result::Result<(), Self::Error>> {
+                std::task::Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, request: http::Request<tonic::transport::Body>) -> Self::Future {
+                route(self.0.clone(), request)
+            }
+        }
+    };
+}
+
+mock_service!(GatewayService, "gateway.Gateway");
+mock_service!(EndorserService, "protos.Endorser");
+mock_service!(DeliverService, "protos.Deliver");
+
+fn route(
+    state: Arc<GatewayState>,
+    request: http::Request<tonic::transport::Body>,
+) -> tonic::codegen::BoxFuture<http::Response<tonic::body::BoxBody>, std::convert::Infallible> {
+    match request.uri().path() {
+        gateway::EVALUATE => serve_unary(request, move |r| state.evaluate(r)),
+        gateway::ENDORSE => serve_unary(request, move |r| state.endorse(r)),
+        gateway::SUBMIT => serve_unary(request, move |r| state.submit(r)),
+        gateway::COMMIT_STATUS => serve_unary(request, move |r| state.commit_status(r)),
+        gateway::PROCESS_PROPOSAL => serve_unary(request, move |r| state.process_proposal(r)),
+        gateway::DELIVER => serve_streaming(request, move |r| state.clone().deliver(r)),
+        _ => Box::pin(async { Ok(Status::unimplemented("").to_http()) }),
+    }
+}
+
+fn serve_unary<Req, Resp, F>(
+    request: http::Request<tonic::transport::Body>,
+    handle: F,
+) -> tonic::codegen::BoxFuture<http::Response<tonic::body::BoxBody>, std::convert::Infallible>
+where
+    Req: Message + Default + Send + 'static,
+    Resp: Message + Send + 'static,
+    F: Fn(Req) -> std::result::Result<Resp, Status> + Send + Sync + 'static,
+{
+    let service = tower::service_fn(move |request: tonic::Request<Req>| {
+        let response = handle(request.into_inner()).map(tonic::Response::new);
+        async move { response }
+    });
+    Box::pin(async move {
+        let mut grpc = tonic::server::Grpc::new(ProstCodec::<Resp, Req>::default());
+        Ok(grpc.unary(service, request).await)
+    })
+}
+
+/// Answer a streaming call from its first request message
+fn serve_streaming<Req, Resp, S, F>(
+    request: http::Request<tonic::transport::Body>,
+    handle: F,
+) -> tonic::codegen::BoxFuture<http::Response<tonic::body::BoxBody>, std::convert::Infallible>
+where
+    Req: Message + Default + Send + 'static,
+    Resp: Message + Send + 'static,
+    S: tokio_stream::Stream<Item = std::result::Result<Resp, Status>> + Send + 'static,
+    F: Fn(Req) -> std::result::Result<S, Status> + Send + Sync + 'static,
+{
+    let handle = Arc::new(handle);
+    let service = tower::service_fn(move |request: tonic::Request<tonic::Streaming<Req>>| {
+        let handle = handle.clone();
+        async move {
+            let first = request
+                .into_inner()
+                .message()
+                .await?
+                .ok_or_else(|| Status::invalid_argument("empty request stream"))?;
+            handle(first).map(tonic::Response::new)
+        }
+    });
+    Box::pin(async move {
+        let mut grpc = tonic::server::Grpc::new(ProstCodec::<Resp, Req>::default());
+        Ok(grpc.streaming(service, request).await)
+    })
+}
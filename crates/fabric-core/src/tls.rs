@@ -0,0 +1,429 @@
+/// TLS configuration for gateway connections
+use crate::crypto::FabricIdentity;
+use crate::error::{FabricCoreError, Result};
+use crate::fabric::FabricNetworkConfig;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use openssl::pkey::PKey;
+use openssl::sha::sha256;
+use openssl::x509::X509;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// TLS settings beyond the CA bundle in `FabricNetworkConfig::tls_cert_path`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// Client certificate presented for mutual TLS
+    #[serde(default)]
+    pub client_auth: TlsClientAuth,
+    /// Accepted SHA-256 digests of the gateway's public key (SPKI), base64
+    /// encoded with an optional `sha256/` prefix
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spki_pins: Vec<String>,
+    /// Oldest protocol version to negotiate
+    #[serde(default)]
+    pub min_version: TlsVersion,
+}
+
+impl TlsConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Source of the client certificate for mutual TLS
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsClientAuth {
+    /// Do not present a client certificate
+    #[default]
+    None,
+    /// Present the enrollment certificate and key of the connecting identity
+    Identity,
+    /// Present a separate TLS certificate and PEM private key
+    Files { cert_path: String, key_path: String },
+}
+
+/// TLS protocol versions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TlsVersion {
+    #[default]
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+/// Compute the `sha256/` SPKI pin of the first certificate in a PEM string
+pub fn spki_pin(cert_pem: &str) -> Result<String> {
+    let cert = X509::from_pem(cert_pem.as_bytes())
+        .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))?;
+    Ok(format!("sha256/{}", BASE64.encode(spki_digest(&cert)?)))
+}
+
+/// Build the HTTP client for a network, applying its TLS settings
+///
+/// Without a CA bundle or `TlsConfig` settings this is the default reqwest
+/// client. Otherwise TLS is handled by rustls, trusting only the bundle
+/// when one is configured and the system roots when not.
+pub(crate) fn http_client(
+    config: &FabricNetworkConfig,
+    identity: &FabricIdentity,
+) -> Result<reqwest::Client> {
+    if config.tls_cert_path.is_none() && config.tls.is_default() {
+        return Ok(reqwest::Client::new());
+    }
+    let tls = client_config(config, identity)?;
+    reqwest::Client::builder()
+        .use_preconfigured_tls(tls)
+        .build()
+        .map_err(|e| tls_err(e.to_string()))
+}
+
+pub(crate) fn client_config(
+    config: &FabricNetworkConfig,
+    identity: &FabricIdentity,
+) -> Result<ClientConfig> {
+    let versions: &[&rustls::SupportedProtocolVersion] = match config.tls.min_version {
+        TlsVersion::Tls12 => &[&rustls::version::TLS13, &rustls::version::TLS12],
+        TlsVersion::Tls13 => &[&rustls::version::TLS13],
+    };
+    let builder = ClientConfig::builder()
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(versions)
+        .map_err(|e| tls_err(e.to_string()))?;
+
+    let roots = root_store(config.tls_cert_path.as_deref())?;
+    let pins = config
+        .tls
+        .spki_pins
+        .iter()
+        .map(|pin| parse_pin(pin))
+        .collect::<Result<Vec<_>>>()?;
+    let builder = builder.with_custom_certificate_verifier(Arc::new(PinnedVerifier {
+        inner: WebPkiVerifier::new(roots, None),
+        pins,
+    }));
+
+    let (chain_pem, key_pem) = match &config.tls.client_auth {
+        TlsClientAuth::None => return Ok(builder.with_no_client_auth()),
+        TlsClientAuth::Identity => {
+            if identity.private_key.is_empty() {
+                return Err(tls_err(
+                    "Identity private key is not available for mutual TLS",
+                ));
+            }
+            (
+                identity.certificate.clone(),
+                identity.private_key.expose_secret().clone(),
+            )
+        }
+        TlsClientAuth::Files {
+            cert_path,
+            key_path,
+        } => (
+            std::fs::read_to_string(cert_path)?,
+            std::fs::read_to_string(key_path)?,
+        ),
+    };
+    let chain = pem_certificates(&chain_pem)?;
+    let key = PKey::private_key_from_pem(key_pem.as_bytes())
+        .and_then(|key| key.private_key_to_pkcs8())
+        .map_err(|e| FabricCoreError::InvalidPrivateKey(e.to_string()))?;
+    builder
+        .with_client_auth_cert(chain, PrivateKey(key))
+        .map_err(|e| tls_err(e.to_string()))
+}
+
+/// Trust the PEM bundle at `path`, or the platform's roots without one
+fn root_store(path: Option<&str>) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    match path {
+        Some(path) => {
+            for cert in pem_certificates(&std::fs::read_to_string(path)?)? {
+                roots.add(&cert).map_err(|e| tls_err(e.to_string()))?;
+            }
+        }
+        None => {
+            let native = rustls_native_certs::load_native_certs()?;
+            let native: Vec<Vec<u8>> = native.into_iter().map(|cert| cert.0).collect();
+            roots.add_parsable_certificates(&native);
+        }
+    }
+    Ok(roots)
+}
+
+fn pem_certificates(pem: &str) -> Result<Vec<Certificate>> {
+    let certs = X509::stack_from_pem(pem.as_bytes())
+        .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))?;
+    if certs.is_empty() {
+        return Err(FabricCoreError::InvalidCertificate(
+            "No certificate found in PEM data".to_string(),
+        ));
+    }
+    certs
+        .iter()
+        .map(|cert| {
+            cert.to_der()
+                .map(Certificate)
+                .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))
+        })
+        .collect()
+}
+
+fn parse_pin(pin: &str) -> Result<[u8; 32]> {
+    let encoded = pin.trim().trim_start_matches("sha256/");
+    BASE64
+        .decode(encoded)
+        .ok()
+        .and_then(|digest| digest.try_into().ok())
+        .ok_or_else(|| tls_err(format!("Invalid SPKI pin: {}", pin)))
+}
+
+fn spki_digest(cert: &X509) -> Result<[u8; 32]> {
+    let spki = cert
+        .public_key()
+        .and_then(|key| key.public_key_to_der())
+        .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))?;
+    Ok(sha256(&spki))
+}
+
+/// Chain verification followed by a check of the leaf's public key when
+/// any pins are configured
+struct PinnedVerifier {
+    inner: WebPkiVerifier,
+    pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+        if self.pins.is_empty() {
+            return Ok(verified);
+        }
+        let digest = X509::from_der(&end_entity.0)
+            .map_err(|e| FabricCoreError::InvalidCertificate(e.to_string()))
+            .and_then(|cert| spki_digest(&cert))
+            .map_err(|e| rustls::Error::General(e.to_string()))?;
+        if self.pins.contains(&digest) {
+            Ok(verified)
+        } else {
+            Err(rustls::Error::General(
+                "Server public key does not match any pinned key".to_string(),
+            ))
+        }
+    }
+}
+
+fn tls_err(message: impl std::fmt::Display) -> FabricCoreError {
+    FabricCoreError::ConfigError(format!("TLS: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fabric::{FabricNetworkClient, KaleidoFabricClient};
+    use crate::test_util::{CertSpec, TestCert, TlsServer};
+    use openssl::ssl::SslVersion;
+
+    struct Pki {
+        root: TestCert,
+        server: TestCert,
+        client: TestCert,
+        root_path: String,
+    }
+
+    fn pki(name: &str) -> Pki {
+        let root = TestCert::root("tlsca.org1.example.com");
+        let server = root.issue(CertSpec {
+            common_name: "gateway",
+            dns_names: vec!["localhost"],
+            ..Default::default()
+        });
+        let client = root.issue(CertSpec::default());
+        let root_path = std::env::temp_dir()
+            .join(format!("fabric-tls-{}-{}.pem", name, uuid::Uuid::new_v4()))
+            .display()
+            .to_string();
+        std::fs::write(&root_path, root.cert_pem()).unwrap();
+        Pki {
+            root,
+            server,
+            client,
+            root_path,
+        }
+    }
+
+    fn identity(pki: &Pki) -> FabricIdentity {
+        FabricIdentity::new(
+            pki.client.key_pem(),
+            pki.client.public_key_pem(),
+            pki.client.cert_pem(),
+            pki.root.cert_pem(),
+            "user1".to_string(),
+            "Org1".to_string(),
+            "Org1MSP".to_string(),
+        )
+    }
+
+    /// Connect with the given TLS settings and issue one query
+    async fn query(
+        server: &TlsServer,
+        tls_cert_path: Option<&str>,
+        tls: TlsConfig,
+        identity: &FabricIdentity,
+    ) -> Result<serde_json::Value> {
+        let mut client = KaleidoFabricClient::from_kaleido_endpoint(&server.url, "");
+        let mut config = client.config().clone();
+        config.tls_cert_path = tls_cert_path.map(str::to_string);
+        config.tls = tls;
+        client = KaleidoFabricClient::new(config);
+        client.connect(identity).await?;
+        client
+            .query_chaincode("movies", "movie-chaincode", "queryAll", Vec::new())
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_custom_ca_and_spki_pinning() {
+        let pki = pki("pinning");
+        let identity = identity(&pki);
+        let server = TlsServer::start(&pki.server, None, None);
+        let ca = Some(pki.root_path.as_str());
+
+        query(&server, ca, TlsConfig::default(), &identity)
+            .await
+            .unwrap();
+        // The test CA is not in the system roots
+        assert!(query(&server, None, TlsConfig::default(), &identity)
+            .await
+            .is_err());
+
+        let pinned = TlsConfig {
+            spki_pins: vec![spki_pin(&pki.server.cert_pem()).unwrap()],
+            ..Default::default()
+        };
+        query(&server, ca, pinned, &identity).await.unwrap();
+        let wrong_pin = TlsConfig {
+            spki_pins: vec![spki_pin(&pki.client.cert_pem()).unwrap()],
+            ..Default::default()
+        };
+        assert!(query(&server, ca, wrong_pin, &identity).await.is_err());
+
+        std::fs::remove_file(&pki.root_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_presents_identity_certificate() {
+        let pki = pki("mtls");
+        let identity = identity(&pki);
+        let server = TlsServer::start(&pki.server, Some(&pki.root), None);
+        let ca = Some(pki.root_path.as_str());
+
+        assert!(query(&server, ca, TlsConfig::default(), &identity)
+            .await
+            .is_err());
+
+        let mtls = TlsConfig {
+            client_auth: TlsClientAuth::Identity,
+            ..Default::default()
+        };
+        query(&server, ca, mtls, &identity).await.unwrap();
+        assert_eq!(server.client_subjects(), vec!["user1".to_string()]);
+
+        // A client certificate from another CA is refused by the server
+        let foreign = TestCert::root("ca.org2.example.com").issue(CertSpec::default());
+        let dir = std::env::temp_dir();
+        let cert_path = dir.join(format!("fabric-tls-{}.pem", uuid::Uuid::new_v4()));
+        let key_path = dir.join(format!("fabric-tls-{}.key", uuid::Uuid::new_v4()));
+        std::fs::write(&cert_path, foreign.cert_pem()).unwrap();
+        std::fs::write(&key_path, foreign.key_pem()).unwrap();
+        let files = TlsConfig {
+            client_auth: TlsClientAuth::Files {
+                cert_path: cert_path.display().to_string(),
+                key_path: key_path.display().to_string(),
+            },
+            ..Default::default()
+        };
+        assert!(query(&server, ca, files, &identity).await.is_err());
+
+        for path in [cert_path, key_path, pki.root_path.clone().into()] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_swapped_identity_presents_new_client_certificate() {
+        let pki = pki("mtls-swap");
+        let server = TlsServer::start(&pki.server, Some(&pki.root), None);
+        let mut config = KaleidoFabricClient::from_kaleido_endpoint(&server.url, "")
+            .config()
+            .clone();
+        config.tls_cert_path = Some(pki.root_path.clone());
+        config.tls.client_auth = TlsClientAuth::Identity;
+        let mut client = KaleidoFabricClient::new(config);
+        client.connect(&identity(&pki)).await.unwrap();
+        client
+            .query_chaincode("movies", "movie-chaincode", "queryAll", Vec::new())
+            .await
+            .unwrap();
+
+        // As after reenrollment
+        let renewed = pki.root.issue(CertSpec {
+            common_name: "user2",
+            ..Default::default()
+        });
+        let mut swapped = identity(&pki);
+        swapped.private_key = renewed.key_pem().into();
+        swapped.public_key = renewed.public_key_pem();
+        swapped.certificate = renewed.cert_pem();
+        client.swap_identity(swapped).unwrap();
+        client
+            .query_chaincode("movies", "movie-chaincode", "queryAll", Vec::new())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server.client_subjects(),
+            vec!["user1".to_string(), "user2".to_string()]
+        );
+        std::fs::remove_file(&pki.root_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_minimum_tls_version() {
+        let pki = pki("version");
+        let identity = identity(&pki);
+        let server = TlsServer::start(&pki.server, None, Some(SslVersion::TLS1_2));
+        let ca = Some(pki.root_path.as_str());
+
+        query(&server, ca, TlsConfig::default(), &identity)
+            .await
+            .unwrap();
+        let tls13 = TlsConfig {
+            min_version: TlsVersion::Tls13,
+            ..Default::default()
+        };
+        assert!(query(&server, ca, tls13, &identity).await.is_err());
+
+        std::fs::remove_file(&pki.root_path).unwrap();
+    }
+}
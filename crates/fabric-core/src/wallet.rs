@@ -0,0 +1,409 @@
+/// Identity wallets compatible with the Fabric Node and Go SDKs
+use crate::crypto::{CryptoManager, FabricIdentity};
+use crate::error::{FabricCoreError, Result};
+use crate::keystore::{write_atomic, EncryptedIdentity, IdentityFileFormat};
+use crate::secret::{expose_secret, SecretString};
+use openssl::nid::Nid;
+use openssl::x509::X509;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Identity type written by the Fabric SDKs for X.509 identities
+pub const X509_IDENTITY_TYPE: &str = "X.509";
+const WALLET_ENTRY_VERSION: u32 = 1;
+const WALLET_FILE_EXTENSION: &str = "id";
+
+/// Storage for identities addressed by label
+pub trait Wallet: Send + Sync {
+    /// Store an identity, replacing any existing one with the same label
+    fn put(&self, label: &str, identity: &FabricIdentity) -> Result<()>;
+
+    /// Fetch an identity by label
+    fn get(&self, label: &str) -> Result<Option<FabricIdentity>>;
+
+    /// Delete an identity; removing a missing label is not an error
+    fn remove(&self, label: &str) -> Result<()>;
+
+    /// List stored labels in sorted order
+    fn list(&self) -> Result<Vec<String>>;
+
+    /// Fetch an identity, failing if the label is unknown
+    fn require(&self, label: &str) -> Result<FabricIdentity> {
+        self.get(label)?.ok_or_else(|| {
+            FabricCoreError::KeyManagementError(format!("Identity not found in wallet: {}", label))
+        })
+    }
+}
+
+/// Credentials section of an SDK wallet entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct X509Credentials {
+    pub certificate: String,
+    #[serde(rename = "privateKey", serialize_with = "expose_secret")]
+    pub private_key: SecretString,
+}
+
+/// X.509 wallet entry as written by the Fabric Node and Go SDKs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct X509WalletEntry {
+    pub credentials: X509Credentials,
+    #[serde(rename = "mspId")]
+    pub msp_id: String,
+    #[serde(rename = "type")]
+    pub identity_type: String,
+    pub version: u32,
+}
+
+impl X509WalletEntry {
+    pub fn from_identity(identity: &FabricIdentity) -> Self {
+        Self {
+            credentials: X509Credentials {
+                certificate: identity.certificate.clone(),
+                private_key: identity.private_key.clone(),
+            },
+            msp_id: identity.mspid.clone(),
+            identity_type: X509_IDENTITY_TYPE.to_string(),
+            version: WALLET_ENTRY_VERSION,
+        }
+    }
+
+    /// Rebuild an identity, taking user and organization from the certificate
+    ///
+    /// SDK wallets do not store the CA certificate, so it is left empty.
+    pub fn into_identity(self, label: &str) -> Result<FabricIdentity> {
+        if self.identity_type != X509_IDENTITY_TYPE {
+            return Err(FabricCoreError::KeyManagementError(format!(
+                "Unsupported wallet identity type: {}",
+                self.identity_type
+            )));
+        }
+
+        let public_key = CryptoManager::public_key_from_private(self.credentials.private_key.expose_secret())?;
+        let (user_id, org_name) = match X509::from_pem(self.credentials.certificate.as_bytes()) {
+            Ok(cert) => (
+                first_entry(&cert, Nid::COMMONNAME).unwrap_or_else(|| label.to_string()),
+                first_entry(&cert, Nid::ORGANIZATIONNAME).unwrap_or_default(),
+            ),
+            Err(_) => (label.to_string(), String::new()),
+        };
+
+        Ok(FabricIdentity::new(
+            self.credentials.private_key,
+            public_key,
+            self.credentials.certificate,
+            String::new(),
+            user_id,
+            org_name,
+            self.msp_id,
+        ))
+    }
+}
+
+/// Wallet storing one `<label>.id` file per identity
+///
+/// Entries use the SDK JSON format. With a passphrase, new entries are
+/// written in the encrypted identity format instead and both formats are
+/// readable.
+pub struct FileSystemWallet {
+    dir: PathBuf,
+    passphrase: Option<String>,
+}
+
+impl FileSystemWallet {
+    /// Open (and create if needed) a wallet directory
+    pub fn new(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            passphrase: None,
+        })
+    }
+
+    /// A view of the same directory that encrypts new entries and unlocks
+    /// encrypted ones with this passphrase
+    pub fn with_passphrase(&self, passphrase: &str) -> Self {
+        Self {
+            dir: self.dir.clone(),
+            passphrase: Some(passphrase.to_string()),
+        }
+    }
+
+    /// Default wallet location: `$FABRIC_WALLET`, else `~/.fabric/wallet`
+    pub fn default_dir() -> PathBuf {
+        if let Ok(dir) = std::env::var("FABRIC_WALLET") {
+            return PathBuf::from(dir);
+        }
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .unwrap_or_else(|_| ".".to_string());
+        PathBuf::from(home).join(".fabric").join("wallet")
+    }
+
+    /// Get the wallet directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether the stored entry for a label is encrypted
+    pub fn is_encrypted(&self, label: &str) -> Result<bool> {
+        let path = self.entry_path(label)?;
+        Ok(IdentityFileFormat::detect_file(&path)? == IdentityFileFormat::Encrypted)
+    }
+
+    fn entry_path(&self, label: &str) -> Result<PathBuf> {
+        validate_label(label)?;
+        Ok(self
+            .dir
+            .join(format!("{}.{}", label, WALLET_FILE_EXTENSION)))
+    }
+}
+
+impl Wallet for FileSystemWallet {
+    fn put(&self, label: &str, identity: &FabricIdentity) -> Result<()> {
+        let path = self.entry_path(label)?;
+        match &self.passphrase {
+            Some(passphrase) => identity.save_encrypted(&path, passphrase)?,
+            None => {
+                let entry = X509WalletEntry::from_identity(identity);
+                write_atomic(&path, serde_json::to_string(&entry)?.as_bytes())?;
+            }
+        }
+        tracing::debug!("Stored identity {} in {:?}", label, self.dir);
+        Ok(())
+    }
+
+    fn get(&self, label: &str) -> Result<Option<FabricIdentity>> {
+        let path = self.entry_path(label)?;
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let json = std::fs::read_to_string(&path)?;
+
+        match IdentityFileFormat::detect(&json)? {
+            IdentityFileFormat::Encrypted => {
+                let passphrase = self
+                    .passphrase
+                    .as_deref()
+                    .ok_or(FabricCoreError::PassphraseRequired)?;
+                let encrypted: EncryptedIdentity = serde_json::from_str(&json)?;
+                encrypted.decrypt(passphrase).map(Some)
+            }
+            IdentityFileFormat::Plaintext => {
+                let entry: X509WalletEntry = serde_json::from_str(&json)?;
+                entry.into_identity(label).map(Some)
+            }
+        }
+    }
+
+    fn remove(&self, label: &str) -> Result<()> {
+        let path = self.entry_path(label)?;
+        if path.is_file() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let mut labels: Vec<String> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == WALLET_FILE_EXTENSION)
+            })
+            .filter_map(|path| {
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+            })
+            .collect();
+        labels.sort();
+        Ok(labels)
+    }
+}
+
+/// Wallet held in memory, for tests and short-lived sessions
+#[derive(Default)]
+pub struct InMemoryWallet {
+    identities: RwLock<HashMap<String, FabricIdentity>>,
+}
+
+impl InMemoryWallet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Wallet for InMemoryWallet {
+    fn put(&self, label: &str, identity: &FabricIdentity) -> Result<()> {
+        validate_label(label)?;
+        self.identities
+            .write()
+            .map_err(lock_err)?
+            .insert(label.to_string(), identity.clone());
+        Ok(())
+    }
+
+    fn get(&self, label: &str) -> Result<Option<FabricIdentity>> {
+        Ok(self.identities.read().map_err(lock_err)?.get(label).cloned())
+    }
+
+    fn remove(&self, label: &str) -> Result<()> {
+        self.identities.write().map_err(lock_err)?.remove(label);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let mut labels: Vec<String> = self
+            .identities
+            .read()
+            .map_err(lock_err)?
+            .keys()
+            .cloned()
+            .collect();
+        labels.sort();
+        Ok(labels)
+    }
+}
+
+fn validate_label(label: &str) -> Result<()> {
+    let valid = !label.is_empty()
+        && label != "."
+        && label != ".."
+        && !label.contains(['/', '\\', '\0']);
+    if valid {
+        Ok(())
+    } else {
+        Err(FabricCoreError::KeyManagementError(format!(
+            "Invalid wallet label: {:?}",
+            label
+        )))
+    }
+}
+
+fn first_entry(cert: &X509, nid: Nid) -> Option<String> {
+    cert.subject_name()
+        .entries_by_nid(nid)
+        .next()
+        .map(|entry| String::from_utf8_lossy(entry.data().as_slice()).to_string())
+}
+
+fn lock_err<T>(_: std::sync::PoisonError<T>) -> FabricCoreError {
+    FabricCoreError::KeyManagementError("Wallet lock poisoned".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{CertSpec, TestCert};
+
+    fn identity() -> FabricIdentity {
+        let root = TestCert::root("ca.org1.example.com");
+        let user = root.issue(CertSpec {
+            common_name: "moderator1",
+            ..Default::default()
+        });
+        FabricIdentity::new(
+            user.key_pem(),
+            user.public_key_pem(),
+            user.cert_pem(),
+            root.cert_pem(),
+            "moderator1".to_string(),
+            "Org1".to_string(),
+            "Org1MSP".to_string(),
+        )
+    }
+
+    fn temp_wallet() -> FileSystemWallet {
+        let dir = std::env::temp_dir().join(format!("wallet-{}", uuid::Uuid::new_v4()));
+        FileSystemWallet::new(&dir).unwrap()
+    }
+
+    #[test]
+    fn test_filesystem_wallet_writes_sdk_format() {
+        let wallet = temp_wallet();
+        let identity = identity();
+        wallet.put("moderator1", &identity).unwrap();
+
+        let json = std::fs::read_to_string(wallet.dir().join("moderator1.id")).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["type"], "X.509");
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["mspId"], "Org1MSP");
+        assert_eq!(value["credentials"]["certificate"], identity.certificate.as_str());
+        assert_eq!(value["credentials"]["privateKey"], identity.private_key.expose_secret().as_str());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let path = wallet.dir().join("moderator1.id");
+            let mode = std::fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let loaded = wallet.require("moderator1").unwrap();
+        assert_eq!(loaded.user_id, "moderator1");
+        assert_eq!(loaded.org_name, "Org1");
+        assert_eq!(loaded.public_key, identity.public_key);
+        assert!(loaded.ca_certificate.is_empty());
+        std::fs::remove_dir_all(wallet.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_filesystem_wallet_reads_sdk_entries() {
+        let wallet = temp_wallet();
+        let identity = identity();
+        // Entry as written by the Node SDK, including key order and spacing
+        let entry = serde_json::json!({
+            "credentials": {
+                "certificate": identity.certificate,
+                "privateKey": identity.private_key.expose_secret(),
+            },
+            "mspId": "Org1MSP",
+            "type": "X.509",
+            "version": 1,
+        });
+        std::fs::write(wallet.dir().join("appUser.id"), entry.to_string()).unwrap();
+        std::fs::write(wallet.dir().join("notes.txt"), "ignored").unwrap();
+
+        assert_eq!(wallet.list().unwrap(), vec!["appUser".to_string()]);
+        let loaded = wallet.get("appUser").unwrap().unwrap();
+        assert_eq!(loaded.user_id, "moderator1");
+        assert_eq!(loaded.private_key, identity.private_key);
+
+        wallet.remove("appUser").unwrap();
+        assert!(wallet.get("appUser").unwrap().is_none());
+        assert!(wallet.list().unwrap().is_empty());
+        std::fs::remove_dir_all(wallet.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_filesystem_wallet_with_passphrase() {
+        let wallet = temp_wallet().with_passphrase("correct horse");
+        let identity = identity();
+        wallet.put("moderator1", &identity).unwrap();
+        assert!(wallet.is_encrypted("moderator1").unwrap());
+
+        let locked = FileSystemWallet::new(wallet.dir()).unwrap();
+        assert!(matches!(
+            locked.get("moderator1"),
+            Err(FabricCoreError::PassphraseRequired)
+        ));
+        let loaded = locked.with_passphrase("correct horse").require("moderator1").unwrap();
+        assert_eq!(loaded.ca_certificate, identity.ca_certificate);
+        assert!(locked.is_encrypted("moderator1").unwrap());
+        std::fs::remove_dir_all(wallet.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_in_memory_wallet() {
+        let wallet = InMemoryWallet::new();
+        wallet.put("b", &identity()).unwrap();
+        wallet.put("a", &identity()).unwrap();
+        assert_eq!(wallet.list().unwrap(), vec!["a".to_string(), "b".to_string()]);
+
+        wallet.remove("a").unwrap();
+        assert!(wallet.get("a").unwrap().is_none());
+        assert!(wallet.require("a").is_err());
+        assert!(wallet.put("../escape", &identity()).is_err());
+    }
+}
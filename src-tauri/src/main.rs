@@ -5,17 +5,31 @@
 )]
 
 use fabric_core::crypto::{CryptoManager, FabricIdentity};
+use fabric_core::events::{EventFilter, FileCheckpointer};
 use fabric_core::fabric::{FabricNetworkClient, KaleidoFabricClient, FabricNetworkConfig};
+use fabric_core::history::HistoryQuery;
+use fabric_core::private_data::PrivateData;
+use fabric_core::tls::TlsConfig;
 use fabric_core::torrent::{TorrentHash, WebTorrentClient, HashType};
+use fabric_core::wallet::{FileSystemWallet, Wallet};
 use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use tokio::sync::Mutex;
-use tauri::State;
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+use tauri::{Manager, State};
 
 // Global state management
 pub struct AppState {
+    wallet: FileSystemWallet,
     identity: Mutex<Option<FabricIdentity>>,
     fabric_client: Mutex<Option<KaleidoFabricClient>>,
     torrent_client: Mutex<Option<WebTorrentClient>>,
+    /// Forwarding tasks of `subscribe_events`, by subscription ID
+    subscriptions: Mutex<HashMap<u64, JoinHandle<()>>>,
+    next_subscription: AtomicU64,
 }
 
 // Tauri Commands for Key Management
@@ -31,39 +45,78 @@ async fn generate_keypair() -> Result<serde_json::Value, String> {
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn list_identities(
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let labels = state.wallet.list().map_err(|e| e.to_string())?;
+    let identities: Vec<_> = labels
+        .iter()
+        .map(|label| {
+            json!({
+                "label": label,
+                "encrypted": state.wallet.is_encrypted(label).unwrap_or(false)
+            })
+        })
+        .collect();
+    Ok(json!({ "identities": identities }))
+}
+
+/// Makes the identity the active one in `AppState` and returns it without
+/// its private key, which stays in the backend
 #[tauri::command]
 async fn load_identity(
-    path: String,
+    label: String,
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
 ) -> Result<serde_json::Value, String> {
-    FabricIdentity::load_from_file(std::path::Path::new(&path))
-        .map(|identity| serde_json::to_value(&identity).unwrap())
-        .map_err(|e| e.to_string())
+    let identity = wallet_identity(&state.wallet, &label, passphrase.as_deref())?;
+    let value = serde_json::to_value(&identity).map_err(|e| e.to_string())?;
+    *state.identity.lock().await = Some(identity);
+    Ok(value)
 }
 
 #[tauri::command]
 async fn save_identity(
-    path: String,
+    label: String,
     identity: serde_json::Value,
+    passphrase: String,
+    state: State<'_, AppState>,
 ) -> Result<(), String> {
     let fabric_id: FabricIdentity =
         serde_json::from_value(identity)
             .map_err(|e| e.to_string())?;
-    fabric_id
-        .save_to_file(std::path::Path::new(&path))
+    state
+        .wallet
+        .with_passphrase(&passphrase)
+        .put(&label, &fabric_id)
         .map_err(|e| e.to_string())
 }
 
+fn wallet_identity(
+    wallet: &FileSystemWallet,
+    label: &str,
+    passphrase: Option<&str>,
+) -> Result<FabricIdentity, String> {
+    let identity = match passphrase {
+        Some(passphrase) => wallet.with_passphrase(passphrase).require(label),
+        None => wallet.require(label),
+    };
+    identity.map_err(|e| e.to_string())
+}
+
 // Tauri Commands for Network Operations
 #[tauri::command]
 async fn connect_network(
     gateway: String,
     ca_url: String,
-    identity_json: serde_json::Value,
+    label: String,
+    passphrase: Option<String>,
+    allow_mspid_mismatch: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<serde_json::Value, String> {
-    let identity: FabricIdentity =
-        serde_json::from_value(identity_json)
-            .map_err(|e| e.to_string())?;
+    let identity =
+        wallet_identity(&state.wallet, &label, passphrase.as_deref())?;
 
     let config = FabricNetworkConfig {
         name: "Kaleido".to_string(),
@@ -72,6 +125,9 @@ async fn connect_network(
         ca_url,
         gateway_url: gateway.clone(),
         tls_cert_path: None,
+        tls: TlsConfig::default(),
+        offline_fallback: false,
+        allow_mspid_mismatch: allow_mspid_mismatch.unwrap_or(false),
     };
 
     let mut client = KaleidoFabricClient::new(config);
@@ -104,7 +160,8 @@ async fn get_channels(
                             "id": ch.id,
                             "name": ch.name,
                             "description": ch.description,
-                            "chaincode_id": ch.chaincode_id
+                            "chaincode_id": ch.chaincode_id,
+                            "chaincodes": ch.chaincodes
                         })
                     })
                     .collect();
@@ -118,6 +175,99 @@ async fn get_channels(
 }
 
 // Tauri Commands for Chaincode Operations
+/// Forward channel events to the window as `fabric-event`, resuming from a
+/// checkpoint in the app data directory
+///
+/// Returns the subscription ID to pass to `unsubscribe_events`.
+#[tauri::command]
+async fn subscribe_events(
+    channel_id: String,
+    filter: EventFilter,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    // Resuming reads the ledger height; don't hold the lock meanwhile
+    let client = state
+        .fabric_client
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "Not connected to network".to_string())?;
+
+    let data_dir = window
+        .app_handle()
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "No app data directory".to_string())?;
+    let name = format!(
+        "{}-{}.json",
+        channel_id,
+        filter.chaincode_id.as_deref().unwrap_or("blocks")
+    );
+    let checkpointer = Arc::new(FileCheckpointer::new(data_dir.join("checkpoints").join(name)));
+
+    let mut events = client
+        .subscribe_events(&channel_id, filter, checkpointer)
+        .await
+        .map_err(|e| e.to_string())?;
+    let task = tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            let sent = match event {
+                Ok(event) => window.emit("fabric-event", event),
+                Err(e) => {
+                    let _ = window.emit("fabric-event-error", e.to_string());
+                    break;
+                }
+            };
+            if sent.is_err() {
+                break;
+            }
+        }
+    });
+
+    let id = state.next_subscription.fetch_add(1, Ordering::Relaxed);
+    let mut subscriptions = state.subscriptions.lock().await;
+    subscriptions.retain(|_, task| !task.is_finished());
+    subscriptions.insert(id, task);
+    Ok(id)
+}
+
+/// Stop a subscription started by `subscribe_events`
+///
+/// Returns false if the ID is unknown or the subscription already ended.
+#[tauri::command]
+async fn unsubscribe_events(subscription_id: u64, state: State<'_, AppState>) -> Result<bool, String> {
+    match state.subscriptions.lock().await.remove(&subscription_id) {
+        Some(task) => {
+            let running = !task.is_finished();
+            task.abort();
+            Ok(running)
+        }
+        None => Ok(false),
+    }
+}
+
+#[tauri::command]
+async fn get_transaction_history(
+    channel_id: String,
+    chaincode_id: String,
+    query: HistoryQuery,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let fabric_client = state.fabric_client.lock().await;
+    if let Some(client) = fabric_client.as_ref() {
+        match client
+            .get_transaction_history(&channel_id, &chaincode_id, &query)
+            .await
+        {
+            Ok(page) => serde_json::to_value(page).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    } else {
+        Err("Not connected to network".to_string())
+    }
+}
+
 #[tauri::command]
 async fn query_chaincode(
     channel_id: String,
@@ -169,6 +319,97 @@ async fn invoke_chaincode(
     }
 }
 
+#[tauri::command]
+async fn query_chaincode_private(
+    channel_id: String,
+    chaincode_id: String,
+    function: String,
+    args: Vec<String>,
+    private: PrivateData,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let fabric_client = state.fabric_client.lock().await;
+    if let Some(client) = fabric_client.as_ref() {
+        client
+            .query_chaincode_private(&channel_id, &chaincode_id, &function, args, &private)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Not connected to network".to_string())
+    }
+}
+
+#[tauri::command]
+async fn invoke_chaincode_private(
+    channel_id: String,
+    chaincode_id: String,
+    function: String,
+    args: Vec<String>,
+    private: PrivateData,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let fabric_client = state.fabric_client.lock().await;
+    if let Some(client) = fabric_client.as_ref() {
+        match client
+            .invoke_chaincode_private(&channel_id, &chaincode_id, &function, args, &private)
+            .await
+        {
+            Ok(result) => serde_json::to_value(result).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    } else {
+        Err("Not connected to network".to_string())
+    }
+}
+
+#[tauri::command]
+async fn submit_chaincode(
+    channel_id: String,
+    chaincode_id: String,
+    function: String,
+    args: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let fabric_client = state.fabric_client.lock().await;
+    if let Some(client) = fabric_client.as_ref() {
+        match client
+            .submit_chaincode(&channel_id, &chaincode_id, &function, args)
+            .await
+        {
+            Ok(result) => serde_json::to_value(result).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    } else {
+        Err("Not connected to network".to_string())
+    }
+}
+
+#[tauri::command]
+async fn wait_for_commit(
+    channel_id: String,
+    transaction_id: String,
+    timeout_secs: u64,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    // Polling can take up to `timeout_secs`; don't hold the lock meanwhile
+    let client = state.fabric_client.lock().await.clone();
+    if let Some(client) = client {
+        match client
+            .wait_for_commit(
+                &channel_id,
+                &transaction_id,
+                std::time::Duration::from_secs(timeout_secs),
+            )
+            .await
+        {
+            Ok(receipt) => serde_json::to_value(receipt).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    } else {
+        Err("Not connected to network".to_string())
+    }
+}
+
 // Tauri Commands for Torrent Operations
 #[tauri::command]
 async fn add_torrent(
@@ -234,22 +475,35 @@ async fn get_torrent_progress(
 }
 
 fn main() {
+    let wallet = FileSystemWallet::new(FileSystemWallet::default_dir())
+        .expect("failed to open identity wallet");
     let app_state = AppState {
+        wallet,
         identity: Mutex::new(None),
         fabric_client: Mutex::new(None),
         torrent_client: Mutex::new(None),
+        subscriptions: Mutex::new(HashMap::new()),
+        next_subscription: AtomicU64::new(1),
     };
 
     tauri::Builder::default()
         .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             generate_keypair,
+            list_identities,
             load_identity,
             save_identity,
             connect_network,
             get_channels,
             query_chaincode,
             invoke_chaincode,
+            query_chaincode_private,
+            invoke_chaincode_private,
+            submit_chaincode,
+            wait_for_commit,
+            get_transaction_history,
+            subscribe_events,
+            unsubscribe_events,
             add_torrent,
             get_torrent_progress
         ])
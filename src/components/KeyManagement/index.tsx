@@ -2,12 +2,14 @@
 
 import React, { useState } from 'react';
 import { generateKeypair, saveIdentity, loadIdentity, getKaleidoConfig } from '@/lib/api';
+import { useAppStore } from '@/lib/store';
 import { Key, Upload } from 'lucide-react';
 
 export default function KeyManagement() {
   const [loading, setLoading] = useState(false);
   const [identity, setIdentity] = useState<any>(null);
   const [error, setError] = useState<string | null>(null);
+  const { setWalletLabel } = useAppStore();
 
   const handleGenerateKey = async () => {
     setLoading(true);
@@ -32,8 +34,11 @@ export default function KeyManagement() {
 
   const handleSaveIdentity = async () => {
     if (!identity) return;
+    const passphrase = window.prompt('Passphrase to encrypt the wallet entry');
+    if (!passphrase) return;
     try {
-      await saveIdentity('/tmp/fabric-identity.json', identity);
+      await saveIdentity(identity.user_id, identity, passphrase);
+      setWalletLabel(identity.user_id);
       alert('Identity saved successfully!');
     } catch (err: any) {
       setError(err.message || 'Failed to save identity');
@@ -20,7 +20,7 @@ export default function NetworkConnection() {
   const [checkingStatus, setCheckingStatus] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const [chaincodes, setChaincodes] = useState<ChaincodeStatus[]>([]);
-  const { connected, setConnected } = useAppStore();
+  const { connected, setConnected, walletLabel, setWalletLabel } = useAppStore();
 
   // Load Kaleido config on mount
   useEffect(() => {
@@ -83,23 +83,19 @@ export default function NetworkConnection() {
   };
 
   const handleConnect = async () => {
+    if (!walletLabel) {
+      setError('Enter the wallet identity to connect with');
+      return;
+    }
     setLoading(true);
     setError(null);
     try {
-      const config = getKaleidoConfig();
-      const mockIdentity = {
-        user_id: 'user1',
-        org_name: config.organization,
-        mspid: config.organization,
-        certificate: 'cert',
-        private_key: 'key',
-        public_key: 'pub',
-        ca_certificate: 'ca_cert',
-      };
+      const passphrase = window.prompt('Wallet passphrase (leave empty if unencrypted)');
       const result = await connectNetwork(
         gateway,
         caUrl,
-        mockIdentity
+        walletLabel,
+        passphrase || undefined
       );
       setConnected(true);
       // Check chaincodes after successful connection
@@ -154,6 +150,20 @@ export default function NetworkConnection() {
             />
           </div>
 
+          <div>
+            <label className="block text-sm font-semibold">
+              Wallet Identity
+            </label>
+            <input
+              type="text"
+              value={walletLabel}
+              onChange={(e) => setWalletLabel(e.target.value)}
+              disabled={connected}
+              className="mt-1 w-full rounded-lg border border-slate-600 bg-slate-700 px-4 py-2 text-white focus:border-cyan-400 focus:outline-none disabled:opacity-50"
+              placeholder="user1"
+            />
+          </div>
+
           {error && (
             <div className="flex items-start gap-2 rounded-lg bg-red-900 p-4 text-red-200">
               <AlertCircle className="mt-0.5 h-5 w-5 flex-shrink-0" />
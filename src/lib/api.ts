@@ -24,12 +24,20 @@ export async function generateKeypair() {
   });
 }
 
-export async function loadIdentity(path: string) {
-  return invoke('load_identity', { path });
+export async function listIdentities() {
+  return invoke('list_identities');
 }
 
-export async function saveIdentity(path: string, identity: any) {
-  return invoke('save_identity', { path, identity });
+export async function loadIdentity(label: string, passphrase?: string) {
+  return invoke('load_identity', { label, passphrase });
+}
+
+export async function saveIdentity(
+  label: string,
+  identity: any,
+  passphrase: string
+) {
+  return invoke('save_identity', { label, identity, passphrase });
 }
 
 /**
@@ -38,7 +46,8 @@ export async function saveIdentity(path: string, identity: any) {
 export async function connectNetwork(
   gateway?: string,
   caUrl?: string,
-  identity?: any
+  label?: string,
+  passphrase?: string
 ) {
   const config = getKaleidoConfig();
   const credentials = getAuthCredentials();
@@ -46,7 +55,8 @@ export async function connectNetwork(
   return invoke('connect_network', {
     gateway: gateway || getGatewayUrl(),
     ca_url: caUrl || getCaUrl(),
-    identity_json: identity,
+    label,
+    passphrase,
     app_id: credentials.username,
     app_password: credentials.password,
     channel_name: config.channelName,
@@ -12,6 +12,8 @@ interface AppStore {
   setConnected: (connected: boolean) => void;
   identity: Identity | null;
   setIdentity: (identity: Identity) => void;
+  walletLabel: string;
+  setWalletLabel: (label: string) => void;
   selectedChannel: string | null;
   setSelectedChannel: (channel: string) => void;
   downloads: Map<string, number>;
@@ -23,6 +25,8 @@ export const useAppStore = create<AppStore>((set) => ({
   setConnected: (connected) => set({ connected }),
   identity: null,
   setIdentity: (identity) => set({ identity }),
+  walletLabel: '',
+  setWalletLabel: (walletLabel) => set({ walletLabel }),
   selectedChannel: null,
   setSelectedChannel: (selectedChannel) => set({ selectedChannel }),
   downloads: new Map(),